@@ -0,0 +1,332 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::cloze;
+use crate::furigana;
+use crate::storage::{Card, Storage};
+
+// Something that can read text out loud
+pub trait SpeechEngine: Send {
+    fn get_name(&self) -> String;
+    fn speak(&self, text: &str) -> Result<(), String>;
+}
+
+// Speaks through the espeak-ng command line program
+pub struct EspeakEngine {
+    voice: String,
+}
+
+impl EspeakEngine {
+    pub fn new(voice: String) -> EspeakEngine {
+        EspeakEngine { voice }
+    }
+
+    pub fn is_available() -> bool {
+        Command::new("espeak-ng")
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok()
+    }
+}
+
+impl SpeechEngine for EspeakEngine {
+    fn get_name(&self) -> String {
+        String::from("espeak-ng")
+    }
+
+    fn speak(&self, text: &str) -> Result<(), String> {
+        let mut child = Command::new("espeak-ng")
+            .arg("-v")
+            .arg(&self.voice)
+            // so text starting with "-" is not taken for an option
+            .arg("--")
+            .arg(text)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| format!("Unable to run espeak-ng: {}", err))?;
+        // reap the process once it is done speaking, without blocking the ui
+        thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
+
+// Speaks nothing, used when no tts program is installed
+pub struct SilentEngine;
+
+impl SpeechEngine for SilentEngine {
+    fn get_name(&self) -> String {
+        String::from("Silent")
+    }
+
+    fn speak(&self, _text: &str) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+static ENGINE: Mutex<Option<Box<dyn SpeechEngine>>> = Mutex::new(None);
+
+// Picks espeak-ng (Japanese voice) when installed
+pub fn get_default_engine() -> Box<dyn SpeechEngine> {
+    if EspeakEngine::is_available() {
+        Box::new(EspeakEngine::new(String::from("ja")))
+    } else {
+        Box::new(SilentEngine)
+    }
+}
+
+pub fn set_engine(engine: Box<dyn SpeechEngine>) {
+    *ENGINE.lock().unwrap() = Some(engine);
+}
+
+pub fn get_engine_name() -> String {
+    match ENGINE.lock().unwrap().as_ref() {
+        Some(engine) => engine.get_name(),
+        None => SilentEngine.get_name(),
+    }
+}
+
+// Speaks card text, reading any furigana instead of the kanji it covers
+pub fn speak(text: &str) -> Result<(), String> {
+    let text = furigana::to_reading(text);
+    if text.trim().is_empty() {
+        return Ok(());
+    }
+    match ENGINE.lock().unwrap().as_ref() {
+        Some(engine) => engine.speak(&text),
+        None => Ok(()),
+    }
+}
+
+// Command line audio players to try, in order, for each platform
+fn get_player_commands(path: &Path) -> Vec<Command> {
+    let mut commands = Vec::new();
+    if cfg!(target_os = "macos") {
+        let mut afplay = Command::new("afplay");
+        afplay.arg(path);
+        commands.push(afplay);
+    } else if cfg!(target_os = "windows") {
+        // quotes in a single quoted powershell string are doubled
+        let script = format!(
+            "(New-Object Media.SoundPlayer '{}').PlaySync()",
+            path.display().to_string().replace('\'', "''")
+        );
+        let mut powershell = Command::new("powershell");
+        powershell.arg("-NoProfile").arg("-Command").arg(script);
+        commands.push(powershell);
+    } else {
+        let mut ffplay = Command::new("ffplay");
+        ffplay
+            .arg("-nodisp")
+            .arg("-autoexit")
+            .arg("-loglevel")
+            .arg("quiet")
+            .arg(path);
+        commands.push(ffplay);
+        let mut paplay = Command::new("paplay");
+        paplay.arg(path);
+        commands.push(paplay);
+        let mut aplay = Command::new("aplay");
+        aplay.arg("-q").arg(path);
+        commands.push(aplay);
+    }
+    commands
+}
+
+pub fn play_file(path: &Path) -> Result<(), String> {
+    if !path.is_file() {
+        return Err(format!("Audio file [{}] not found", path.display()));
+    }
+    for mut command in get_player_commands(path) {
+        let spawned = command.stdout(Stdio::null()).stderr(Stdio::null()).spawn();
+        if let Ok(mut child) = spawned {
+            thread::spawn(move || child.wait());
+            return Ok(());
+        }
+    }
+    Err(String::from("No audio player found"))
+}
+
+// Command line recorders to try, in order, for each platform. Each records from the default
+// microphone into a wav file until it is stopped
+fn get_recorder_commands(path: &Path) -> Vec<Command> {
+    let mut commands = Vec::new();
+    if cfg!(target_os = "macos") {
+        let mut rec = Command::new("rec");
+        rec.arg("-q").arg(path);
+        commands.push(rec);
+        let mut ffmpeg = Command::new("ffmpeg");
+        ffmpeg
+            .arg("-loglevel")
+            .arg("quiet")
+            .arg("-f")
+            .arg("avfoundation")
+            .arg("-i")
+            .arg(":0")
+            .arg(path);
+        commands.push(ffmpeg);
+    } else if cfg!(target_os = "windows") {
+        let mut sox = Command::new("sox");
+        sox.arg("-q")
+            .arg("-t")
+            .arg("waveaudio")
+            .arg("default")
+            .arg(path);
+        commands.push(sox);
+    } else {
+        let mut arecord = Command::new("arecord");
+        arecord.arg("-q").arg("-f").arg("cd").arg(path);
+        commands.push(arecord);
+        let mut rec = Command::new("rec");
+        rec.arg("-q").arg(path);
+        commands.push(rec);
+    }
+    commands
+}
+
+// The recorder running and the file it records into
+static RECORDING: Mutex<Option<(Child, PathBuf)>> = Mutex::new(None);
+
+// Starts recording into `path`. A recording already running is stopped and thrown away
+pub fn start_recording(path: &Path) -> Result<(), String> {
+    if let Ok(unfinished) = stop_recording() {
+        let _ = fs::remove_file(unfinished);
+    }
+    for mut command in get_recorder_commands(path) {
+        let spawned = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        if let Ok(child) = spawned {
+            *RECORDING.lock().unwrap() = Some((child, path.to_path_buf()));
+            return Ok(());
+        }
+    }
+    Err(String::from("No audio recorder found"))
+}
+
+// Stops the recording, returning the file recorded into. A recorder that could not use the
+// microphone leaves no file or an empty one
+pub fn stop_recording() -> Result<PathBuf, String> {
+    let (mut child, path) = match RECORDING.lock().unwrap().take() {
+        Some(recording) => recording,
+        None => return Err(String::from("Not recording")),
+    };
+    let _ = child.kill();
+    let _ = child.wait();
+    match fs::metadata(&path) {
+        Ok(metadata) if metadata.len() > 0 => Ok(path),
+        _ => {
+            let _ = fs::remove_file(&path);
+            Err(String::from("Nothing was recorded, check the microphone"))
+        }
+    }
+}
+
+// Plays the card's own recording if it has one, otherwise speaks its word
+pub fn play_card(set_name: String, card: &Card) {
+    if let Some(file_name) = card.get_audio() {
+        if play_file(&Storage::get_media_path(set_name, file_name)).is_ok() {
+            return;
+        }
+    }
+    let _ = speak(&cloze::fill_deletions(&card.get_word()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    // Remembers what it was asked to speak instead of speaking it
+    struct RecordingEngine {
+        spoken: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl SpeechEngine for RecordingEngine {
+        fn get_name(&self) -> String {
+            String::from("Recording")
+        }
+
+        fn speak(&self, text: &str) -> Result<(), String> {
+            self.spoken.lock().unwrap().push(text.to_string());
+            Ok(())
+        }
+    }
+
+    // The engine is shared by the whole app, so tests using it take turns
+    static ENGINE_IN_USE: Mutex<()> = Mutex::new(());
+
+    fn with_engine(engine: Option<RecordingEngine>, test: impl FnOnce()) {
+        let _guard = ENGINE_IN_USE.lock().unwrap_or_else(|err| err.into_inner());
+        *ENGINE.lock().unwrap() = engine.map(|engine| Box::new(engine) as Box<dyn SpeechEngine>);
+        test();
+        *ENGINE.lock().unwrap() = None;
+    }
+
+    fn recording_engine() -> (RecordingEngine, Arc<Mutex<Vec<String>>>) {
+        let spoken = Arc::new(Mutex::new(Vec::new()));
+        let engine = RecordingEngine {
+            spoken: spoken.clone(),
+        };
+        (engine, spoken)
+    }
+
+    #[test]
+    fn speaks_reading_of_furigana() {
+        let (engine, spoken) = recording_engine();
+        with_engine(Some(engine), || {
+            assert_eq!(get_engine_name(), "Recording");
+            speak("日曜日{にちようび}です").unwrap();
+            speak("  ").unwrap();
+        });
+        assert_eq!(*spoken.lock().unwrap(), vec!["にちようびです"]);
+    }
+
+    #[test]
+    fn replays_card_word_without_recording() {
+        let (engine, spoken) = recording_engine();
+        let card = Card::new(
+            0,
+            String::from("{{c1::わたし}}は学生{がくせい}です"),
+            String::new(),
+            String::new(),
+        );
+        with_engine(Some(engine), || {
+            play_card(String::from("Unit 1"), &card);
+            play_card(String::from("Unit 1"), &card);
+        });
+        assert_eq!(
+            *spoken.lock().unwrap(),
+            vec!["わたしはがくせいです", "わたしはがくせいです"]
+        );
+    }
+
+    #[test]
+    fn speaks_word_when_recording_is_missing() {
+        let (engine, spoken) = recording_engine();
+        let mut card = Card::new(0, String::from("猫"), String::new(), String::new());
+        card.set_audio(Some(String::from("no such recording.mp3")));
+        with_engine(Some(engine), || play_card(String::from("Unit 1"), &card));
+        assert_eq!(*spoken.lock().unwrap(), vec!["猫"]);
+    }
+
+    #[test]
+    fn does_nothing_without_engine() {
+        with_engine(None, || {
+            assert_eq!(get_engine_name(), "Silent");
+            assert_eq!(speak("ねこ"), Ok(()));
+        });
+    }
+
+    #[test]
+    fn stopping_without_recording_fails() {
+        assert_eq!(stop_recording(), Err(String::from("Not recording")));
+    }
+}
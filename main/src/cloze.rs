@@ -0,0 +1,177 @@
+// Cloze markup in card words: "{{c1::わたし}}は学生です" blanks out わたし when studying.
+// A hint can follow the answer, e.g. "{{c1::わたし::I}}", and is shown in the blank.
+// Answers may use furigana, e.g. "{{c1::私{わたし}}}".
+
+use crate::storage::CardType;
+
+#[derive(Clone, Debug, PartialEq)]
+enum ClozePart {
+    Text(String),
+    Deletion {
+        answer: String,
+        hint: Option<String>,
+    },
+}
+
+// byte offset of the "}}" closing a deletion, skipping braces of furigana inside it
+fn find_deletion_end(body: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut chars = body.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            '}' if chars.peek().map(|(_, next)| *next) == Some('}') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+// Markup that is not a complete deletion is kept as plain text
+fn parse_cloze(text: &str) -> Vec<ClozePart> {
+    let mut parts = Vec::new();
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{c") {
+        let after = &rest[start + 3..];
+        let digits = after.chars().take_while(|c| c.is_ascii_digit()).count();
+        let deletion = match after[digits..].strip_prefix("::") {
+            Some(body) if digits > 0 => find_deletion_end(body).map(|end| (body, end)),
+            _ => None,
+        };
+        match deletion {
+            Some((body, end)) => {
+                plain.push_str(&rest[..start]);
+                if !plain.is_empty() {
+                    parts.push(ClozePart::Text(plain.clone()));
+                    plain.clear();
+                }
+                let (answer, hint) = match body[..end].split_once("::") {
+                    Some((answer, hint)) => (answer, Some(hint.to_string())),
+                    None => (&body[..end], None),
+                };
+                parts.push(ClozePart::Deletion {
+                    answer: answer.to_string(),
+                    hint,
+                });
+                rest = &body[end + 2..];
+            }
+            None => {
+                plain.push_str(&rest[..start + 3]);
+                rest = after;
+            }
+        }
+    }
+    plain.push_str(rest);
+    if !plain.is_empty() {
+        parts.push(ClozePart::Text(plain));
+    }
+    parts
+}
+
+pub fn has_deletions(text: &str) -> bool {
+    !get_deletions(text).is_empty()
+}
+
+// Words with cloze markup make cloze cards, see Card::set_card_type
+pub fn get_card_type(word: &str) -> CardType {
+    if has_deletions(word) {
+        CardType::Cloze
+    } else {
+        CardType::Basic
+    }
+}
+
+// The hidden answers, in the order they appear
+pub fn get_deletions(text: &str) -> Vec<String> {
+    parse_cloze(text)
+        .into_iter()
+        .filter_map(|part| match part {
+            ClozePart::Deletion { answer, .. } => Some(answer),
+            ClozePart::Text(_) => None,
+        })
+        .collect()
+}
+
+// "{{c1::わたし}}は学生です" becomes "[...]は学生です"
+pub fn to_blanks(text: &str) -> String {
+    parse_cloze(text)
+        .into_iter()
+        .map(|part| match part {
+            ClozePart::Text(text) => text,
+            ClozePart::Deletion { hint, .. } => {
+                format!("[{}]", hint.unwrap_or(String::from("...")))
+            }
+        })
+        .collect()
+}
+
+// "{{c1::わたし}}は学生です" becomes "わたしは学生です"
+pub fn fill_deletions(text: &str) -> String {
+    parse_cloze(text)
+        .into_iter()
+        .map(|part| match part {
+            ClozePart::Text(text) => text,
+            ClozePart::Deletion { answer, .. } => answer,
+        })
+        .collect()
+}
+
+// Answers for several blanks are typed in order, separated by "、" or ","
+pub fn split_answers(input: &str, num_of_deletions: usize) -> Vec<String> {
+    if num_of_deletions <= 1 {
+        return vec![input.trim().to_string()];
+    }
+    input
+        .split(['、', ','])
+        .map(|answer| answer.trim().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_deletions_with_hints() {
+        let text = "{{c1::わたし::I}}は{{c2::学生}}です";
+        assert_eq!(get_deletions(text), vec!["わたし", "学生"]);
+        assert_eq!(to_blanks(text), "[I]は[...]です");
+        assert_eq!(fill_deletions(text), "わたしは学生です");
+        assert_eq!(get_card_type(text), CardType::Cloze);
+    }
+
+    #[test]
+    fn keeps_furigana_inside_deletion() {
+        let text = "{{c1::私{わたし}}}は学生です";
+        assert_eq!(get_deletions(text), vec!["私{わたし}"]);
+        assert_eq!(to_blanks(text), "[...]は学生です");
+        assert_eq!(fill_deletions(text), "私{わたし}は学生です");
+    }
+
+    #[test]
+    fn keeps_unbalanced_markers_as_text() {
+        for text in [
+            "{{c1::わたしは学生です",
+            "{{c1::私{わたし}は学生です",
+            "{{c::わたし}}は学生です",
+            "{{c1:わたし}}は学生です",
+            "わたし}}は{{学生}}です",
+        ] {
+            assert!(get_deletions(text).is_empty(), "{}", text);
+            assert_eq!(to_blanks(text), text);
+            assert_eq!(get_card_type(text), CardType::Basic);
+        }
+        let text = "{{c1::わたし}}は{{c2::学生です";
+        assert_eq!(get_deletions(text), vec!["わたし"]);
+        assert_eq!(to_blanks(text), "[...]は{{c2::学生です");
+    }
+
+    #[test]
+    fn splits_answers_only_for_several_blanks() {
+        assert_eq!(split_answers(" I, me ", 1), vec!["I, me"]);
+        assert_eq!(split_answers("わたし、 学生", 2), vec!["わたし", "学生"]);
+        assert_eq!(split_answers("I,me,you", 2), vec!["I", "me", "you"]);
+    }
+}
@@ -0,0 +1,138 @@
+// Character level differences between a typed answer and the expected one
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiffKind {
+    // in both answers
+    Same,
+    // typed, but not in the expected answer
+    Extra,
+    // in the expected answer, but not typed
+    Missing,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffSegment {
+    kind: DiffKind,
+    text: String,
+}
+
+impl DiffSegment {
+    pub fn get_kind(&self) -> DiffKind {
+        self.kind
+    }
+
+    pub fn get_text(&self) -> String {
+        self.text.clone()
+    }
+}
+
+// joins runs of the same kind into one segment
+fn push_char(segments: &mut Vec<DiffSegment>, kind: DiffKind, c: char) {
+    if let Some(last) = segments.last_mut() {
+        if last.kind == kind {
+            last.text.push(c);
+            return;
+        }
+    }
+    segments.push(DiffSegment {
+        kind,
+        text: c.to_string(),
+    });
+}
+
+// Keeps the longest run of characters common to both, in order
+pub fn diff_chars(input: &str, expected: &str) -> Vec<DiffSegment> {
+    let typed: Vec<char> = input.chars().collect();
+    let wanted: Vec<char> = expected.chars().collect();
+    // common[i][j] is how many characters typed[i..] and wanted[j..] have in common
+    let mut common = vec![vec![0; wanted.len() + 1]; typed.len() + 1];
+    for i in (0..typed.len()).rev() {
+        for j in (0..wanted.len()).rev() {
+            common[i][j] = if typed[i] == wanted[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let mut segments = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < typed.len() || j < wanted.len() {
+        if i < typed.len() && j < wanted.len() && typed[i] == wanted[j] {
+            push_char(&mut segments, DiffKind::Same, typed[i]);
+            i += 1;
+            j += 1;
+        } else if j < wanted.len() && (i == typed.len() || common[i][j + 1] >= common[i + 1][j]) {
+            push_char(&mut segments, DiffKind::Missing, wanted[j]);
+            j += 1;
+        } else {
+            push_char(&mut segments, DiffKind::Extra, typed[i]);
+            i += 1;
+        }
+    }
+    segments
+}
+
+pub fn count_differences(segments: &[DiffSegment]) -> usize {
+    segments
+        .iter()
+        .filter(|segment| segment.kind != DiffKind::Same)
+        .map(|segment| segment.text.chars().count())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(input: &str, expected: &str) -> Vec<(DiffKind, String)> {
+        diff_chars(input, expected)
+            .iter()
+            .map(|segment| (segment.get_kind(), segment.get_text()))
+            .collect()
+    }
+
+    #[test]
+    fn same_answer_is_one_segment() {
+        assert_eq!(
+            diff("たべる", "たべる"),
+            vec![(DiffKind::Same, String::from("たべる"))]
+        );
+        assert!(diff("", "").is_empty());
+    }
+
+    #[test]
+    fn keeps_longest_common_run_in_order() {
+        let segments = diff_chars("tabemasu", "tabemashita");
+        assert_eq!(
+            diff("tabemasu", "tabemashita"),
+            vec![
+                (DiffKind::Same, String::from("tabemas")),
+                (DiffKind::Missing, String::from("hita")),
+                (DiffKind::Extra, String::from("u")),
+            ]
+        );
+        assert_eq!(count_differences(&segments), 5);
+        assert_eq!(
+            diff("ab", "ba"),
+            vec![
+                (DiffKind::Missing, String::from("b")),
+                (DiffKind::Same, String::from("a")),
+                (DiffKind::Extra, String::from("b")),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_answers_are_all_missing_or_extra() {
+        assert_eq!(
+            diff("", "ねこ"),
+            vec![(DiffKind::Missing, String::from("ねこ"))]
+        );
+        assert_eq!(
+            diff("ねこ", ""),
+            vec![(DiffKind::Extra, String::from("ねこ"))]
+        );
+        assert_eq!(count_differences(&diff_chars("", "ねこ")), 2);
+    }
+}
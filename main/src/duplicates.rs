@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+
+use crate::furigana;
+use crate::kana;
+use crate::search::CardField;
+use crate::storage::{Card, Catalogue, Storage, StudySet};
+
+// Characters ignored when comparing, e.g. "(polite)" vs "（polite）"
+const IGNORED_CHARS: &str = " 　・、。,.!?！？()（）[]「」『』~〜/";
+
+// Texts that only differ by case, spacing, punctuation, kana script or furigana share a key
+pub fn get_comparable_key(text: &str) -> String {
+    let key = kana::to_comparable_kana(&furigana::strip_ruby(&text.to_lowercase()));
+    key.chars()
+        .filter(|c| !IGNORED_CHARS.contains(*c))
+        .collect()
+}
+
+// Cards with the same key count as duplicates when merging sets
+pub fn get_card_key(card: &Card) -> (String, String) {
+    (
+        get_comparable_key(&card.get_word()),
+        get_comparable_key(&card.get_ans()),
+    )
+}
+
+#[derive(Clone, Debug)]
+pub struct CardRef {
+    set_name: String,
+    card: Card,
+}
+
+impl CardRef {
+    pub fn get_set_name(&self) -> String {
+        self.set_name.clone()
+    }
+
+    pub fn get_card(&self) -> Card {
+        self.card.clone()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DuplicateGroup {
+    field: CardField,
+    key: String,
+    cards: Vec<CardRef>,
+}
+
+impl DuplicateGroup {
+    // stable across rescans, used to remember groups the user chose to keep
+    pub fn get_id(&self) -> String {
+        format!("{}:{}", self.field.get_label(), self.key)
+    }
+
+    pub fn get_field(&self) -> CardField {
+        self.field
+    }
+
+    pub fn get_cards(&self) -> Vec<CardRef> {
+        self.cards.clone()
+    }
+}
+
+pub struct DuplicateFinder {
+    by_word: HashMap<String, Vec<CardRef>>,
+    by_ans: HashMap<String, Vec<CardRef>>,
+}
+
+impl DuplicateFinder {
+    pub fn new(study_sets: Vec<StudySet>) -> DuplicateFinder {
+        let mut by_word: HashMap<String, Vec<CardRef>> = HashMap::new();
+        let mut by_ans: HashMap<String, Vec<CardRef>> = HashMap::new();
+        for study_set in study_sets {
+            for card in study_set.get_all_cards() {
+                let card_ref = CardRef {
+                    set_name: study_set.get_set_name(),
+                    card: card.clone(),
+                };
+                let word_key = get_comparable_key(&card.get_word());
+                if !word_key.is_empty() {
+                    by_word.entry(word_key).or_default().push(card_ref.clone());
+                }
+                let ans_key = get_comparable_key(&card.get_ans());
+                if !ans_key.is_empty() {
+                    by_ans.entry(ans_key).or_default().push(card_ref);
+                }
+            }
+        }
+        DuplicateFinder { by_word, by_ans }
+    }
+
+    pub fn get_duplicate_groups(&self) -> Vec<DuplicateGroup> {
+        let mut res = Vec::new();
+        for (field, cards_by_key) in [
+            (CardField::Word, &self.by_word),
+            (CardField::Answer, &self.by_ans),
+        ] {
+            for (key, cards) in cards_by_key {
+                if cards.len() > 1 {
+                    res.push(DuplicateGroup {
+                        field,
+                        key: key.clone(),
+                        cards: cards.clone(),
+                    });
+                }
+            }
+        }
+        res.sort_by_key(|group| group.get_id());
+        res
+    }
+
+    // Existing cards sharing the word or answer of a card about to be added
+    pub fn get_existing_cards(&self, word: String, ans: String) -> Vec<(CardField, CardRef)> {
+        let mut res = Vec::new();
+        for (field, text, cards_by_key) in [
+            (CardField::Word, word, &self.by_word),
+            (CardField::Answer, ans, &self.by_ans),
+        ] {
+            if let Some(cards) = cards_by_key.get(&get_comparable_key(&text)) {
+                for card_ref in cards {
+                    res.push((field, card_ref.clone()));
+                }
+            }
+        }
+        res
+    }
+}
+
+fn save_set(catalogue: &mut Catalogue, study_set: StudySet) {
+    if let Some(set_id) = catalogue.get_id_by_name(study_set.get_set_name()) {
+        catalogue.update_set(set_id, study_set);
+    }
+}
+
+// Removes cards, highest id first so the remaining ids stay valid. Returns the names of the
+// sets changed, whose card positions have moved
+pub fn delete_cards(catalogue: &mut Catalogue, to_delete: Vec<CardRef>) -> Vec<String> {
+    let mut ids_by_set: HashMap<String, Vec<usize>> = HashMap::new();
+    for card_ref in to_delete {
+        ids_by_set
+            .entry(card_ref.get_set_name())
+            .or_default()
+            .push(card_ref.get_card().get_id());
+    }
+    let mut changed_sets: Vec<String> = Vec::new();
+    for (set_name, mut card_ids) in ids_by_set {
+        let mut target_set = match Storage::read_set_file(set_name.clone()) {
+            Ok(target_set) => target_set,
+            Err(_) => continue,
+        };
+        card_ids.sort();
+        card_ids.dedup();
+        for card_id in card_ids.into_iter().rev() {
+            if card_id < target_set.get_num_of_cards() {
+                target_set.delete_card(card_id);
+            }
+        }
+        save_set(catalogue, target_set);
+        changed_sets.push(set_name);
+    }
+    Storage::update_inventory(catalogue.clone());
+    changed_sets
+}
+
+// Keeps `target` with the remarks and card tags of the other cards added, and deletes the others.
+// Returns the names of the sets changed, see delete_cards
+pub fn merge_cards(
+    catalogue: &mut Catalogue,
+    target: CardRef,
+    others: Vec<CardRef>,
+) -> Vec<String> {
+    let target_card = target.get_card();
+    let mut remarks: Vec<String> = Vec::new();
+    let mut tags: Vec<String> = Vec::new();
+    for card_ref in [target.clone()].iter().chain(others.iter()) {
+        let remark = card_ref.get_card().get_remarks().trim().to_string();
+        if !remark.is_empty() && !remarks.contains(&remark) {
+            remarks.push(remark);
+        }
+        for tag in card_ref.get_card().get_tags() {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    let mut target_set = match Storage::read_set_file(target.get_set_name()) {
+        Ok(target_set) => target_set,
+        Err(_) => return Vec::new(),
+    };
+    let mut merged_card = Card::new(
+        target_card.get_id(),
+        target_card.get_word(),
+        target_card.get_ans(),
+        remarks.join("; "),
+    );
+    merged_card.set_card_type(target_card.get_card_type());
+    merged_card.set_audio(target_card.get_audio());
+    merged_card.set_image(target_card.get_image());
+    merged_card.set_extra_fields(target_card.get_extra_fields());
+    merged_card.set_tags(tags);
+    merged_card.set_flags(target_card.get_flags());
+    target_set.replace_card(target_card.get_id(), merged_card);
+    save_set(catalogue, target_set);
+    let mut changed_sets = delete_cards(catalogue, others);
+    if !changed_sets.contains(&target.get_set_name()) {
+        changed_sets.push(target.get_set_name());
+    }
+    changed_sets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_set(name: &str, cards: &[(&str, &str)]) -> StudySet {
+        let mut study_set = StudySet::new(0, name.to_string());
+        for (word, ans) in cards {
+            study_set.add_card(Card::new(
+                study_set.get_num_of_cards(),
+                word.to_string(),
+                ans.to_string(),
+                String::new(),
+            ));
+        }
+        study_set
+    }
+
+    fn get_words(cards: &[CardRef]) -> Vec<(String, String)> {
+        cards
+            .iter()
+            .map(|card_ref| (card_ref.get_set_name(), card_ref.get_card().get_word()))
+            .collect()
+    }
+
+    #[test]
+    fn comparable_key_ignores_case_spacing_script_and_furigana() {
+        assert_eq!(
+            get_comparable_key("To Eat (polite)"),
+            get_comparable_key("to eat（polite）")
+        );
+        assert_eq!(get_comparable_key("ネコ"), "ねこ");
+        assert_eq!(get_comparable_key("neko"), "ねこ");
+        assert_eq!(get_comparable_key("日曜日{にちようび}"), "日曜日");
+        assert_eq!(get_comparable_key(" ・。 "), "");
+    }
+
+    #[test]
+    fn groups_cards_sharing_word_or_answer_across_sets() {
+        let finder = DuplicateFinder::new(vec![
+            make_set("Lesson 1", &[("ねこ", "cat"), ("いぬ", "dog")]),
+            make_set(
+                "Lesson 2",
+                &[("ネコ", "Cat!"), ("とり", "bird"), ("。", "")],
+            ),
+        ]);
+        let groups = finder.get_duplicate_groups();
+        let ids: Vec<String> = groups.iter().map(|group| group.get_id()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                format!("Answer:{}", get_comparable_key("cat")),
+                String::from("Word:ねこ")
+            ]
+        );
+        assert_eq!(groups[1].get_field(), CardField::Word);
+        assert_eq!(
+            get_words(&groups[1].get_cards()),
+            vec![
+                (String::from("Lesson 1"), String::from("ねこ")),
+                (String::from("Lesson 2"), String::from("ネコ")),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_existing_cards_for_new_word() {
+        let finder = DuplicateFinder::new(vec![make_set(
+            "Lesson 1",
+            &[("ねこ", "cat"), ("こねこ", "kitten")],
+        )]);
+        let existing = finder.get_existing_cards(String::from("ネコ"), String::from("kitten"));
+        let fields: Vec<(CardField, String)> = existing
+            .iter()
+            .map(|(field, card_ref)| (*field, card_ref.get_card().get_word()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                (CardField::Word, String::from("ねこ")),
+                (CardField::Answer, String::from("こねこ")),
+            ]
+        );
+        assert!(finder
+            .get_existing_cards(String::from("いぬ"), String::new())
+            .is_empty());
+    }
+
+    #[test]
+    fn card_key_compares_word_and_answer() {
+        let card = Card::new(0, String::from("ネコ"), String::from("Cat"), String::new());
+        let same = Card::new(
+            1,
+            String::from("ねこ"),
+            String::from("cat"),
+            String::from("x"),
+        );
+        let other = Card::new(
+            2,
+            String::from("ねこ"),
+            String::from("kitten"),
+            String::new(),
+        );
+        assert_eq!(get_card_key(&card), get_card_key(&same));
+        assert_ne!(get_card_key(&card), get_card_key(&other));
+    }
+}
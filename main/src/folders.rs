@@ -0,0 +1,106 @@
+// Study sets can be kept in folders inside the data folder, e.g. the set "Lesson 1" in the
+// folder "LAJ 1/Lessons" is saved as "./data/LAJ 1/Lessons/Lesson 1.json".
+// The data folder itself is the top level folder "".
+
+use crate::storage::{Catalogue, ListItem, Storage, FOLDER_SEPARATOR};
+
+// " LAJ 1 / Lessons/ " becomes "LAJ 1/Lessons"
+pub fn normalize_folder(folder: &str) -> Result<String, String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in folder
+        .split([FOLDER_SEPARATOR, '\\'])
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+    {
+        // media folders and set files share the data folder with the set folders
+        if part == "." || part == ".." || part.ends_with(".media") || part.ends_with(".json") {
+            return Err(format!("[{}] cannot be used as a folder name", part));
+        }
+        parts.push(part);
+    }
+    Ok(parts.join(&FOLDER_SEPARATOR.to_string()))
+}
+
+pub fn join_folder(folder: &str, name: &str) -> String {
+    if folder.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}{}{}", folder, FOLDER_SEPARATOR, name)
+    }
+}
+
+// Whether the folder is the ancestor itself or anywhere inside it
+pub fn is_in_folder(folder: &str, ancestor: &str) -> bool {
+    ancestor.is_empty()
+        || folder == ancestor
+        || folder
+            .strip_prefix(ancestor)
+            .is_some_and(|rest| rest.starts_with(FOLDER_SEPARATOR))
+}
+
+pub fn get_parent(folder: &str) -> String {
+    folder
+        .rsplit_once(FOLDER_SEPARATOR)
+        .map_or(String::new(), |(parent, _)| parent.to_string())
+}
+
+// "LAJ 1/Lessons" gives "Lessons"
+pub fn get_folder_name(folder: &str) -> String {
+    folder
+        .rsplit(FOLDER_SEPARATOR)
+        .next()
+        .unwrap_or(folder)
+        .to_string()
+}
+
+// e.g. "LAJ 1 / Lessons", or "Top Level" for the data folder
+pub fn get_display_name(folder: &str) -> String {
+    if folder.is_empty() {
+        String::from("Top Level")
+    } else {
+        folder.replace(FOLDER_SEPARATOR, " / ")
+    }
+}
+
+// The folders directly inside the given one
+pub fn get_subfolders(folders: &[String], folder: &str) -> Vec<String> {
+    folders
+        .iter()
+        .filter(|sub| !sub.is_empty() && get_parent(sub) == folder)
+        .cloned()
+        .collect()
+}
+
+// Moving "LAJ 1" to "Old/LAJ 1" turns "LAJ 1/Lessons" into "Old/LAJ 1/Lessons"
+pub fn move_folder(folder: &str, from: &str, to: &str) -> String {
+    if is_in_folder(folder, from) && !from.is_empty() {
+        format!("{}{}", to, &folder[from.len()..])
+    } else {
+        folder.to_string()
+    }
+}
+
+// Sets inside the folder or any of its subfolders
+pub fn get_items_under_folder(catalogue: &Catalogue, folder: &str) -> Vec<ListItem> {
+    catalogue
+        .get_inventory()
+        .into_iter()
+        .filter(|item| is_in_folder(&item.get_folder(), folder))
+        .collect()
+}
+
+// Moves the folder with everything in it, e.g. to rename it
+pub fn move_folder_with_sets(
+    catalogue: &mut Catalogue,
+    from: &str,
+    to: &str,
+) -> Result<(), String> {
+    if is_in_folder(to, from) {
+        return Err(format!("[{}] cannot be moved into itself", from));
+    }
+    Storage::move_folder(from, to)?;
+    for item in get_items_under_folder(catalogue, from) {
+        catalogue.set_item_folder(item.get_id(), move_folder(&item.get_folder(), from, to));
+    }
+    Ok(())
+}
@@ -0,0 +1,171 @@
+// Ruby markup in card text: "日曜日{にちようび}" shows にちようび above 日曜日.
+// The reading covers the kanji right before it, or everything after a "|",
+// e.g. "|お茶{おちゃ}".
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RubySegment {
+    base: String,
+    reading: Option<String>,
+}
+
+impl RubySegment {
+    pub fn get_base(&self) -> String {
+        self.base.clone()
+    }
+
+    pub fn get_reading(&self) -> Option<String> {
+        self.reading.clone()
+    }
+}
+
+fn is_kanji(c: char) -> bool {
+    ('\u{4E00}'..='\u{9FFF}').contains(&c)
+        || ('\u{3400}'..='\u{4DBF}').contains(&c)
+        || "々〆ヶ".contains(c)
+}
+
+fn push_plain(segments: &mut Vec<RubySegment>, text: &str) {
+    if !text.is_empty() {
+        segments.push(RubySegment {
+            base: text.to_string(),
+            reading: None,
+        });
+    }
+}
+
+// Text without markup, or with unmatched braces, comes back as plain segments
+pub fn parse_ruby(text: &str) -> Vec<RubySegment> {
+    let mut segments = Vec::new();
+    let mut plain = String::new();
+    // byte offset in `plain` right after the last "|"
+    let mut base_start: Option<usize> = None;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        if c == '|' {
+            plain.push(c);
+            base_start = Some(plain.len());
+            continue;
+        }
+        let close = rest.find('}');
+        if c != '{' || close.is_none() {
+            plain.push(c);
+            continue;
+        }
+        let close = close.unwrap();
+        let reading = rest[..close].trim().to_string();
+        let (before, base) = match base_start {
+            Some(start) => (plain[..start - 1].to_string(), plain[start..].to_string()),
+            None => {
+                let kanji_len: usize = plain
+                    .chars()
+                    .rev()
+                    .take_while(|c| is_kanji(*c))
+                    .map(|c| c.len_utf8())
+                    .sum();
+                let split = plain.len() - kanji_len;
+                (plain[..split].to_string(), plain[split..].to_string())
+            }
+        };
+        if base.is_empty() || reading.is_empty() {
+            plain.push(c);
+            continue;
+        }
+        push_plain(&mut segments, &before);
+        segments.push(RubySegment {
+            base,
+            reading: Some(reading),
+        });
+        plain.clear();
+        base_start = None;
+        rest = &rest[close + 1..];
+    }
+    push_plain(&mut segments, &plain);
+    segments
+}
+
+pub fn has_ruby(text: &str) -> bool {
+    parse_ruby(text)
+        .iter()
+        .any(|segment| segment.reading.is_some())
+}
+
+// Text as it reads without furigana, e.g. "日曜日{にちようび}" gives "日曜日"
+pub fn strip_ruby(text: &str) -> String {
+    parse_ruby(text)
+        .iter()
+        .map(|segment| segment.base.clone())
+        .collect()
+}
+
+// Text with every annotated part replaced by its reading, e.g. "にちようび"
+pub fn to_reading(text: &str) -> String {
+    parse_ruby(text)
+        .iter()
+        .map(|segment| segment.reading.clone().unwrap_or(segment.base.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments(text: &str) -> Vec<(String, Option<String>)> {
+        parse_ruby(text)
+            .iter()
+            .map(|segment| (segment.get_base(), segment.get_reading()))
+            .collect()
+    }
+
+    #[test]
+    fn reading_covers_kanji_before_it() {
+        assert_eq!(
+            segments("毎日曜日{にちようび}に"),
+            vec![
+                (String::from("毎日曜日"), Some(String::from("にちようび"))),
+                (String::from("に"), None),
+            ]
+        );
+        assert_eq!(
+            segments("お茶{ちゃ}を飲{の}む"),
+            vec![
+                (String::from("お"), None),
+                (String::from("茶"), Some(String::from("ちゃ"))),
+                (String::from("を"), None),
+                (String::from("飲"), Some(String::from("の"))),
+                (String::from("む"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn bar_marks_start_of_base() {
+        assert_eq!(
+            segments("これは|お茶{おちゃ}"),
+            vec![
+                (String::from("これは"), None),
+                (String::from("お茶"), Some(String::from("おちゃ"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_markup_without_base_or_reading_as_text() {
+        assert_eq!(
+            segments("ねこ{neko}"),
+            vec![(String::from("ねこ{neko}"), None)]
+        );
+        assert_eq!(segments("猫{ }"), vec![(String::from("猫{ }"), None)]);
+        assert_eq!(segments("猫{ねこ"), vec![(String::from("猫{ねこ"), None)]);
+        assert!(!has_ruby("猫{ねこ"));
+        assert!(has_ruby("猫{ねこ}"));
+    }
+
+    #[test]
+    fn strips_or_reads_annotations() {
+        let text = "日曜日{にちようび}は|お茶{おちゃ}";
+        assert_eq!(strip_ruby(text), "日曜日はお茶");
+        assert_eq!(to_reading(text), "にちようびはおちゃ");
+        assert_eq!(strip_ruby("plain text"), "plain text");
+    }
+}
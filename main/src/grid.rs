@@ -0,0 +1,166 @@
+// The grid editor shows every card of a set as a row of text cells, one per field.
+// Unlike the edit word page, an emptied cell clears the field when saved.
+
+use crate::cloze;
+use crate::schema::FieldSchema;
+use crate::storage::{Card, StudySet};
+
+// Every field of the schema, and word, answer and remarks even if the schema has fewer,
+// plus any extra field values kept from fields removed from the schema
+pub fn get_num_of_columns(study_set: &StudySet) -> usize {
+    study_set
+        .get_all_cards()
+        .iter()
+        .map(|card| 3 + card.get_extra_fields().len())
+        .chain([3, study_set.get_schema().get_num_of_fields()])
+        .max()
+        .unwrap_or(3)
+}
+
+pub fn get_column_name(schema: &FieldSchema, column: usize) -> String {
+    match schema.get_field_name(column) {
+        name if name.is_empty() => format!("Field {}", column + 1),
+        name => name,
+    }
+}
+
+pub fn get_rows(study_set: &StudySet) -> Vec<Vec<String>> {
+    let num_of_columns = get_num_of_columns(study_set);
+    study_set
+        .get_all_cards()
+        .iter()
+        .map(|card| {
+            (0..num_of_columns)
+                .map(|column| card.get_field(column))
+                .collect()
+        })
+        .collect()
+}
+
+// Rows copied from a spreadsheet: cells separated by tabs, rows by new lines, and cells with
+// tabs, new lines or quotes in them quoted with '"', quotes inside doubled
+pub fn parse_table(text: &str) -> Vec<Vec<String>> {
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut row: Vec<String> = Vec::new();
+    let mut cell = String::new();
+    let mut chars = text.chars().peekable();
+    let mut is_quoted = false;
+    while let Some(c) = chars.next() {
+        if is_quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    cell.push('"');
+                }
+                '"' => is_quoted = false,
+                _ => cell.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if cell.is_empty() => is_quoted = true,
+            '\t' => row.push(std::mem::take(&mut cell)),
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => cell.push(c),
+        }
+    }
+    if !cell.is_empty() || !row.is_empty() {
+        row.push(cell);
+        rows.push(row);
+    }
+    rows.retain(|row| row.iter().any(|cell| !cell.trim().is_empty()));
+    rows
+}
+
+// The cards the rows describe, in order. Rows from a card keep its audio, image, tags and
+// flags, rows with no card become new cards, and blank rows are left out
+pub fn build_cards(
+    study_set: &StudySet,
+    rows: &[Vec<String>],
+    card_ids: &[Option<usize>],
+) -> Result<Vec<Card>, String> {
+    let schema = study_set.get_schema();
+    let mut cards: Vec<Card> = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        if row.iter().all(|cell| cell.trim().is_empty()) {
+            continue;
+        }
+        let cell = |column: usize| {
+            row.get(column)
+                .map_or(String::new(), |cell| cell.trim().to_string())
+        };
+        if cell(0).is_empty() {
+            return Err(format!(
+                "Row {} needs a {}",
+                index + 1,
+                get_column_name(&schema, 0)
+            ));
+        }
+        let mut card = Card::new(cards.len(), cell(0), cell(1), cell(2));
+        card.set_card_type(cloze::get_card_type(&cell(0)));
+        let mut extra_fields: Vec<String> = (3..row.len()).map(cell).collect();
+        // fields past the schema are only kept while they have a value
+        while extra_fields.len() + 3 > schema.get_num_of_fields()
+            && extra_fields.last().is_some_and(|field| field.is_empty())
+        {
+            extra_fields.pop();
+        }
+        card.set_extra_fields(extra_fields);
+        let card_id = card_ids.get(index).copied().flatten();
+        if let Some(card_id) = card_id.filter(|card_id| *card_id < study_set.get_num_of_cards()) {
+            let curr_card = study_set.get_card(card_id);
+            card.set_audio(curr_card.get_audio());
+            card.set_image(curr_card.get_image());
+            card.set_tags(curr_card.get_tags());
+            card.set_flags(curr_card.get_flags());
+        }
+        cards.push(card);
+    }
+    Ok(cards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn splits_cells_and_rows() {
+        assert_eq!(
+            parse_table("ねこ\tcat\t\r\nいぬ\tdog\tpet\n"),
+            table(&[&["ねこ", "cat", ""], &["いぬ", "dog", "pet"]])
+        );
+        assert_eq!(parse_table("ねこ"), table(&[&["ねこ"]]));
+    }
+
+    #[test]
+    fn reads_quoted_cells() {
+        assert_eq!(
+            parse_table("\"a\tb\"\t\"line 1\nline 2\"\t\"say \"\"hi\"\"\"\n"),
+            table(&[&["a\tb", "line 1\nline 2", "say \"hi\""]])
+        );
+        // quotes not at the start of a cell are kept as they are
+        assert_eq!(
+            parse_table("5\" floppy\tdisk"),
+            table(&[&["5\" floppy", "disk"]])
+        );
+    }
+
+    #[test]
+    fn leaves_out_blank_rows() {
+        assert_eq!(
+            parse_table("\n\t \nねこ\tcat\n\n"),
+            table(&[&["ねこ", "cat"]])
+        );
+        assert!(parse_table("").is_empty());
+    }
+}
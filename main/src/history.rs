@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+
+use crate::storage::{get_unix_time, StudyMode};
+
+// How well a card was known: picked by the user on flashcards, or from a typed answer
+// (Good if correct, Again if not)
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Grade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl Grade {
+    pub fn get_all() -> Vec<Grade> {
+        vec![Grade::Again, Grade::Hard, Grade::Good, Grade::Easy]
+    }
+
+    pub fn get_label(&self) -> String {
+        match self {
+            Grade::Again => String::from("Again"),
+            Grade::Hard => String::from("Hard"),
+            Grade::Good => String::from("Good"),
+            Grade::Easy => String::from("Easy"),
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Grade> {
+        Grade::get_all()
+            .into_iter()
+            .find(|grade| grade.get_label() == label)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CardReview {
+    card_id: usize,
+    // kept so the review can still be matched after cards are reordered or deleted
+    word: String,
+    grade: Grade,
+}
+
+impl CardReview {
+    pub fn new(card_id: usize, word: String, grade: Grade) -> CardReview {
+        CardReview {
+            card_id,
+            word,
+            grade,
+        }
+    }
+}
+
+// One finished round of study on a set
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StudyRecord {
+    mode: StudyMode,
+    // seconds since the unix epoch
+    finished_at: u64,
+    reviews: Vec<CardReview>,
+}
+
+impl StudyRecord {
+    pub fn new(mode: StudyMode, reviews: Vec<CardReview>) -> StudyRecord {
+        StudyRecord {
+            mode,
+            finished_at: get_unix_time(),
+            reviews,
+        }
+    }
+}
+
+pub fn get_last_studied(history: &[StudyRecord]) -> Option<u64> {
+    history.iter().map(|record| record.finished_at).max()
+}
+
+// Share of reviews in every round that were not graded Again
+pub fn get_accuracy(history: &[StudyRecord]) -> Option<f64> {
+    let reviews: Vec<&CardReview> = history
+        .iter()
+        .flat_map(|record| record.reviews.iter())
+        .collect();
+    if reviews.is_empty() {
+        return None;
+    }
+    let num_correct = reviews
+        .iter()
+        .filter(|review| review.grade != Grade::Again)
+        .count();
+    Some(num_correct as f64 / reviews.len() as f64)
+}
+
+// What the study sets list shows and sorts by, worked out once from a set's history
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SetStats {
+    last_studied: Option<u64>,
+    accuracy: Option<f64>,
+}
+
+impl SetStats {
+    pub fn new(history: &[StudyRecord]) -> SetStats {
+        SetStats {
+            last_studied: get_last_studied(history),
+            accuracy: get_accuracy(history),
+        }
+    }
+
+    pub fn get_last_studied(&self) -> Option<u64> {
+        self.last_studied
+    }
+
+    pub fn get_accuracy(&self) -> Option<f64> {
+        self.accuracy
+    }
+}
+
+// e.g. "today", "yesterday" or "12 days ago"
+pub fn format_days_ago(unix_time: u64) -> String {
+    match get_unix_time().saturating_sub(unix_time) / (24 * 60 * 60) {
+        0 => String::from("today"),
+        1 => String::from("yesterday"),
+        days => format!("{} days ago", days),
+    }
+}
@@ -0,0 +1,414 @@
+use serde::{Deserialize, Serialize};
+
+// Romaji and kana conversion, following the usual IME spellings
+const ROMAJI_TABLE: &[(&str, &str)] = &[
+    ("a", "あ"),
+    ("i", "い"),
+    ("u", "う"),
+    ("e", "え"),
+    ("o", "お"),
+    ("ka", "か"),
+    ("ki", "き"),
+    ("ku", "く"),
+    ("ke", "け"),
+    ("ko", "こ"),
+    ("ca", "か"),
+    ("cu", "く"),
+    ("co", "こ"),
+    ("ga", "が"),
+    ("gi", "ぎ"),
+    ("gu", "ぐ"),
+    ("ge", "げ"),
+    ("go", "ご"),
+    ("sa", "さ"),
+    ("shi", "し"),
+    ("si", "し"),
+    ("su", "す"),
+    ("se", "せ"),
+    ("so", "そ"),
+    ("za", "ざ"),
+    ("ji", "じ"),
+    ("zi", "じ"),
+    ("zu", "ず"),
+    ("ze", "ぜ"),
+    ("zo", "ぞ"),
+    ("ta", "た"),
+    ("chi", "ち"),
+    ("ti", "ち"),
+    ("tsu", "つ"),
+    ("tu", "つ"),
+    ("te", "て"),
+    ("to", "と"),
+    ("da", "だ"),
+    ("di", "ぢ"),
+    ("du", "づ"),
+    ("de", "で"),
+    ("do", "ど"),
+    ("na", "な"),
+    ("ni", "に"),
+    ("nu", "ぬ"),
+    ("ne", "ね"),
+    ("no", "の"),
+    ("ha", "は"),
+    ("hi", "ひ"),
+    ("fu", "ふ"),
+    ("hu", "ふ"),
+    ("he", "へ"),
+    ("ho", "ほ"),
+    ("ba", "ば"),
+    ("bi", "び"),
+    ("bu", "ぶ"),
+    ("be", "べ"),
+    ("bo", "ぼ"),
+    ("pa", "ぱ"),
+    ("pi", "ぴ"),
+    ("pu", "ぷ"),
+    ("pe", "ぺ"),
+    ("po", "ぽ"),
+    ("ma", "ま"),
+    ("mi", "み"),
+    ("mu", "む"),
+    ("me", "め"),
+    ("mo", "も"),
+    ("ya", "や"),
+    ("yu", "ゆ"),
+    ("yo", "よ"),
+    ("ra", "ら"),
+    ("ri", "り"),
+    ("ru", "る"),
+    ("re", "れ"),
+    ("ro", "ろ"),
+    ("la", "ら"),
+    ("li", "り"),
+    ("lu", "る"),
+    ("le", "れ"),
+    ("lo", "ろ"),
+    ("wa", "わ"),
+    ("wi", "うぃ"),
+    ("we", "うぇ"),
+    ("wo", "を"),
+    ("vu", "ゔ"),
+    ("va", "ゔぁ"),
+    ("vi", "ゔぃ"),
+    ("ve", "ゔぇ"),
+    ("vo", "ゔぉ"),
+    ("kya", "きゃ"),
+    ("kyu", "きゅ"),
+    ("kyo", "きょ"),
+    ("gya", "ぎゃ"),
+    ("gyu", "ぎゅ"),
+    ("gyo", "ぎょ"),
+    ("sha", "しゃ"),
+    ("shu", "しゅ"),
+    ("she", "しぇ"),
+    ("sho", "しょ"),
+    ("sya", "しゃ"),
+    ("syu", "しゅ"),
+    ("syo", "しょ"),
+    ("ja", "じゃ"),
+    ("ju", "じゅ"),
+    ("je", "じぇ"),
+    ("jo", "じょ"),
+    ("jya", "じゃ"),
+    ("jyu", "じゅ"),
+    ("jyo", "じょ"),
+    ("zya", "じゃ"),
+    ("zyu", "じゅ"),
+    ("zyo", "じょ"),
+    ("cha", "ちゃ"),
+    ("chu", "ちゅ"),
+    ("che", "ちぇ"),
+    ("cho", "ちょ"),
+    ("tya", "ちゃ"),
+    ("tyu", "ちゅ"),
+    ("tyo", "ちょ"),
+    ("cya", "ちゃ"),
+    ("cyu", "ちゅ"),
+    ("cyo", "ちょ"),
+    ("dya", "ぢゃ"),
+    ("dyu", "ぢゅ"),
+    ("dyo", "ぢょ"),
+    ("thi", "てぃ"),
+    ("dhi", "でぃ"),
+    ("twu", "とぅ"),
+    ("dwu", "どぅ"),
+    ("nya", "にゃ"),
+    ("nyu", "にゅ"),
+    ("nyo", "にょ"),
+    ("hya", "ひゃ"),
+    ("hyu", "ひゅ"),
+    ("hyo", "ひょ"),
+    ("bya", "びゃ"),
+    ("byu", "びゅ"),
+    ("byo", "びょ"),
+    ("pya", "ぴゃ"),
+    ("pyu", "ぴゅ"),
+    ("pyo", "ぴょ"),
+    ("mya", "みゃ"),
+    ("myu", "みゅ"),
+    ("myo", "みょ"),
+    ("rya", "りゃ"),
+    ("ryu", "りゅ"),
+    ("ryo", "りょ"),
+    ("fa", "ふぁ"),
+    ("fi", "ふぃ"),
+    ("fe", "ふぇ"),
+    ("fo", "ふぉ"),
+    ("xa", "ぁ"),
+    ("xi", "ぃ"),
+    ("xu", "ぅ"),
+    ("xe", "ぇ"),
+    ("xo", "ぉ"),
+    ("xya", "ゃ"),
+    ("xyu", "ゅ"),
+    ("xyo", "ょ"),
+    ("xtu", "っ"),
+    ("xtsu", "っ"),
+    ("xwa", "ゎ"),
+    ("ltu", "っ"),
+    ("ltsu", "っ"),
+    ("lya", "ゃ"),
+    ("lyu", "ゅ"),
+    ("lyo", "ょ"),
+    ("-", "ー"),
+    ("nn", "ん"),
+    ("n'", "ん"),
+    ("xn", "ん"),
+];
+
+const MAX_ROMAJI_LEN: usize = 4;
+
+// Script that romaji typed into answer boxes is converted to, chosen per study set
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub enum KanaInput {
+    #[default]
+    Off,
+    Hiragana,
+    Katakana,
+}
+
+impl KanaInput {
+    pub fn get_label(&self) -> String {
+        match self {
+            KanaInput::Off => String::from("Off"),
+            KanaInput::Hiragana => String::from("Hiragana"),
+            KanaInput::Katakana => String::from("Katakana"),
+        }
+    }
+
+    pub fn next(&self) -> KanaInput {
+        match self {
+            KanaInput::Off => KanaInput::Hiragana,
+            KanaInput::Hiragana => KanaInput::Katakana,
+            KanaInput::Katakana => KanaInput::Off,
+        }
+    }
+}
+
+fn is_vowel(c: char) -> bool {
+    "aiueo".contains(c)
+}
+
+fn is_consonant(c: char) -> bool {
+    c.is_ascii_lowercase() && !is_vowel(c)
+}
+
+fn lookup_romaji(romaji: &str) -> Option<&'static str> {
+    for &(key, kana) in ROMAJI_TABLE {
+        if key == romaji {
+            return Some(kana);
+        }
+    }
+    None
+}
+
+// Converts romaji to hiragana, leaving anything that is not romaji as it is
+pub fn romaji_to_hiragana(input: &str) -> String {
+    convert_romaji(input, false)
+}
+
+// While typing, a trailing "n" or "nn" is kept as it may still become な, に, ...
+// Romaji is matched in any case, anything else keeps the case it was typed in
+fn convert_romaji(input: &str, is_typing: bool) -> String {
+    let typed: Vec<char> = input.chars().collect();
+    let chars: Vec<char> = typed.iter().map(|c| c.to_ascii_lowercase()).collect();
+    let mut res = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let curr = chars[i];
+        let next = chars.get(i + 1).copied();
+        // doubled consonants ("kk", "tch") become a small tsu
+        if is_consonant(curr)
+            && curr != 'n'
+            && (next == Some(curr) || curr == 't' && next == Some('c'))
+        {
+            res.push('っ');
+            i += 1;
+            continue;
+        }
+        if curr == 'n' {
+            let after_next = chars.get(i + 2).copied();
+            // "nn" is only ん once the next letter shows it does not start a "n" syllable
+            if is_typing && (next.is_none() || next == Some('n') && after_next.is_none()) {
+                res.extend(&typed[i..]);
+                break;
+            }
+            let is_syllable_start = |c: Option<char>| c.is_some_and(|c| is_vowel(c) || c == 'y');
+            // "nn" followed by a vowel is ん plus a "n" syllable, e.g. "konnichiwa"
+            if next == Some('n') && is_syllable_start(after_next) {
+                res.push('ん');
+                i += 1;
+                continue;
+            }
+            if next != Some('n') && next != Some('\'') && !is_syllable_start(next) {
+                res.push('ん');
+                i += 1;
+                continue;
+            }
+        }
+        let mut matched = false;
+        for len in (1..=MAX_ROMAJI_LEN).rev() {
+            if i + len > chars.len() {
+                continue;
+            }
+            let romaji: String = chars[i..i + len].iter().collect();
+            if let Some(kana) = lookup_romaji(&romaji) {
+                res.push_str(kana);
+                i += len;
+                matched = true;
+                break;
+            }
+        }
+        if !matched {
+            res.push(typed[i]);
+            i += 1;
+        }
+    }
+    res
+}
+
+pub fn katakana_to_hiragana(input: &str) -> String {
+    input
+        .chars()
+        .map(|c| match c {
+            'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+pub fn hiragana_to_katakana(input: &str) -> String {
+    input
+        .chars()
+        .map(|c| match c {
+            'ぁ'..='ゖ' => char::from_u32(c as u32 + 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+fn to_kana_input(input: &str, kana_input: KanaInput, is_typing: bool) -> String {
+    match kana_input {
+        KanaInput::Off => input.to_string(),
+        KanaInput::Hiragana => convert_romaji(input, is_typing),
+        KanaInput::Katakana => hiragana_to_katakana(&convert_romaji(input, is_typing)),
+    }
+}
+
+// Converts what has been typed so far, leaving incomplete syllables as romaji
+pub fn convert_typing(input: &str, kana_input: KanaInput) -> String {
+    to_kana_input(input, kana_input, true)
+}
+
+// Converts a finished answer, including any trailing "n"
+pub fn convert_finished(input: &str, kana_input: KanaInput) -> String {
+    to_kana_input(input, kana_input, false)
+}
+
+// Reduces romaji, katakana and hiragana spellings of a word to the same hiragana
+pub fn to_comparable_kana(input: &str) -> String {
+    romaji_to_hiragana(&katakana_to_hiragana(input.trim()).to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // What the answer box shows after each letter is typed, converting the text it holds
+    fn type_letters(input: &str, kana_input: KanaInput) -> String {
+        let mut text = String::new();
+        for c in input.chars() {
+            text.push(c);
+            text = convert_typing(&text, kana_input);
+        }
+        text
+    }
+
+    #[test]
+    fn converts_finished_romaji() {
+        assert_eq!(
+            convert_finished("konnichiwa", KanaInput::Hiragana),
+            "こんにちわ"
+        );
+        assert_eq!(convert_finished("kanji", KanaInput::Hiragana), "かんじ");
+        assert_eq!(convert_finished("zasshi", KanaInput::Hiragana), "ざっし");
+        assert_eq!(convert_finished("kon", KanaInput::Hiragana), "こん");
+        assert_eq!(convert_finished("kan'i", KanaInput::Hiragana), "かんい");
+        assert_eq!(convert_finished("ra-men", KanaInput::Katakana), "ラーメン");
+        assert_eq!(convert_finished("kyou", KanaInput::Off), "kyou");
+    }
+
+    #[test]
+    fn typing_matches_finished_conversion() {
+        for word in [
+            "konnichiwa",
+            "onna",
+            "kanji",
+            "konbanwa",
+            "sannin",
+            "hon'ya",
+            "kon",
+        ] {
+            let typed = convert_finished(
+                &type_letters(word, KanaInput::Hiragana),
+                KanaInput::Hiragana,
+            );
+            assert_eq!(
+                typed,
+                convert_finished(word, KanaInput::Hiragana),
+                "{}",
+                word
+            );
+        }
+    }
+
+    #[test]
+    fn typing_holds_back_trailing_n() {
+        assert_eq!(convert_typing("kon", KanaInput::Hiragana), "こn");
+        assert_eq!(convert_typing("konn", KanaInput::Hiragana), "こnn");
+        assert_eq!(convert_typing("konni", KanaInput::Hiragana), "こんに");
+        assert_eq!(convert_typing("kanj", KanaInput::Hiragana), "かんj");
+        assert_eq!(
+            type_letters("konnichiwa", KanaInput::Hiragana),
+            "こんにちわ"
+        );
+    }
+
+    #[test]
+    fn keeps_case_of_text_not_converted() {
+        assert_eq!(convert_typing("KA", KanaInput::Hiragana), "か");
+        assert_eq!(
+            convert_typing("Tokyo 2020", KanaInput::Hiragana),
+            "ときょ 2020"
+        );
+        assert_eq!(convert_finished("XYZ", KanaInput::Hiragana), "XYZ");
+        assert_eq!(convert_typing("Qk", KanaInput::Hiragana), "Qk");
+    }
+
+    #[test]
+    fn compares_kana_across_scripts() {
+        assert_eq!(to_comparable_kana("カタカナ"), "かたかな");
+        assert_eq!(to_comparable_kana(" Katakana "), "かたかな");
+        assert_eq!(hiragana_to_katakana("ひらがな"), "ヒラガナ");
+    }
+}
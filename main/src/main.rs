@@ -1,19 +1,75 @@
 #![windows_subsystem = "windows"]
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
+use diff::DiffKind;
+use druid::text::{Formatter, Selection, Validation, ValidationError};
 use druid::{
     theme,
-    widget::{Align, Button, Flex, Label, Painter, Scroll, TextBox},
-    AppLauncher, Color, Data, Lens, RenderContext, Widget, WidgetExt, WindowDesc,
+    widget::{
+        Align, Button, Checkbox, Controller, CrossAxisAlignment, Either, FillStrat, Flex, Image,
+        Label, Painter, Scroll, SizedBox, TextBox, ViewSwitcher,
+    },
+    AppDelegate, AppLauncher, Application, Color, Command, Data, DelegateCtx, Env, Event, Handled,
+    ImageBuf, KeyOrValue, Lens, LifeCycle, LifeCycleCtx, RenderContext, Selector, Target,
+    TimerToken, UpdateCtx, Vec2, Widget, WidgetExt, WindowDesc,
 };
-use storage::{Card, Catalogue, ListItem, Storage, StudySet};
+use duplicates::{CardRef, DuplicateFinder, DuplicateGroup};
+use history::{CardReview, Grade, SetStats, StudyRecord};
+use kana::KanaInput;
+use nav::{ListFilter, NavStack, Route};
+use query::SavedQuery;
+use replace::{CardChange, ReplaceScope, Replacement};
+use schema::{FieldRoles, FieldSchema};
+use search::{CardField, MatchMode, SearchIndex, SearchResult};
+use settings::{KeyAction, KeyBindings};
+use sort::{ListSort, SortKey};
+use storage::{
+    Card, CardFlag, CardType, Catalogue, ListItem, MediaKind, Session, Storage, StudyMode, StudySet,
+};
+use tags::TagColor;
+use timer::TimeLimits;
+use watcher::DATA_CHANGED;
 
 const MAIN_TITLE: &str = "Quiz Late";
 const SELECTED_TAG_COLOR: druid::Color = Color::rgba8(52, 222, 235, 255);
 const UNSELECTED_TAG_COLOR: druid::Color = Color::rgba8(52, 222, 235, 0);
+const MAX_SEARCH_RESULTS: usize = 200;
+const MAX_DUPLICATE_WARNINGS: usize = 5;
+const CARD_IMAGE_HEIGHT: f64 = 200.0;
+// colours of a card's fields on the view page, in order
+const FIELD_COLORS: [Color; 6] = [
+    Color::FUCHSIA,
+    Color::SILVER,
+    Color::OLIVE,
+    Color::TEAL,
+    Color::AQUA,
+    Color::PURPLE,
+];
 
+mod audio;
+mod cloze;
+mod diff;
+mod duplicates;
+mod folders;
+mod furigana;
+mod grid;
+mod history;
+mod kana;
+mod nav;
+mod query;
+mod replace;
+mod schema;
+mod search;
+mod settings;
+mod sort;
+mod split;
 mod storage;
+mod tags;
+mod timer;
+mod watcher;
 /*
  * Data
  * Ui Builder
@@ -34,16 +90,110 @@ struct AppState {
     word_to_add: String,
     word_ans_to_add: String,
     word_remark_to_add: String,
+    // fields of the set after the first three
+    extra_fields_to_add: Vec<String>,
     // for adding a studyset
     new_set_name: String,
     new_set_tag: String,
+    // comma separated field names for the set's schema
+    new_set_fields: String,
     // for learn function
     answer_to_show: String,
     current_filter: HashSet<String>,
+    // typed on the study sets list, see query::parse_query
+    list_query: String,
+    query_name: String,
+    // which cards of a set are studied
+    starred_only: bool,
+    skip_suspended: bool,
+    // typed on the view page, added to cards one at a time
+    new_card_tag: String,
+    // tag groups folded away on the study sets list, "" for untagged sets
+    collapsed_tags: Vec<String>,
+    // for adding and moving folders, and moving sets into them
+    new_set_folder: String,
+    new_folder_name: String,
+    folder_message: String,
+    // sets left to study from a folder, after the one studied last
+    folder_study: Vec<String>,
+    folder_study_set: String,
+    // ids of the cards ticked on the view page of `selected_set`
+    selected_cards: Vec<usize>,
+    selected_set: String,
+    // for moving and copying the ticked cards to another set
+    card_target_set: String,
+    card_message: String,
+    // typed on the edit set page, see split.rs
+    sets_to_merge: String,
+    skip_duplicates_on_merge: bool,
+    split_input: String,
+    // the grid editor's cells for `grid_set`, with the card each row came from, None for
+    // new rows
+    grid_set: String,
+    grid_rows: Vec<Vec<String>>,
+    grid_card_ids: Vec<Option<usize>>,
+    grid_selected: Vec<usize>,
+    grid_edited: bool,
+    grid_message: String,
+    // for renaming, merging and deleting tags across all sets
+    tag_to_manage: String,
+    new_tag_name: String,
+    tag_message: String,
+    // for searching cards across all sets
+    search_query: String,
+    search_prefix_only: bool,
+    search_ignore_kana: bool,
+    search_card_tag: String,
+    // for find and replace, see replace.rs
+    replace_find: String,
+    replace_with: String,
+    replace_is_regex: bool,
+    replace_match_case: bool,
+    replace_in_word: bool,
+    replace_in_ans: bool,
+    replace_in_remarks: bool,
+    replace_scope: ReplaceScope,
+    replace_scope_text: String,
+    // changes shown for the inputs in `replace_preview_key`, and the last replace made
+    replace_preview: Vec<CardChange>,
+    replace_preview_key: String,
+    replace_undo: Vec<CardChange>,
+    replace_message: String,
+    // for finding duplicate cards
+    kept_duplicates: Vec<String>,
+    warn_duplicates: bool,
+    // bumped whenever set files are reloaded from disk
+    data_version: usize,
+    // sets changed on disk while there were unsaved changes
+    reload_conflicts: Vec<String>,
+    // set files that could not be read, e.g. half saved by another program
+    unreadable_sets: Vec<String>,
+    // what the study sets list shows and sorts by, read once and updated as rounds are added
+    // to the history, by set name
+    set_stats: HashMap<String, SetStats>,
+    // for hiding furigana during tests
+    hide_readings: bool,
+    // for speaking each word as it appears in learn
+    auto_play: bool,
+    // for attaching media files to a card, and exporting sets
+    media_path_to_add: String,
+    media_message: String,
+    // the set and id of the card a recording is running for
+    recording: Option<(String, usize)>,
+    // pages visited in the window, the last one is shown
+    nav: NavStack,
+    // for flashcards, whether the answer side is showing
+    card_flipped: bool,
+    // ids of the cards to study in each set, empty for all of them
+    card_orders: Vec<Vec<usize>>,
+    // for timed tests, limits as typed and seconds spent on each card
+    card_time_limit: String,
+    total_time_limit: String,
+    card_times: Vec<f64>,
 }
 
 fn is_valid(input_str: String) -> bool {
-    input_str.replace(" ", "") != ""
+    !input_str.replace(" ", "").is_empty()
 }
 
 fn has_duplicate(input_name: String, existing_names: Vec<String>) -> bool {
@@ -51,12 +201,39 @@ fn has_duplicate(input_name: String, existing_names: Vec<String>) -> bool {
     existing_names.contains(&actual_name)
 }
 
-// todo: color coding of tags
-// enum Module {}
+// Set names are used as file names, see Storage::get_set_path
+const INVALID_SET_NAME_CHARS: &str = "/\\:*?\"<>|";
+
+// Whether a new set can be given the name, and why not
+fn check_set_name(input_name: &str, existing_names: &[String]) -> Result<(), String> {
+    if !is_valid(input_name.to_string()) {
+        return Err(String::from("Set Name Cannot Be Empty"));
+    }
+    if has_duplicate(input_name.to_string(), existing_names.to_vec()) {
+        return Err(format!("Set [{}] already exists!", input_name.trim()));
+    }
+    if let Some(c) = input_name
+        .chars()
+        .find(|c| INVALID_SET_NAME_CHARS.contains(*c) || c.is_control())
+    {
+        return Err(format!("Set Name Cannot Contain [{}]", c));
+    }
+    Ok(())
+}
 
-// fn get_color_code(tag: String) -> Color {
-//     Color::RED
-// }
+// Colour a tag is shown in, chosen on the tags page for it or one of its parents
+fn get_color_code(tag: &str, tag_colors: &[TagColor]) -> Color {
+    match tags::find_color_name(tag, tag_colors).as_deref() {
+        Some("Aqua") => Color::AQUA,
+        Some("Fuchsia") => Color::FUCHSIA,
+        Some("Yellow") => Color::YELLOW,
+        Some("Orange") => Color::rgb8(255, 165, 0),
+        Some("Red") => Color::RED,
+        Some("Silver") => Color::SILVER,
+        Some("Purple") => Color::PURPLE,
+        _ => Color::LIME,
+    }
+}
 
 impl Data for AppState {
     fn same(&self, other: &Self) -> bool {
@@ -77,13 +254,20 @@ impl Data for AppState {
         if self.str != other.str {
             return false;
         }
+        if self.word_to_add != other.word_to_add
+            || self.word_ans_to_add != other.word_ans_to_add
+            || self.word_remark_to_add != other.word_remark_to_add
+            || self.extra_fields_to_add != other.extra_fields_to_add
+        {
+            return false;
+        }
         if self.answer_to_show != other.answer_to_show {
             return false;
         }
         if self.new_set_name != other.new_set_name {
             return false;
         }
-        if self.new_set_tag != other.new_set_tag {
+        if self.new_set_tag != other.new_set_tag || self.new_set_fields != other.new_set_fields {
             return false;
         }
         for elem in &self.current_filter {
@@ -91,7 +275,110 @@ impl Data for AppState {
                 return false;
             }
         }
-        return true;
+        if self.list_query != other.list_query || self.query_name != other.query_name {
+            return false;
+        }
+        if self.starred_only != other.starred_only
+            || self.skip_suspended != other.skip_suspended
+            || self.new_card_tag != other.new_card_tag
+            || !is_same_vec_string(self.collapsed_tags.clone(), other.collapsed_tags.clone())
+        {
+            return false;
+        }
+        if self.new_set_folder != other.new_set_folder
+            || self.new_folder_name != other.new_folder_name
+            || self.folder_message != other.folder_message
+            || !is_same_vec_string(self.folder_study.clone(), other.folder_study.clone())
+            || self.folder_study_set != other.folder_study_set
+        {
+            return false;
+        }
+        if self.selected_cards != other.selected_cards
+            || self.selected_set != other.selected_set
+            || self.card_target_set != other.card_target_set
+            || self.card_message != other.card_message
+            || self.sets_to_merge != other.sets_to_merge
+            || self.skip_duplicates_on_merge != other.skip_duplicates_on_merge
+            || self.split_input != other.split_input
+        {
+            return false;
+        }
+        if self.grid_set != other.grid_set
+            || !is_same_2_d_vec_string(self.grid_rows.clone(), other.grid_rows.clone())
+            || self.grid_card_ids != other.grid_card_ids
+            || self.grid_selected != other.grid_selected
+            || self.grid_edited != other.grid_edited
+            || self.grid_message != other.grid_message
+        {
+            return false;
+        }
+        if self.tag_to_manage != other.tag_to_manage
+            || self.new_tag_name != other.new_tag_name
+            || self.tag_message != other.tag_message
+        {
+            return false;
+        }
+        if self.search_query != other.search_query
+            || self.search_prefix_only != other.search_prefix_only
+            || self.search_ignore_kana != other.search_ignore_kana
+            || self.search_card_tag != other.search_card_tag
+        {
+            return false;
+        }
+        if self.replace_find != other.replace_find
+            || self.replace_with != other.replace_with
+            || self.replace_is_regex != other.replace_is_regex
+            || self.replace_match_case != other.replace_match_case
+            || self.replace_in_word != other.replace_in_word
+            || self.replace_in_ans != other.replace_in_ans
+            || self.replace_in_remarks != other.replace_in_remarks
+            || !self.replace_scope.same(&other.replace_scope)
+            || self.replace_scope_text != other.replace_scope_text
+            || self.replace_preview != other.replace_preview
+            || self.replace_preview_key != other.replace_preview_key
+            || self.replace_undo != other.replace_undo
+            || self.replace_message != other.replace_message
+        {
+            return false;
+        }
+        if !is_same_vec_string(self.kept_duplicates.clone(), other.kept_duplicates.clone())
+            || self.warn_duplicates != other.warn_duplicates
+        {
+            return false;
+        }
+        if self.data_version != other.data_version {
+            return false;
+        }
+        if !is_same_vec_string(
+            self.reload_conflicts.clone(),
+            other.reload_conflicts.clone(),
+        ) || !is_same_vec_string(self.unreadable_sets.clone(), other.unreadable_sets.clone())
+            || self.set_stats != other.set_stats
+        {
+            return false;
+        }
+        if self.hide_readings != other.hide_readings || self.auto_play != other.auto_play {
+            return false;
+        }
+        if self.media_path_to_add != other.media_path_to_add
+            || self.media_message != other.media_message
+            || self.recording != other.recording
+        {
+            return false;
+        }
+        if !self.nav.same(&other.nav) {
+            return false;
+        }
+        if self.card_flipped != other.card_flipped || self.card_orders != other.card_orders {
+            return false;
+        }
+        if self.card_time_limit != other.card_time_limit
+            || self.total_time_limit != other.total_time_limit
+            || self.card_times != other.card_times
+        {
+            return false;
+        }
+        true
     }
 }
 
@@ -125,6 +412,7 @@ impl AppState {
         let mut input_all: Vec<Vec<String>> = Vec::new();
         let mut res_all: Vec<Vec<String>> = Vec::new();
         let mut indexes = Vec::new();
+        let time_limits = Storage::read_settings_file().get_time_limits();
         for item in catalouge.get_inventory() {
             let mut card_set_inputs = Vec::new();
             let mut card_set_res = Vec::new();
@@ -145,260 +433,1179 @@ impl AppState {
             word_to_add: String::new(),
             word_ans_to_add: String::new(),
             word_remark_to_add: String::new(),
+            extra_fields_to_add: Vec::new(),
             new_set_name: String::new(),
             new_set_tag: String::new(),
+            new_set_fields: String::new(),
             answer_to_show: String::new(),
             current_filter: HashSet::new(),
+            list_query: String::new(),
+            query_name: String::new(),
+            starred_only: false,
+            skip_suspended: true,
+            new_card_tag: String::new(),
+            collapsed_tags: Vec::new(),
+            new_set_folder: String::new(),
+            new_folder_name: String::new(),
+            folder_message: String::new(),
+            folder_study: Vec::new(),
+            folder_study_set: String::new(),
+            selected_cards: Vec::new(),
+            selected_set: String::new(),
+            card_target_set: String::new(),
+            card_message: String::new(),
+            sets_to_merge: String::new(),
+            skip_duplicates_on_merge: true,
+            split_input: String::new(),
+            grid_set: String::new(),
+            grid_rows: Vec::new(),
+            grid_card_ids: Vec::new(),
+            grid_selected: Vec::new(),
+            grid_edited: false,
+            grid_message: String::new(),
+            tag_to_manage: String::new(),
+            new_tag_name: String::new(),
+            tag_message: String::new(),
+            search_query: String::new(),
+            search_prefix_only: false,
+            search_ignore_kana: true,
+            search_card_tag: String::new(),
+            replace_find: String::new(),
+            replace_with: String::new(),
+            replace_is_regex: false,
+            replace_match_case: true,
+            replace_in_word: true,
+            replace_in_ans: true,
+            replace_in_remarks: true,
+            replace_scope: ReplaceScope::AllSets,
+            replace_scope_text: String::new(),
+            replace_preview: Vec::new(),
+            replace_preview_key: String::new(),
+            replace_undo: Vec::new(),
+            replace_message: String::new(),
+            kept_duplicates: Vec::new(),
+            warn_duplicates: true,
+            data_version: 0,
+            reload_conflicts: Vec::new(),
+            unreadable_sets: Vec::new(),
+            set_stats: read_set_stats(&catalouge),
+            hide_readings: false,
+            auto_play: false,
+            media_path_to_add: String::new(),
+            media_message: String::new(),
+            recording: None,
+            nav: NavStack::new(),
+            card_flipped: false,
+            card_orders: Vec::new(),
+            card_time_limit: time_limits
+                .get_per_card()
+                .map_or(String::new(), |seconds| seconds.to_string()),
+            total_time_limit: time_limits.get_total().map_or(String::new(), |seconds| {
+                timer::format_countdown(seconds as f64)
+            }),
+            card_times: Vec::new(),
         }
     }
-}
 
-fn test_page_builder(set_index: usize, file_name: String) -> impl Widget<AppState> {
-    let set_name = file_name.clone();
-    let study_set = Storage::read_set_file(file_name);
-    let num_of_cards = study_set.get_num_of_cards();
-    // clone 1 for each purpose
-    let cloned_set_for_words = study_set.clone();
-    let cloned_set_for_remarks = study_set.clone();
-    let word_label = Label::dynamic(move |data: &AppState, _env| -> String {
-        let word_index = data.curr_indexes[set_index];
-        cloned_set_for_words.get_card(word_index).get_word()
-    })
-    .with_text_size(32.0);
-    let remarks_label = Label::dynamic(move |data: &AppState, _env| -> String {
-        let remark_index = data.curr_indexes[set_index];
-        cloned_set_for_remarks.get_card(remark_index).get_remarks()
-    })
-    .with_text_size(32.0);
+    fn has_unsaved_changes(&self) -> bool {
+        is_valid(self.word_to_add.clone())
+            || is_valid(self.word_ans_to_add.clone())
+            || is_valid(self.word_remark_to_add.clone())
+            || self
+                .extra_fields_to_add
+                .iter()
+                .any(|field| is_valid(field.clone()))
+            || is_valid(self.new_set_name.clone())
+            || is_valid(self.new_set_fields.clone())
+            || is_valid(self.new_set_tag.clone())
+            || is_valid(self.media_path_to_add.clone())
+            || self.grid_edited
+            || self.has_answers_in_progress()
+    }
 
-    let text_box = TextBox::new()
-        .with_placeholder("Enter text here")
-        .with_text_size(24.0)
-        .fix_width(300.0)
-        .lens(AppState::str);
+    // answers typed or cards graded in the set being studied, which a reload would throw away
+    fn has_answers_in_progress(&self) -> bool {
+        let set_id = match self.nav.get_route().get_studied_set() {
+            Some(set_name) => match self.catalogue.get_id_by_name(set_name) {
+                Some(set_id) => set_id,
+                None => return false,
+            },
+            None => return false,
+        };
+        is_valid(self.str.clone())
+            || self
+                .input_str
+                .get(set_id)
+                .into_iter()
+                .chain(self.res.get(set_id))
+                .flatten()
+                .any(|answer| is_valid(answer.clone()))
+    }
 
-    let clear = Button::new("Clear").on_click(move |ctx, data: &mut AppState, _env| -> () {
-        let message = String::from("Input Cleared");
-        data.str.clear();
-        let word_index = data.curr_indexes[set_index];
-        data.res[set_index][word_index] = message;
-        ctx.request_update();
-    });
-    let prev = Button::new("Prev").on_click(move |ctx, data: &mut AppState, _env| -> () {
-        let ind = data.curr_indexes[set_index];
-        data.input_str[set_index][ind] = data.str.clone();
-        if data.curr_indexes[set_index] >= 1 {
-            data.curr_indexes[set_index] -= 1;
-            data.str.clear();
+    fn discard_unsaved_changes(&mut self) {
+        self.word_to_add.clear();
+        self.word_ans_to_add.clear();
+        self.word_remark_to_add.clear();
+        self.extra_fields_to_add.clear();
+        self.new_set_name.clear();
+        self.new_set_fields.clear();
+        self.new_set_tag.clear();
+        self.media_path_to_add.clear();
+        // the grid is filled again from the set file when next opened or reloaded
+        self.grid_edited = false;
+    }
+
+    // fill the grid editor with the cards of the set as saved
+    fn load_grid(&mut self, set_name: String) {
+        let study_set = match Storage::read_set_file(set_name.clone()) {
+            Ok(study_set) => study_set,
+            Err(_) => return,
+        };
+        self.grid_rows = grid::get_rows(&study_set);
+        self.grid_card_ids = (0..study_set.get_num_of_cards()).map(Some).collect();
+        self.grid_selected.clear();
+        self.grid_edited = false;
+        self.grid_set = set_name;
+    }
+
+    // keep the per set inputs and results in step with the catalogue. Set ids change when sets
+    // are added or removed, so each set takes its state from its id in the previous catalogue
+    fn sync_with_catalogue(&mut self, prev_catalogue: &Catalogue) {
+        // the history of a deleted set is deleted with it
+        let catalogue = &self.catalogue;
+        self.set_stats
+            .retain(|set_name, _| catalogue.get_id_by_name(set_name.clone()).is_some());
+        let num_of_items = self.catalogue.get_num_of_items();
+        let mut input_str = vec![Vec::new(); num_of_items];
+        let mut res = vec![Vec::new(); num_of_items];
+        let mut curr_indexes = vec![0; num_of_items];
+        let mut card_orders = vec![Vec::new(); num_of_items];
+        for item in self.catalogue.get_inventory() {
+            let id = item.get_id();
+            let num_of_cards = item.get_num_of_cards();
+            if id >= num_of_items {
+                continue;
+            }
+            let prev_id = prev_catalogue
+                .get_id_by_name(item.get_name())
+                .filter(|prev_id| {
+                    self.input_str.get(*prev_id).map(|answers| answers.len()) == Some(num_of_cards)
+                });
+            match prev_id {
+                Some(prev_id) => {
+                    input_str[id] = std::mem::take(&mut self.input_str[prev_id]);
+                    res[id] = self
+                        .res
+                        .get_mut(prev_id)
+                        .map(std::mem::take)
+                        .unwrap_or_default();
+                    card_orders[id] = self
+                        .card_orders
+                        .get_mut(prev_id)
+                        .map(std::mem::take)
+                        .unwrap_or_default();
+                    curr_indexes[id] = self
+                        .curr_indexes
+                        .get(prev_id)
+                        .copied()
+                        .filter(|index| *index < num_of_cards)
+                        .unwrap_or(0);
+                }
+                // a new set, or one whose cards changed, starts over
+                None => {
+                    input_str[id] = vec![String::new(); num_of_cards];
+                    res[id] = vec![String::new(); num_of_cards];
+                }
+            }
         }
-        ctx.request_update();
-    });
-    let next = Button::new("Next").on_click(move |ctx, data: &mut AppState, _env| -> () {
-        let ind = data.curr_indexes[set_index];
-        data.input_str[set_index][ind] = data.str.clone();
-        if data.curr_indexes[set_index] < num_of_cards - 1 {
-            data.curr_indexes[set_index] += 1;
-            data.str.clear();
+        self.input_str = input_str;
+        self.res = res;
+        self.curr_indexes = curr_indexes;
+        self.card_orders = card_orders;
+    }
+
+    // ids of the cards being studied in a set, all of them unless retrying missed ones
+    fn get_card_order(&self, set_index: usize) -> Vec<usize> {
+        match self.card_orders.get(set_index) {
+            Some(order) if !order.is_empty() => order.clone(),
+            _ => (0..self.input_str[set_index].len()).collect(),
         }
-        ctx.request_update();
-    });
+    }
 
-    let eval_results = Button::new("Submit Test").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-            let ind = data.curr_indexes[set_index];
-            data.input_str[set_index][ind] = data.str.clone();
-            let results_window = WindowDesc::new(result_page_builder(
-                set_name.clone(),
-                data.input_str[set_index].clone(),
-                study_set.clone(),
-            ))
-            .title("Resuts");
-            ctx.window().close();
-            ctx.new_window(results_window);
-        },
-    );
+    // how far through the card order the current card is
+    fn get_card_position(&self, set_index: usize) -> usize {
+        let word_index = self.curr_indexes[set_index];
+        self.get_card_order(set_index)
+            .iter()
+            .position(|id| *id == word_index)
+            .unwrap_or(0)
+    }
 
-    let res_label = Label::dynamic(move |data: &AppState, _| {
-        let word_index = data.curr_indexes[set_index];
-        data.res[set_index][word_index].clone()
+    fn reload_from_disk(&mut self) {
+        self.unreadable_sets = Storage::inventory_check();
+        let prev_catalogue = std::mem::replace(&mut self.catalogue, Storage::read_inventory_file());
+        self.sync_with_catalogue(&prev_catalogue);
+        // the history may have been changed outside the app too
+        self.set_stats = read_set_stats(&self.catalogue);
+        if self
+            .catalogue
+            .get_id_by_name(self.grid_set.clone())
+            .is_some()
+        {
+            self.load_grid(self.grid_set.clone());
+        }
+        self.reload_conflicts.clear();
+        self.data_version += 1;
+    }
+}
+
+struct Delegate;
+
+impl AppDelegate<AppState> for Delegate {
+    fn command(
+        &mut self,
+        _ctx: &mut DelegateCtx,
+        _target: Target,
+        cmd: &Command,
+        data: &mut AppState,
+        _env: &Env,
+    ) -> Handled {
+        if let Some(set_names) = cmd.get(DATA_CHANGED) {
+            if data.has_unsaved_changes() {
+                for set_name in set_names {
+                    if !data.reload_conflicts.contains(set_name) {
+                        data.reload_conflicts.push(set_name.clone());
+                    }
+                }
+            } else {
+                data.reload_from_disk();
+            }
+            return Handled::Yes;
+        }
+        Handled::No
+    }
+}
+
+fn reload_banner_builder() -> impl Widget<AppState> {
+    let message = Label::dynamic(|data: &AppState, _env| -> String {
+        format!(
+            "Changed outside the app: {}",
+            data.reload_conflicts.join(", ")
+        )
     })
-    .with_text_size(24.0);
-    let index_label = Label::dynamic(move |data: &AppState, _| {
-        format!("{} / {}\n", data.curr_indexes[set_index] + 1, num_of_cards)
+    .with_text_size(20.0)
+    .with_text_color(Color::YELLOW);
+    let reload = Button::new("Reload & Discard My Changes").on_click(
+        |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.discard_unsaved_changes();
+            data.reload_from_disk();
+        },
+    );
+    let keep = Button::new("Keep My Changes").on_click(
+        |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.reload_conflicts.clear();
+        },
+    );
+    let banner = Flex::column()
+        .with_child(message)
+        .with_spacer(10.0)
+        .with_child(
+            Flex::row()
+                .with_child(reload)
+                .with_spacer(10.0)
+                .with_child(keep),
+        )
+        .padding(10.0)
+        .border(Color::YELLOW, 1.0);
+    let unreadable_message = Label::dynamic(|data: &AppState, _env| -> String {
+        format!(
+            "Could not read: {}. Sets read before are kept as last read",
+            data.unreadable_sets.join(", ")
+        )
     })
-    .with_text_size(24.0);
+    .with_text_size(20.0)
+    .with_text_color(Color::YELLOW);
+    let dismiss = Button::new("OK").on_click(
+        |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.unreadable_sets.clear();
+        },
+    );
+    let unreadable_banner = Flex::row()
+        .with_child(unreadable_message)
+        .with_spacer(10.0)
+        .with_child(dismiss)
+        .padding(10.0)
+        .border(Color::YELLOW, 1.0);
+    Flex::column()
+        .with_child(Either::new(
+            |data: &AppState, _env| !data.reload_conflicts.is_empty(),
+            banner,
+            SizedBox::empty(),
+        ))
+        .with_child(Either::new(
+            |data: &AppState, _env| !data.unreadable_sets.is_empty(),
+            unreadable_banner,
+            SizedBox::empty(),
+        ))
+}
 
-    let inputs = Flex::row()
-        .with_child(prev)
-        .with_child(clear)
-        .with_child(next);
+// Builds a page for a set, looking its id up and reading it again as the set may have been
+// renamed or deleted
+fn set_page<W: Widget<AppState> + 'static>(
+    set_name: String,
+    data: &AppState,
+    builder: impl Fn(usize, StudySet) -> W,
+) -> Box<dyn Widget<AppState>> {
+    match (
+        data.catalogue.get_id_by_name(set_name.clone()),
+        Storage::read_set_file(set_name.clone()),
+    ) {
+        (Some(set_id), Ok(study_set)) => Box::new(builder(set_id, study_set)),
+        _ => Box::new(missing_set_page_builder(set_name)),
+    }
+}
 
-    let return_to_main = Button::new("Return to Study Sets List").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-            let new_win = WindowDesc::new(list_page_builder(
-                data.catalogue.get_inventory(),
-                data.catalogue.get_all_tags(),
-            ))
-            .title(MAIN_TITLE);
-            ctx.window().close();
-            ctx.new_window(new_win);
+fn get_list_items(filter: ListFilter, catalogue: &Catalogue) -> Vec<ListItem> {
+    match filter {
+        ListFilter::All => catalogue.get_inventory(),
+        ListFilter::MatchAll(filters) => tags::get_items_by_tags(catalogue, &filters, false),
+        ListFilter::MatchAny(filters) => tags::get_items_by_tags(catalogue, &filters, true),
+        ListFilter::Untagged => catalogue.get_all_untagged_study_sets(),
+        ListFilter::Query(text) => match query::parse_query(&text) {
+            Ok(query) => query::get_items_by_query(catalogue, &query),
+            Err(_) => Vec::new(),
         },
-    );
+        ListFilter::Folder(folder) => catalogue.get_items_in_folder(&folder),
+    }
+}
 
-    let card = Flex::column()
-        .with_child(index_label)
-        .with_spacer(20.0)
-        .with_child(word_label)
-        .with_spacer(20.0)
-        .with_child(remarks_label)
-        .with_spacer(30.0);
-    let card = card
-        .with_child(text_box)
-        .with_spacer(20.0)
-        .with_child(inputs)
-        .with_spacer(20.0)
-        .with_child(res_label)
-        .with_spacer(20.0)
-        .with_child(eval_results);
-    card.with_spacer(20.0).with_child(return_to_main)
+fn route_page(route: Route, data: &AppState) -> Box<dyn Widget<AppState>> {
+    match route {
+        Route::List(filter) => {
+            let folder_browser = match &filter {
+                ListFilter::Folder(folder) => {
+                    Some(folder_browser_builder(folder.clone(), &data.catalogue))
+                }
+                _ => None,
+            };
+            Box::new(list_page_builder(
+                get_list_items(filter, &data.catalogue),
+                tags::get_all_tags(&data.catalogue),
+                data.set_stats.clone(),
+                folder_browser,
+            ))
+        }
+        Route::View(set_name) => set_page(set_name.clone(), data, |set_id, study_set| {
+            view_page_builder(
+                set_id,
+                study_set.get_set_name(),
+                study_set.get_all_cards(),
+                study_set.get_all_tags(),
+                study_set.get_schema(),
+            )
+        }),
+        Route::Learn(set_name) => set_page(set_name, data, learn_page_builder),
+        Route::Test(set_name) => set_page(set_name, data, |set_id, study_set| {
+            test_page_builder(set_id, study_set, None)
+        }),
+        Route::Flashcards(set_name) => set_page(set_name, data, flashcards_page_builder),
+        Route::TimedTestSetup(set_name) => set_page(set_name.clone(), data, |set_id, _| {
+            timed_test_setup_page_builder(set_id, set_name.clone())
+        }),
+        Route::TimedTest(set_name, time_limits) => set_page(set_name, data, |set_id, study_set| {
+            test_page_builder(set_id, study_set, Some(time_limits))
+        }),
+        Route::TestNoRemarks(set_name) => set_page(set_name, data, test_no_remarks_page_builder),
+        Route::Listen(set_name) => set_page(set_name, data, listen_page_builder),
+        Route::Results {
+            set_name,
+            user_answers,
+            card_ids,
+            study_mode,
+            card_times,
+            time_limits,
+        } => set_page(
+            set_name.clone(),
+            data,
+            |_, study_set| -> Box<dyn Widget<AppState>> {
+                // cards may have been removed outside the app since the set was studied
+                let num_of_cards = study_set.get_num_of_cards().min(user_answers.len());
+                if card_ids.iter().any(|id| *id >= num_of_cards) {
+                    return route_page(Route::View(set_name.clone()), data);
+                }
+                // only the set studied last from a folder carries on with the rest of it
+                let folder_sets_left = if data.folder_study_set == set_name {
+                    data.folder_study.clone()
+                } else {
+                    Vec::new()
+                };
+                Box::new(result_page_builder(
+                    study_set,
+                    user_answers.clone(),
+                    card_ids.clone(),
+                    study_mode,
+                    card_times.clone(),
+                    time_limits,
+                    folder_sets_left,
+                ))
+            },
+        ),
+        Route::AddWord(set_name) => set_page(set_name, data, add_word_page_builder),
+        Route::EditWord(set_name, word_id) => set_page(
+            set_name.clone(),
+            data,
+            |set_id, study_set| -> Box<dyn Widget<AppState>> {
+                // the card may have been removed outside the app
+                if word_id >= study_set.get_num_of_cards() {
+                    return route_page(Route::View(set_name.clone()), data);
+                }
+                let curr_card = study_set.get_card(word_id);
+                // values of fields no longer in the schema are kept as they are
+                let num_of_fields = study_set
+                    .get_schema()
+                    .get_num_of_fields()
+                    .max(3 + curr_card.get_extra_fields().len());
+                Box::new(edit_word_page_builder(
+                    set_id,
+                    word_id,
+                    (0..num_of_fields)
+                        .map(|field| curr_card.get_field(field))
+                        .collect(),
+                    curr_card.get_audio(),
+                    curr_card.get_image(),
+                    study_set,
+                ))
+            },
+        ),
+        Route::EditAllWords(set_name) => set_page(set_name.clone(), data, |set_id, study_set| {
+            let num_of_rows = if data.grid_set == set_name {
+                data.grid_rows.len()
+            } else {
+                0
+            };
+            grid_page_builder(
+                set_id,
+                set_name.clone(),
+                study_set.get_schema(),
+                study_set.get_kana_input(),
+                num_of_rows,
+                grid::get_num_of_columns(&study_set),
+            )
+        }),
+        Route::AddSet => Box::new(add_set_page_builder()),
+        Route::EditSet(set_name) => set_page(set_name, data, |set_id, study_set| {
+            edit_set_page_builder(
+                set_id,
+                study_set.get_set_name(),
+                study_set.get_all_tags(),
+                study_set.get_kana_input(),
+                study_set.get_schema(),
+                data.catalogue.get_item_by_id(set_id)[0].get_folder(),
+            )
+        }),
+        Route::Search => Box::new(search_page_builder()),
+        Route::Replace => Box::new(replace_page_builder(data.replace_preview.clone())),
+        Route::Duplicates => Box::new(duplicates_page_builder(data.kept_duplicates.clone())),
+        Route::Tags => Box::new(tags_page_builder(&data.catalogue)),
+    }
 }
 
-fn test_no_remarks_page_builder(set_index: usize, file_name: String) -> impl Widget<AppState> {
-    let set_name = file_name.clone();
-    let study_set = Storage::read_set_file(file_name);
-    let num_of_cards = study_set.get_num_of_cards();
-    // clone 1 for each purpose
-    let cloned_set_for_words = study_set.clone();
-    let word_label = Label::dynamic(move |data: &AppState, _env| -> String {
-        let word_index = data.curr_indexes[set_index];
-        cloned_set_for_words.get_card(word_index).get_word()
-    })
-    .with_text_size(32.0);
+// Keeps the window title in step with the page being shown
+struct RouteTitle;
 
-    let text_box = TextBox::new()
-        .with_placeholder("Enter text here")
-        .with_text_size(24.0)
-        .fix_width(300.0)
-        .lens(AppState::str);
+impl<W: Widget<AppState>> Controller<AppState, W> for RouteTitle {
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        let route = data.nav.get_route();
+        if old_data.nav.get_route() != route {
+            ctx.window().set_title(&route.get_title());
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
 
-    let clear = Button::new("Clear").on_click(move |ctx, data: &mut AppState, _env| -> () {
-        let message = String::from("Input Cleared");
-        data.str.clear();
-        let word_index = data.curr_indexes[set_index];
-        data.res[set_index][word_index] = message;
-        ctx.request_update();
-    });
-    let prev = Button::new("Prev").on_click(move |ctx, data: &mut AppState, _env| -> () {
-        let ind = data.curr_indexes[set_index];
-        data.input_str[set_index][ind] = data.str.clone();
-        if data.curr_indexes[set_index] >= 1 {
-            data.curr_indexes[set_index] -= 1;
-            data.str.clear();
+// Remembers how far a page is scrolled, and scrolls back there when returning to it
+struct KeepScroll {
+    restored: bool,
+}
+
+impl KeepScroll {
+    fn new() -> KeepScroll {
+        KeepScroll { restored: false }
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, Scroll<AppState, W>> for KeepScroll {
+    fn event(
+        &mut self,
+        child: &mut Scroll<AppState, W>,
+        ctx: &mut druid::EventCtx<'_, '_>,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        child.event(ctx, event, data, env);
+        let offset = child.offset().y;
+        if self.restored && offset != data.nav.get_scroll_offset() {
+            data.nav.set_scroll_offset(offset);
         }
-        ctx.request_update();
-    });
-    let next = Button::new("Next").on_click(move |ctx, data: &mut AppState, _env| -> () {
-        let ind = data.curr_indexes[set_index];
-        data.input_str[set_index][ind] = data.str.clone();
-        if data.curr_indexes[set_index] < num_of_cards - 1 {
-            data.curr_indexes[set_index] += 1;
-            data.str.clear();
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut Scroll<AppState, W>,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        child.lifecycle(ctx, event, data, env);
+        // the content has to be laid out before it can be scrolled
+        if let LifeCycle::Size(_) = event {
+            if !self.restored {
+                child.scroll_by(ctx, Vec2::new(0.0, data.nav.get_scroll_offset()));
+                self.restored = true;
+            }
         }
-        ctx.request_update();
-    });
+    }
+}
 
-    let eval_results = Button::new("Submit Test").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-            let ind = data.curr_indexes[set_index];
-            data.input_str[set_index][ind] = data.str.clone();
-            let results_window = WindowDesc::new(result_page_builder(
-                set_name.clone(),
-                data.input_str[set_index].clone(),
-                study_set.clone(),
-            ))
-            .title("Resuts");
-            ctx.window().close();
-            ctx.new_window(results_window);
+// The single window: a back button, the reload banner and the current page
+fn app_root() -> impl Widget<AppState> {
+    let back = Button::new("Back").on_click(
+        |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.discard_unsaved_changes();
+            data.answer_to_show.clear();
+            data.nav.back();
         },
     );
+    let back_row = Either::new(
+        |data: &AppState, _env| data.nav.can_go_back(),
+        back.align_left().padding(5.0),
+        SizedBox::empty(),
+    );
+    // rebuilt on every navigation, and whenever set files are reloaded from disk
+    let page = ViewSwitcher::new(
+        |data: &AppState, _env| (data.nav.get_version(), data.data_version),
+        |_key, data: &AppState, _env| route_page(data.nav.get_route(), data),
+    )
+    .controller(RouteTitle);
+    Flex::column()
+        .with_child(back_row)
+        .with_child(reload_banner_builder())
+        .with_flex_child(page, 1.0)
+}
 
-    let res_label = Label::dynamic(move |data: &AppState, _| {
-        let word_index = data.curr_indexes[set_index];
-        data.res[set_index][word_index].clone()
-    })
-    .with_text_size(24.0);
-    let index_label = Label::dynamic(move |data: &AppState, _| {
-        format!("{} / {}\n", data.curr_indexes[set_index] + 1, num_of_cards)
-    })
-    .with_text_size(24.0);
-
-    let inputs = Flex::row()
-        .with_child(prev)
-        .with_child(clear)
-        .with_child(next);
-
+fn missing_set_page_builder(set_name: String) -> impl Widget<AppState> {
+    let message = Label::new(format!("Set [{}] no longer exists", set_name))
+        .with_text_size(32.0)
+        .with_text_color(Color::YELLOW);
     let return_to_main = Button::new("Return to Study Sets List").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-            let new_win = WindowDesc::new(list_page_builder(
-                data.catalogue.get_inventory(),
-                data.catalogue.get_all_tags(),
-            ))
-            .title(MAIN_TITLE);
-            ctx.window().close();
-            ctx.new_window(new_win);
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.nav.home();
         },
     );
+    Flex::column()
+        .with_child(message)
+        .with_spacer(50.0)
+        .with_child(return_to_main)
+        .center()
+}
 
-    let card = Flex::column()
-        .with_child(index_label)
-        .with_spacer(20.0)
-        .with_child(word_label)
-        .with_spacer(30.0);
-    let card = card
-        .with_child(text_box)
-        .with_spacer(20.0)
-        .with_child(inputs)
-        .with_spacer(20.0)
-        .with_child(res_label)
-        .with_spacer(20.0)
-        .with_child(eval_results);
-    card.with_spacer(20.0).with_child(return_to_main)
+// Converts romaji typed into an answer box to kana, as chosen for the study set
+struct KanaFormatter {
+    kana_input: KanaInput,
 }
 
-// index is the id of the study set
-fn learn_page_builder(set_index: usize, file_name: String) -> impl Widget<AppState> {
-    let set_name = file_name.clone();
-    let study_set = Storage::read_set_file(file_name);
-    let num_of_cards = study_set.get_num_of_cards();
-    // clone 1 for each purpose
-    let cloned_set_for_words = study_set.clone();
-    let cloned_set_for_remarks = study_set.clone();
-    let cloned_set_for_answers = study_set.clone();
-    let cloned_set_for_show_answers = study_set.clone();
-    let word_label = Label::dynamic(move |data: &AppState, _env| -> String {
-        let word_index = data.curr_indexes[set_index];
-        cloned_set_for_words.get_card(word_index).get_word()
-    })
-    .with_text_size(32.0);
-    let remarks_label = Label::dynamic(move |data: &AppState, _env| -> String {
-        let remark_index = data.curr_indexes[set_index];
-        cloned_set_for_remarks.get_card(remark_index).get_remarks()
-    })
-    .with_text_size(32.0);
-    let text_box = TextBox::new()
-        .with_placeholder("Enter text here")
+impl Formatter<String> for KanaFormatter {
+    fn format(&self, value: &String) -> String {
+        value.clone()
+    }
+
+    fn validate_partial_input(&self, input: &str, sel: &Selection) -> Validation {
+        let converted = kana::convert_typing(input, self.kana_input);
+        if converted == input {
+            return Validation::success();
+        }
+        // keep the caret at the same distance from the end of the text
+        let from_end = input.len() - sel.active.min(input.len());
+        let mut caret = converted.len().saturating_sub(from_end);
+        while !converted.is_char_boundary(caret) {
+            caret -= 1;
+        }
+        Validation::success()
+            .change_text(converted)
+            .change_selection(Selection::caret(caret))
+    }
+
+    fn value(&self, input: &str) -> Result<String, ValidationError> {
+        Ok(kana::convert_finished(input, self.kana_input))
+    }
+}
+
+fn answer_text_box(placeholder: String, kana_input: KanaInput) -> impl Widget<String> {
+    TextBox::new()
+        .with_placeholder(placeholder)
         .with_text_size(24.0)
-        .fix_width(300.0)
-        .lens(AppState::str);
-    let enter = Button::new("Confirm").on_click(move |ctx, data: &mut AppState, _env| -> () {
-        let word_index = data.curr_indexes[set_index];
-        if data.str == cloned_set_for_answers.get_card(word_index).get_ans() {
-            data.res[set_index][word_index] = String::from("Correct!");
-        } else {
-            data.res[set_index][word_index] = String::from("Try Again!");
+        .with_formatter(KanaFormatter { kana_input })
+        .update_data_while_editing(true)
+}
+
+// Card text with any furigana drawn above its kanji
+fn ruby_label(
+    text: String,
+    text_size: f64,
+    text_color: impl Into<KeyOrValue<Color>>,
+    show_readings: bool,
+) -> Flex<AppState> {
+    let text_color = text_color.into();
+    let mut row = Flex::row().cross_axis_alignment(CrossAxisAlignment::End);
+    for segment in furigana::parse_ruby(&text) {
+        let base_label = Label::new(segment.get_base())
+            .with_text_size(text_size)
+            .with_text_color(text_color.clone());
+        match segment.get_reading() {
+            Some(reading) if show_readings => {
+                let reading_label = Label::new(reading)
+                    .with_text_size(text_size / 2.0)
+                    .with_text_color(text_color.clone());
+                row = row.with_child(
+                    Flex::column()
+                        .with_child(reading_label)
+                        .with_child(base_label),
+                );
+            }
+            _ => row = row.with_child(base_label),
         }
-        ctx.request_update();
+    }
+    row
+}
+
+// The card's picture, or nothing if it has none or it cannot be loaded
+fn card_image_builder(set_name: String, card: &Card) -> Box<dyn Widget<AppState>> {
+    if let Some(file_name) = card.get_image() {
+        let image_path = Storage::get_media_path(set_name, file_name);
+        if let Ok(image_buf) = ImageBuf::from_file(image_path) {
+            let image = Image::new(image_buf)
+                .fill_mode(FillStrat::Contain)
+                .fix_height(CARD_IMAGE_HEIGHT);
+            return Box::new(image);
+        }
+    }
+    Box::new(SizedBox::empty())
+}
+
+// Switches to the picture of whichever card is shown
+fn card_image(set_index: usize, study_set: StudySet) -> impl Widget<AppState> {
+    ViewSwitcher::new(
+        move |data: &AppState, _env| data.curr_indexes[set_index],
+        move |word_index, _data, _env| {
+            card_image_builder(study_set.get_set_name(), &study_set.get_card(*word_index))
+        },
+    )
+}
+
+// e.g. "Word 1:" above "[日曜日]" with its furigana
+fn titled_ruby_label(title: String, text: String, text_color: Color) -> Flex<AppState> {
+    let title_label = Label::new(title)
+        .with_text_size(24.0)
+        .with_text_color(text_color);
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(title_label)
+        .with_child(ruby_label(format!("[{}]", text), 24.0, text_color, true))
+}
+
+// keep what was typed for the current card, then move to the previous one
+fn show_prev_card(data: &mut AppState, set_index: usize) {
+    data.answer_to_show.clear();
+    data.card_flipped = false;
+    let ind = data.curr_indexes[set_index];
+    data.input_str[set_index][ind] = data.str.clone();
+    let position = data.get_card_position(set_index);
+    if position >= 1 {
+        data.curr_indexes[set_index] = data.get_card_order(set_index)[position - 1];
+        data.str.clear();
+    }
+}
+
+fn show_next_card(data: &mut AppState, set_index: usize) {
+    data.answer_to_show.clear();
+    data.card_flipped = false;
+    let ind = data.curr_indexes[set_index];
+    data.input_str[set_index][ind] = data.str.clone();
+    let order = data.get_card_order(set_index);
+    let position = data.get_card_position(set_index);
+    if position + 1 < order.len() {
+        data.curr_indexes[set_index] = order[position + 1];
+        data.str.clear();
+    }
+}
+
+fn check_answer(data: &mut AppState, set_index: usize, study_set: &StudySet) {
+    let word_index = data.curr_indexes[set_index];
+    let card = study_set.get_card(word_index);
+    let roles = study_set.get_schema().get_roles(StudyMode::Learn);
+    if is_correct_card_answer(&data.str, &card, QuizMode::Written, roles) {
+        data.res[set_index][word_index] = String::from("Correct!");
+    } else if card.get_card_type() == CardType::Cloze {
+        let blanks = get_cloze_results(&data.str, &card);
+        data.res[set_index][word_index] = format!(
+            "Try Again! ({}/{} blanks correct)",
+            blanks.iter().filter(|correct| **correct).count(),
+            blanks.len()
+        );
+    } else {
+        data.res[set_index][word_index] = String::from("Try Again!");
+    }
+}
+
+fn show_answer(data: &mut AppState, set_index: usize, study_set: &StudySet) {
+    let word_index = data.curr_indexes[set_index];
+    let roles = study_set.get_schema().get_roles(StudyMode::Learn);
+    data.answer_to_show = get_card_answer(&study_set.get_card(word_index), roles);
+}
+
+// Keyboard shortcuts for the learn, test and listen pages, as set in the settings file
+struct StudyKeys {
+    set_index: usize,
+    study_set: StudySet,
+    // only learn checks answers as they are confirmed, and can show them
+    is_learn: bool,
+    key_bindings: KeyBindings,
+}
+
+impl StudyKeys {
+    fn new(set_index: usize, study_set: StudySet, is_learn: bool) -> StudyKeys {
+        StudyKeys {
+            set_index,
+            study_set,
+            is_learn,
+            key_bindings: Storage::read_settings_file().get_key_bindings(),
+        }
+    }
+
+    fn get_actions(&self) -> Vec<KeyAction> {
+        let mut actions = vec![KeyAction::Confirm, KeyAction::Next, KeyAction::Prev];
+        if self.is_learn {
+            actions.push(KeyAction::ToggleAnswer);
+        }
+        actions.push(KeyAction::Back);
+        actions
+    }
+
+    fn get_shortcuts_label(&self) -> Label<AppState> {
+        let descriptions: Vec<String> = self
+            .get_actions()
+            .into_iter()
+            .map(|action| self.key_bindings.get_description(action))
+            .collect();
+        Label::new(descriptions.join("    "))
+            .with_text_size(16.0)
+            .with_text_color(Color::GRAY)
+    }
+
+    fn do_action(&self, data: &mut AppState, action: KeyAction) {
+        match action {
+            KeyAction::Confirm => {
+                if !self.is_learn {
+                    show_next_card(data, self.set_index);
+                    return;
+                }
+                // confirming a correct answer again moves on to the next card
+                let word_index = data.curr_indexes[self.set_index];
+                let was_correct = data.res[self.set_index][word_index] == "Correct!";
+                check_answer(data, self.set_index, &self.study_set);
+                if was_correct && data.res[self.set_index][word_index] == "Correct!" {
+                    show_next_card(data, self.set_index);
+                }
+            }
+            KeyAction::Next => show_next_card(data, self.set_index),
+            KeyAction::Prev => show_prev_card(data, self.set_index),
+            KeyAction::ToggleAnswer => {
+                if data.answer_to_show.is_empty() {
+                    show_answer(data, self.set_index, &self.study_set);
+                } else {
+                    data.answer_to_show.clear();
+                }
+            }
+            KeyAction::Back => {
+                data.answer_to_show.clear();
+                data.nav.home();
+            }
+        }
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for StudyKeys {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut druid::EventCtx<'_, '_>,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::KeyDown(key_event) = event {
+            let action = self.key_bindings.get_action(key_event);
+            if let Some(action) = action.filter(|action| self.get_actions().contains(action)) {
+                self.do_action(data, action);
+                ctx.request_update();
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+}
+
+// Stores the answer being typed and shows the results, ending the set's session
+// and adding it to the set's history
+fn submit_answers(
+    data: &mut AppState,
+    set_index: usize,
+    set_name: String,
+    study_mode: StudyMode,
+    time_limits: Option<TimeLimits>,
+) {
+    let ind = data.curr_indexes[set_index];
+    data.input_str[set_index][ind] = data.str.clone();
+    Storage::delete_session_file(set_name.clone());
+    let user_answers = match study_mode {
+        StudyMode::Flashcards => data.res[set_index].clone(),
+        _ => data.input_str[set_index].clone(),
+    };
+    let card_ids = data.get_card_order(set_index);
+    let study_set = match Storage::read_set_file(set_name.clone()) {
+        Ok(study_set) => study_set,
+        Err(_) => return,
+    };
+    let roles = study_set.get_schema().get_roles(study_mode);
+    let reviews = card_ids
+        .iter()
+        // the set may have been changed outside the app during the test
+        .filter(|id| **id < study_set.get_num_of_cards())
+        .filter_map(|id| {
+            let card = study_set.get_card(*id);
+            get_card_grade(&user_answers[*id], &card, study_mode, roles)
+                .map(|grade| CardReview::new(*id, card.get_word(), grade))
+        })
+        .collect();
+    // the results are still shown if the history cannot be written, e.g. on a full disk
+    if Storage::add_history_record(set_name.clone(), StudyRecord::new(study_mode, reviews)).is_ok()
+    {
+        let set_history = Storage::read_history_file(set_name.clone());
+        data.set_stats
+            .insert(set_name.clone(), SetStats::new(&set_history));
+    }
+    let card_times = match time_limits {
+        Some(_) => data.card_times.clone(),
+        None => Vec::new(),
+    };
+    data.nav.replace(Route::Results {
+        set_name,
+        user_answers,
+        card_ids,
+        study_mode,
+        card_times,
+        time_limits,
     });
+    // the next round covers the whole set again
+    data.card_orders[set_index].clear();
+}
+
+const TIMER_TICK: Duration = Duration::from_millis(200);
+
+// Counts down a timed test, moving on when a card's time is up and submitting when the test's is
+struct TestTimer {
+    set_index: usize,
+    set_name: String,
+    num_of_cards: usize,
+    time_limits: TimeLimits,
+    timer_token: TimerToken,
+    last_tick: Instant,
+}
+
+impl TestTimer {
+    fn new(
+        set_index: usize,
+        set_name: String,
+        num_of_cards: usize,
+        time_limits: TimeLimits,
+    ) -> TestTimer {
+        TestTimer {
+            set_index,
+            set_name,
+            num_of_cards,
+            time_limits,
+            timer_token: TimerToken::INVALID,
+            last_tick: Instant::now(),
+        }
+    }
+
+    // Returns false once the test has been submitted
+    fn tick(&mut self, data: &mut AppState) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_tick).as_secs_f64();
+        self.last_tick = now;
+        if data.card_times.len() != self.num_of_cards {
+            data.card_times.resize(self.num_of_cards, 0.0);
+        }
+        let word_index = data.curr_indexes[self.set_index];
+        data.card_times[word_index] += elapsed;
+        let total_left = self.time_limits.get_total_time_left(&data.card_times);
+        let card_left = self
+            .time_limits
+            .get_card_time_left(data.card_times[word_index]);
+        let is_last_card =
+            data.get_card_position(self.set_index) + 1 >= data.get_card_order(self.set_index).len();
+        if total_left == Some(0.0) || card_left == Some(0.0) && is_last_card {
+            submit_answers(
+                data,
+                self.set_index,
+                self.set_name.clone(),
+                StudyMode::Test,
+                Some(self.time_limits),
+            );
+            return false;
+        }
+        if card_left == Some(0.0) {
+            show_next_card(data, self.set_index);
+        }
+        true
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for TestTimer {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut druid::EventCtx<'_, '_>,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::Timer(token) = event {
+            if *token == self.timer_token {
+                if self.tick(data) {
+                    self.timer_token = ctx.request_timer(TIMER_TICK);
+                }
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.last_tick = Instant::now();
+            self.timer_token = ctx.request_timer(TIMER_TICK);
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+}
+
+// Saves the set's position, answers and results so the session can be resumed: when the card
+// or the results change, and when the page is left with an answer still being typed
+struct SaveSession {
+    set_index: usize,
+    set_name: String,
+    mode: StudyMode,
+    // the session with the answer being typed, written once the page is left
+    unsaved: Option<Session>,
+}
+
+impl SaveSession {
+    fn new(set_index: usize, set_name: String, mode: StudyMode) -> SaveSession {
+        SaveSession {
+            set_index,
+            set_name,
+            mode,
+            unsaved: None,
+        }
+    }
+
+    // None once the set has another id or is gone, e.g. after a reload
+    fn get_session(&self, data: &AppState) -> Option<Session> {
+        if data.catalogue.get_id_by_name(self.set_name.clone()) != Some(self.set_index) {
+            return None;
+        }
+        let mut answers = data.input_str.get(self.set_index)?.clone();
+        // the answer being typed has not been stored yet
+        *answers.get_mut(*data.curr_indexes.get(self.set_index)?)? = data.str.clone();
+        Some(Session::new(
+            self.mode,
+            data.get_card_position(self.set_index),
+            data.get_card_order(self.set_index),
+            answers,
+            data.res.get(self.set_index)?.clone(),
+        ))
+    }
+
+    fn save(&mut self, data: &AppState) {
+        let session = match self.get_session(data) {
+            Some(session) => session,
+            None => return,
+        };
+        // kept to be tried again when the page is left if it cannot be written now
+        self.unsaved = match Storage::update_session_file(self.set_name.clone(), &session) {
+            Ok(()) => None,
+            Err(_) => Some(session),
+        };
+    }
+}
+
+impl Drop for SaveSession {
+    // the page is rebuilt or left, e.g. with the back button. A session that cannot be written
+    // is lost rather than stopping the app
+    fn drop(&mut self) {
+        if let Some(session) = self.unsaved.take() {
+            let _ = Storage::update_session_file(self.set_name.clone(), &session);
+        }
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for SaveSession {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut druid::EventCtx<'_, '_>,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        child.event(ctx, event, data, env);
+        // a finished session is deleted, so it is not saved again
+        if let Route::Results { .. } = data.nav.get_route() {
+            self.unsaved = None;
+        }
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        let set_index = self.set_index;
+        if old_data.curr_indexes.get(set_index) != data.curr_indexes.get(set_index)
+            || old_data.input_str.get(set_index) != data.input_str.get(set_index)
+            || old_data.res.get(set_index) != data.res.get(set_index)
+            || old_data.card_orders.get(set_index) != data.card_orders.get(set_index)
+        {
+            self.save(data);
+        } else if old_data.str != data.str {
+            if let Some(session) = self.get_session(data) {
+                self.unsaved = Some(session);
+            }
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+// Puts a saved session back into the set's inputs and results
+fn resume_session(data: &mut AppState, set_index: usize, session: &Session) {
+    let order = session.get_order();
+    let word_index = order[session.get_position()];
+    data.card_orders[set_index] = order;
+    data.curr_indexes[set_index] = word_index;
+    data.input_str[set_index] = session.get_answers();
+    data.res[set_index] = session.get_results();
+    data.str = data.input_str[set_index][word_index].clone();
+    data.answer_to_show.clear();
+    data.card_flipped = false;
+}
+
+const FOCUS_ANSWER: Selector = Selector::new("quiz_late.focus-answer");
+
+// Puts the caret in the answer box when the page opens, so shortcuts work straight away
+struct FocusOnShow;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for FocusOnShow {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut druid::EventCtx<'_, '_>,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::Command(command) = event {
+            if command.is(FOCUS_ANSWER) {
+                ctx.request_focus();
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            ctx.submit_command(FOCUS_ANSWER.to(ctx.widget_id()));
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+}
+
+// A timed test counts down with the given limits, and is not saved as a session
+fn test_page_builder(
+    set_index: usize,
+    study_set: StudySet,
+    time_limits: Option<TimeLimits>,
+) -> Box<dyn Widget<AppState>> {
+    let set_name = study_set.get_set_name();
+    let session_saver = SaveSession::new(set_index, set_name.clone(), StudyMode::Test);
+    let roles = study_set.get_schema().get_roles(StudyMode::Test);
+    let num_of_cards = study_set.get_num_of_cards();
+    let study_keys = StudyKeys::new(set_index, study_set.clone(), false);
+    let shortcuts_label = study_keys.get_shortcuts_label();
+    let image = card_image(set_index, study_set.clone());
+    // clone 1 for each purpose
+    let cloned_set_for_words = study_set.clone();
+    let cloned_set_for_remarks = study_set.clone();
+    let word_label = ViewSwitcher::new(
+        move |data: &AppState, _env| (data.curr_indexes[set_index], data.hide_readings),
+        move |(word_index, hide_readings), _data, _env| {
+            let word = get_card_prompt(&cloned_set_for_words.get_card(*word_index), roles);
+            Box::new(ruby_label(word, 32.0, theme::TEXT_COLOR, !hide_readings))
+        },
+    );
+    let remarks_label = ViewSwitcher::new(
+        move |data: &AppState, _env| (data.curr_indexes[set_index], data.hide_readings),
+        move |(remark_index, hide_readings), _data, _env| {
+            let remarks = get_card_hint(&cloned_set_for_remarks.get_card(*remark_index), roles);
+            Box::new(ruby_label(remarks, 32.0, theme::TEXT_COLOR, !hide_readings))
+        },
+    );
+
+    let text_box = answer_text_box(String::from("Enter text here"), study_set.get_kana_input())
+        .fix_width(300.0)
+        .lens(AppState::str)
+        .controller(FocusOnShow);
 
     let clear = Button::new("Clear").on_click(move |ctx, data: &mut AppState, _env| -> () {
         let message = String::from("Input Cleared");
@@ -408,52 +1615,24 @@ fn learn_page_builder(set_index: usize, file_name: String) -> impl Widget<AppSta
         ctx.request_update();
     });
     let prev = Button::new("Prev").on_click(move |ctx, data: &mut AppState, _env| -> () {
-        data.answer_to_show.clear();
-        let ind = data.curr_indexes[set_index];
-        data.input_str[set_index][ind] = data.str.clone();
-        if data.curr_indexes[set_index] >= 1 {
-            data.curr_indexes[set_index] -= 1;
-            data.str.clear();
-        }
+        show_prev_card(data, set_index);
         ctx.request_update();
     });
     let next = Button::new("Next").on_click(move |ctx, data: &mut AppState, _env| -> () {
-        data.answer_to_show.clear();
-        let ind = data.curr_indexes[set_index];
-        data.input_str[set_index][ind] = data.str.clone();
-        if data.curr_indexes[set_index] < num_of_cards - 1 {
-            data.curr_indexes[set_index] += 1;
-            data.str.clear();
-        }
+        show_next_card(data, set_index);
         ctx.request_update();
     });
 
-    let show_answer =
-        Button::new("Show Answer").on_click(move |ctx, data: &mut AppState, _env| -> () {
-            let word_index = data.curr_indexes[set_index];
-            data.answer_to_show = cloned_set_for_show_answers.get_card(word_index).get_ans();
-            ctx.request_update();
-        });
-
-    let hide_answer =
-        Button::new("Hide Answer").on_click(move |ctx, data: &mut AppState, _env| -> () {
-            data.answer_to_show.clear();
-            ctx.request_update();
-        });
-
-    let eval_results = Button::new("Calculate Score").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-            data.answer_to_show.clear();
-            let ind = data.curr_indexes[set_index];
-            data.input_str[set_index][ind] = data.str.clone();
-            let results_window = WindowDesc::new(result_page_builder(
-                set_name.clone(),
-                data.input_str[set_index].clone(),
-                study_set.clone(),
-            ))
-            .title("Resuts");
-            ctx.window().close();
-            ctx.new_window(results_window);
+    let name_for_submit = set_name.clone();
+    let eval_results = Button::new("Submit Test").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            submit_answers(
+                data,
+                set_index,
+                name_for_submit.clone(),
+                StudyMode::Test,
+                time_limits,
+            );
         },
     );
 
@@ -462,478 +1641,3201 @@ fn learn_page_builder(set_index: usize, file_name: String) -> impl Widget<AppSta
         data.res[set_index][word_index].clone()
     })
     .with_text_size(24.0);
-
-    let show_answer_label = Label::dynamic(|data: &AppState, _| format!("[{}]", data.answer_to_show.clone()))
-        .with_text_size(24.0)
-        .with_text_color(Color::AQUA);
-
     let index_label = Label::dynamic(move |data: &AppState, _| {
-        format!("{} / {}\n", data.curr_indexes[set_index] + 1, num_of_cards)
+        format!(
+            "{} / {}\n",
+            data.get_card_position(set_index) + 1,
+            data.get_card_order(set_index).len()
+        )
     })
     .with_text_size(24.0);
 
     let inputs = Flex::row()
         .with_child(prev)
-        .with_child(enter)
         .with_child(clear)
         .with_child(next);
 
-    let answer_toggle = Flex::row().with_child(show_answer).with_child(hide_answer);
-
     let return_to_main = Button::new("Return to Study Sets List").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-            data.answer_to_show.clear();
-            let new_win = WindowDesc::new(list_page_builder(
-                data.catalogue.get_inventory(),
-                data.catalogue.get_all_tags(),
-            ))
-            .title(MAIN_TITLE);
-            ctx.window().close();
-            ctx.new_window(new_win);
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.nav.home();
         },
     );
 
+    let hide_readings = Checkbox::new("Hide Readings").lens(AppState::hide_readings);
+
+    let clock_label = Label::dynamic(move |data: &AppState, _| -> String {
+        let time_limits = match time_limits {
+            Some(time_limits) => time_limits,
+            None => return String::new(),
+        };
+        let word_index = data.curr_indexes[set_index];
+        let card_time = data.card_times.get(word_index).copied().unwrap_or(0.0);
+        let mut clocks = Vec::new();
+        if let Some(card_left) = time_limits.get_card_time_left(card_time) {
+            clocks.push(format!("Card: {}", timer::format_countdown(card_left)));
+        }
+        if let Some(total_left) = time_limits.get_total_time_left(&data.card_times) {
+            clocks.push(format!("Test: {}", timer::format_countdown(total_left)));
+        }
+        clocks.join("    ")
+    })
+    .with_text_size(24.0)
+    .with_text_color(Color::YELLOW);
+
     let card = Flex::column()
         .with_child(index_label)
+        .with_child(clock_label)
+        .with_child(hide_readings)
+        .with_spacer(20.0)
+        .with_child(image)
         .with_child(word_label)
-        .with_spacer(10.0)
+        .with_spacer(20.0)
         .with_child(remarks_label)
-        .with_spacer(20.0);
+        .with_spacer(30.0);
     let card = card
         .with_child(text_box)
         .with_spacer(20.0)
         .with_child(inputs)
         .with_spacer(20.0)
-        .with_child(answer_toggle)
-        .with_spacer(20.0)
-        .with_child(show_answer_label)
-        .with_spacer(20.0)
         .with_child(res_label)
-        .with_spacer(10.0)
+        .with_spacer(20.0)
         .with_child(eval_results);
-    card.with_spacer(20.0).with_child(return_to_main)
-}
-
-fn get_scores(user_answers: Vec<String>, study_set: StudySet) -> usize {
-    let mut score = 0;
-    for i in 0..user_answers.len() {
-        if user_answers[i] == study_set.get_card(i).get_ans() {
-            score += 1;
-        }
+    let page = card
+        .with_spacer(20.0)
+        .with_child(return_to_main)
+        .with_spacer(20.0)
+        .with_child(shortcuts_label)
+        .controller(study_keys);
+    match time_limits {
+        Some(time_limits) => Box::new(page.controller(TestTimer::new(
+            set_index,
+            set_name,
+            num_of_cards,
+            time_limits,
+        ))),
+        None => Box::new(page.controller(session_saver)),
     }
-    score
 }
 
-// fn get_ind(ans: String, input: String) -> usize {
-//     let ans_arr = ans.chars().collect::<Vec<_>>();
-//     let input_arr = input.chars().collect::<Vec<_>>();
-//     let min_len = ans_arr.len().min(input_arr.len());
-//     for i in 0..min_len {
-//         if ans_arr[i] != input_arr[i] {
-//             return i;
-//         }
-//     }
-//     min_len
-// }
-
-fn result_page_builder(
-    test_name: String,
-    user_answers: Vec<String>,
-    study_set: StudySet,
-) -> impl Widget<AppState> {
-    let lesson_label: Align<AppState> = Label::new(test_name.clone())
+// Asks for the time limits before starting a timed test
+fn timed_test_setup_page_builder(set_index: usize, set_name: String) -> impl Widget<AppState> {
+    let title_label = Label::new(format!("Timed Test: {}", set_name))
         .with_text_size(32.0)
-        .with_text_color(Color::TEAL)
-        .center();
-    let score_label = Label::new(format!(
-        "You Scored: {}/{}",
-        get_scores(user_answers.clone(), study_set.clone()),
-        user_answers.len()
-    ))
-    .with_text_size(32.0)
-    .with_text_color(Color::AQUA);
-    let mut list: Flex<AppState> = Flex::column()
-        .with_child(lesson_label)
-        .with_spacer(30.0)
-        .with_child(score_label);
-    for i in 0..user_answers.len() {
-        let word = format!("Word:\n[{}]", study_set.get_card(i).get_word());
-        let word_label: Label<AppState> = Label::new(word)
-            .with_text_size(24.0)
-            .with_text_color(Color::FUCHSIA);
-        let mut word_row: Flex<AppState> = Flex::row().with_child(word_label.padding(25.0));
-        let user_ans = format!("Your Answer:\n[{}]", user_answers[i]);
-        if user_answers[i] == study_set.get_card(i).get_ans() {
-            let correct_label: Label<AppState> = Label::new(user_ans)
-                .with_text_size(24.0)
-                .with_text_color(Color::LIME);
-            word_row = word_row.with_child(correct_label.padding(25.0));
-        } else {
-            let wrong_label: Label<AppState> = Label::new(user_ans)
-                .with_text_size(24.0)
-                .with_text_color(Color::MAROON);
-            word_row = word_row.with_child(wrong_label.padding(25.0));
-        }
-        let expected_ans = format!("Correct Answer:\n[{}]", study_set.get_card(i).get_ans());
-        let answer_label: Label<AppState> = Label::new(expected_ans)
-            .with_text_size(24.0)
-            .with_text_color(Color::SILVER);
-        word_row = word_row.with_child(answer_label);
-        list = list.with_child(word_row);
-    }
-    let return_to_main = Button::new("Return to Study Sets List").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-            let new_win = WindowDesc::new(list_page_builder(
-                data.catalogue.get_inventory(),
-                data.catalogue.get_all_tags(),
-            ))
-            .title(MAIN_TITLE);
-            ctx.request_update();
-            ctx.window().close();
-            ctx.new_window(new_win);
-        },
-    );
-    list = list.with_spacer(30.0).with_child(return_to_main);
-    let scroll = Scroll::new(list.padding(20.0)).vertical();
-    scroll
-}
-
-fn add_word_page_builder(set_id: usize, set_name: String) -> impl Widget<AppState> {
-    let word_label = Label::new(String::from("New Word")).with_text_size(32.0);
-    let word = TextBox::new()
-        .with_placeholder("Enter Word")
-        .with_text_size(24.0)
-        .fix_width(300.0)
-        .lens(AppState::word_to_add);
-    let word_ans_label = Label::new(String::from("Answer for New Word")).with_text_size(32.0);
-    let word_ans = TextBox::new()
-        .with_placeholder("Enter Answer for Word")
+        .with_text_color(Color::TEAL);
+    let card_limit_label = Label::new("Seconds per Word").with_text_size(24.0);
+    let card_limit_input = TextBox::new()
+        .with_placeholder("e.g. 20 (blank for no limit)")
         .with_text_size(24.0)
         .fix_width(300.0)
-        .lens(AppState::word_ans_to_add);
-    let word_remark_label = Label::new(String::from("Remarks for New Word")).with_text_size(32.0);
-    let word_remark = TextBox::new()
-        .with_placeholder("Enter Remark for Word")
+        .lens(AppState::card_time_limit);
+    let total_limit_label = Label::new("Time for Whole Test").with_text_size(24.0);
+    let total_limit_input = TextBox::new()
+        .with_placeholder("e.g. 5:00 (blank for no limit)")
         .with_text_size(24.0)
         .fix_width(300.0)
-        .lens(AppState::word_remark_to_add);
-    let save_button =
-        Button::new("Add to Set").on_click(move |ctx, data: &mut AppState, _env| -> () {
-            let mut target_set = Storage::read_set_file(set_name.clone());
-            let new_card = Card::new(
-                target_set.get_num_of_cards(),
-                data.word_to_add.clone(),
-                data.word_ans_to_add.clone(),
-                data.word_remark_to_add.clone(),
+        .lens(AppState::total_time_limit);
+    let get_time_limits = |data: &AppState| {
+        TimeLimits::new(
+            timer::parse_seconds(&data.card_time_limit),
+            timer::parse_seconds(&data.total_time_limit),
+        )
+    };
+    let limits_label = Label::dynamic(move |data: &AppState, _env| -> String {
+        let time_limits = get_time_limits(data);
+        if !time_limits.is_timed() {
+            return String::from("Enter at least one time limit");
+        }
+        format!(
+            "Per Word: {}    Whole Test: {}",
+            timer::format_limit(time_limits.get_per_card()),
+            timer::format_limit(time_limits.get_total())
+        )
+    })
+    .with_text_size(24.0)
+    .with_text_color(Color::YELLOW);
+    let start_button = Button::new("Start Timed Test").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            let time_limits = get_time_limits(data);
+            if !time_limits.is_timed() {
+                return;
+            }
+            let mut settings = Storage::read_settings_file();
+            settings.set_time_limits(time_limits);
+            Storage::update_settings(settings);
+            // a timed test always starts from the first word with no answers
+            let num_of_cards = data.input_str[set_index].len();
+            data.curr_indexes[set_index] = 0;
+            data.input_str[set_index] = vec![String::new(); num_of_cards];
+            data.res[set_index] = vec![String::new(); num_of_cards];
+            data.str.clear();
+            data.card_orders[set_index].clear();
+            if !filter_study_cards(data, set_index, set_name.clone()) {
+                return;
+            }
+            data.card_times = vec![0.0; num_of_cards];
+            data.nav
+                .replace(Route::TimedTest(set_name.clone(), time_limits));
+        },
+    );
+    Flex::column()
+        .with_child(title_label)
+        .with_spacer(50.0)
+        .with_child(card_limit_label)
+        .with_spacer(10.0)
+        .with_child(card_limit_input)
+        .with_spacer(30.0)
+        .with_child(total_limit_label)
+        .with_spacer(10.0)
+        .with_child(total_limit_input)
+        .with_spacer(30.0)
+        .with_child(limits_label)
+        .with_spacer(50.0)
+        .with_child(start_button)
+        .center()
+}
+
+fn test_no_remarks_page_builder(set_index: usize, study_set: StudySet) -> impl Widget<AppState> {
+    let set_name = study_set.get_set_name();
+    let session_saver = SaveSession::new(set_index, set_name.clone(), StudyMode::TestNoRemarks);
+    let roles = study_set.get_schema().get_roles(StudyMode::TestNoRemarks);
+    let study_keys = StudyKeys::new(set_index, study_set.clone(), false);
+    let shortcuts_label = study_keys.get_shortcuts_label();
+    let image = card_image(set_index, study_set.clone());
+    // clone 1 for each purpose
+    let cloned_set_for_words = study_set.clone();
+    let word_label = ViewSwitcher::new(
+        move |data: &AppState, _env| (data.curr_indexes[set_index], data.hide_readings),
+        move |(word_index, hide_readings), _data, _env| {
+            let word = get_card_prompt(&cloned_set_for_words.get_card(*word_index), roles);
+            Box::new(ruby_label(word, 32.0, theme::TEXT_COLOR, !hide_readings))
+        },
+    );
+    // no hint unless one is chosen for this mode in the set's fields
+    let cloned_set_for_hints = study_set.clone();
+    let hint_label = ViewSwitcher::new(
+        move |data: &AppState, _env| (data.curr_indexes[set_index], data.hide_readings),
+        move |(word_index, hide_readings), _data, _env| {
+            let hint = get_card_hint(&cloned_set_for_hints.get_card(*word_index), roles);
+            Box::new(ruby_label(hint, 32.0, theme::TEXT_COLOR, !hide_readings))
+        },
+    );
+
+    let text_box = answer_text_box(String::from("Enter text here"), study_set.get_kana_input())
+        .fix_width(300.0)
+        .lens(AppState::str)
+        .controller(FocusOnShow);
+
+    let clear = Button::new("Clear").on_click(move |ctx, data: &mut AppState, _env| -> () {
+        let message = String::from("Input Cleared");
+        data.str.clear();
+        let word_index = data.curr_indexes[set_index];
+        data.res[set_index][word_index] = message;
+        ctx.request_update();
+    });
+    let prev = Button::new("Prev").on_click(move |ctx, data: &mut AppState, _env| -> () {
+        show_prev_card(data, set_index);
+        ctx.request_update();
+    });
+    let next = Button::new("Next").on_click(move |ctx, data: &mut AppState, _env| -> () {
+        show_next_card(data, set_index);
+        ctx.request_update();
+    });
+
+    let eval_results = Button::new("Submit Test").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            submit_answers(
+                data,
+                set_index,
+                set_name.clone(),
+                StudyMode::TestNoRemarks,
+                None,
             );
-            target_set.add_card(new_card);
-            let window_title = target_set.get_set_name();
-            let lesson_name = window_title.clone();
-            let new_win = WindowDesc::new(view_page_builder(
-                set_id,
-                lesson_name,
-                target_set.get_all_cards(),
-                target_set.get_all_tags(),
-            ))
-            .title(window_title);
-            // clear data
-            data.word_remark_to_add.clear();
-            data.word_ans_to_add.clear();
-            data.word_to_add.clear();
-            data.catalogue.update_set(set_id, target_set);
-            Storage::update_inventory(data.catalogue.clone());
-            data.res[set_id].push(String::new());
-            data.input_str[set_id].push(String::new());
+        },
+    );
+
+    let res_label = Label::dynamic(move |data: &AppState, _| {
+        let word_index = data.curr_indexes[set_index];
+        data.res[set_index][word_index].clone()
+    })
+    .with_text_size(24.0);
+    let index_label = Label::dynamic(move |data: &AppState, _| {
+        format!(
+            "{} / {}\n",
+            data.get_card_position(set_index) + 1,
+            data.get_card_order(set_index).len()
+        )
+    })
+    .with_text_size(24.0);
+
+    let inputs = Flex::row()
+        .with_child(prev)
+        .with_child(clear)
+        .with_child(next);
+
+    let return_to_main = Button::new("Return to Study Sets List").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.nav.home();
+        },
+    );
+
+    let hide_readings = Checkbox::new("Hide Readings").lens(AppState::hide_readings);
+
+    let card = Flex::column()
+        .with_child(index_label)
+        .with_child(hide_readings)
+        .with_spacer(20.0)
+        .with_child(image)
+        .with_child(word_label)
+        .with_child(hint_label)
+        .with_spacer(30.0);
+    let card = card
+        .with_child(text_box)
+        .with_spacer(20.0)
+        .with_child(inputs)
+        .with_spacer(20.0)
+        .with_child(res_label)
+        .with_spacer(20.0)
+        .with_child(eval_results);
+    card.with_spacer(20.0)
+        .with_child(return_to_main)
+        .with_spacer(20.0)
+        .with_child(shortcuts_label)
+        .controller(study_keys)
+        .controller(session_saver)
+}
+
+// Plays each word (its recording, or speech) instead of showing it
+fn listen_page_builder(set_index: usize, study_set: StudySet) -> impl Widget<AppState> {
+    let set_name = study_set.get_set_name();
+    let session_saver = SaveSession::new(set_index, set_name.clone(), StudyMode::Listen);
+    let roles = study_set.get_schema().get_roles(StudyMode::Listen);
+    let study_keys = StudyKeys::new(set_index, study_set.clone(), false);
+    let shortcuts_label = study_keys.get_shortcuts_label();
+    // clone 1 for each purpose
+    let cloned_set_for_play = study_set.clone();
+    let instructions_label = Label::new("Type the word you hear")
+        .with_text_size(32.0)
+        .controller(AutoPlay {
+            set_index,
+            study_set: study_set.clone(),
+            roles,
+            always: true,
+        });
+    let play_again = Button::new("Play Again").on_click(move |_ctx, data: &mut AppState, _env| {
+        let card = cloned_set_for_play.get_card(data.curr_indexes[set_index]);
+        play_card_prompt(&cloned_set_for_play, &card, roles);
+    });
+    let cloned_set_for_hints = study_set.clone();
+    let hint_label = ViewSwitcher::new(
+        move |data: &AppState, _env| data.curr_indexes[set_index],
+        move |word_index, _data, _env| {
+            let hint = get_card_hint(&cloned_set_for_hints.get_card(*word_index), roles);
+            Box::new(ruby_label(hint, 24.0, theme::TEXT_COLOR, true))
+        },
+    );
+
+    let text_box = answer_text_box(String::from("Enter text here"), study_set.get_kana_input())
+        .fix_width(300.0)
+        .lens(AppState::str)
+        .controller(FocusOnShow);
+
+    let clear = Button::new("Clear").on_click(move |ctx, data: &mut AppState, _env| -> () {
+        let message = String::from("Input Cleared");
+        data.str.clear();
+        let word_index = data.curr_indexes[set_index];
+        data.res[set_index][word_index] = message;
+        ctx.request_update();
+    });
+    let prev = Button::new("Prev").on_click(move |ctx, data: &mut AppState, _env| -> () {
+        show_prev_card(data, set_index);
+        ctx.request_update();
+    });
+    let next = Button::new("Next").on_click(move |ctx, data: &mut AppState, _env| -> () {
+        show_next_card(data, set_index);
+        ctx.request_update();
+    });
+
+    let eval_results = Button::new("Submit Test").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            submit_answers(data, set_index, set_name.clone(), StudyMode::Listen, None);
+        },
+    );
+
+    let res_label = Label::dynamic(move |data: &AppState, _| {
+        let word_index = data.curr_indexes[set_index];
+        data.res[set_index][word_index].clone()
+    })
+    .with_text_size(24.0);
+    let index_label = Label::dynamic(move |data: &AppState, _| {
+        format!(
+            "{} / {}\n",
+            data.get_card_position(set_index) + 1,
+            data.get_card_order(set_index).len()
+        )
+    })
+    .with_text_size(24.0);
+
+    let inputs = Flex::row()
+        .with_child(prev)
+        .with_child(clear)
+        .with_child(next);
+
+    let return_to_main = Button::new("Return to Study Sets List").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.nav.home();
+        },
+    );
+
+    let card = Flex::column()
+        .with_child(index_label)
+        .with_spacer(20.0)
+        .with_child(instructions_label)
+        .with_spacer(10.0)
+        .with_child(play_again)
+        .with_spacer(10.0)
+        .with_child(hint_label)
+        .with_spacer(20.0);
+    let card = card
+        .with_child(text_box)
+        .with_spacer(20.0)
+        .with_child(inputs)
+        .with_spacer(20.0)
+        .with_child(res_label)
+        .with_spacer(20.0)
+        .with_child(eval_results);
+    card.with_spacer(20.0)
+        .with_child(return_to_main)
+        .with_spacer(20.0)
+        .with_child(shortcuts_label)
+        .controller(study_keys)
+        .controller(session_saver)
+}
+
+// Plays the word whenever a new card appears, if auto-play is ticked or `always` is set
+struct AutoPlay {
+    set_index: usize,
+    study_set: StudySet,
+    roles: FieldRoles,
+    always: bool,
+}
+
+// A word's own recording is only played when the word is what is asked
+fn play_card_prompt(study_set: &StudySet, card: &Card, roles: FieldRoles) {
+    match roles.get_prompt() {
+        0 => audio::play_card(study_set.get_set_name(), card),
+        _ => {
+            let _ = audio::speak(&get_heard_text(card, roles));
+        }
+    }
+}
+
+impl AutoPlay {
+    fn speak_word(&self, data: &AppState) {
+        let word_index = data.curr_indexes[self.set_index];
+        let card = self.study_set.get_card(word_index);
+        play_card_prompt(&self.study_set, &card, self.roles);
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for AutoPlay {
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            if self.always || data.auto_play {
+                self.speak_word(data);
+            }
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        let old_index = old_data.curr_indexes.get(self.set_index);
+        let is_new_card = old_index != data.curr_indexes.get(self.set_index);
+        if self.always && is_new_card || data.auto_play && (is_new_card || !old_data.auto_play) {
+            self.speak_word(data);
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+// index is the id of the study set
+fn learn_page_builder(set_index: usize, study_set: StudySet) -> impl Widget<AppState> {
+    let set_name = study_set.get_set_name();
+    let session_saver = SaveSession::new(set_index, set_name.clone(), StudyMode::Learn);
+    let roles = study_set.get_schema().get_roles(StudyMode::Learn);
+    let study_keys = StudyKeys::new(set_index, study_set.clone(), true);
+    let shortcuts_label = study_keys.get_shortcuts_label();
+    let image = card_image(set_index, study_set.clone());
+    // clone 1 for each purpose
+    let cloned_set_for_words = study_set.clone();
+    let cloned_set_for_remarks = study_set.clone();
+    let cloned_set_for_answers = study_set.clone();
+    let cloned_set_for_show_answers = study_set.clone();
+    let cloned_set_for_play = study_set.clone();
+    let word_label = ViewSwitcher::new(
+        move |data: &AppState, _env| data.curr_indexes[set_index],
+        move |word_index, _data, _env| {
+            let word = get_card_prompt(&cloned_set_for_words.get_card(*word_index), roles);
+            Box::new(ruby_label(word, 32.0, theme::TEXT_COLOR, true))
+        },
+    )
+    .controller(AutoPlay {
+        set_index,
+        study_set: study_set.clone(),
+        roles,
+        always: false,
+    });
+    let remarks_label = ViewSwitcher::new(
+        move |data: &AppState, _env| data.curr_indexes[set_index],
+        move |remark_index, _data, _env| {
+            let remarks = get_card_hint(&cloned_set_for_remarks.get_card(*remark_index), roles);
+            Box::new(ruby_label(remarks, 32.0, theme::TEXT_COLOR, true))
+        },
+    );
+    let text_box = answer_text_box(String::from("Enter text here"), study_set.get_kana_input())
+        .fix_width(300.0)
+        .lens(AppState::str)
+        .controller(FocusOnShow);
+    let enter = Button::new("Confirm").on_click(move |ctx, data: &mut AppState, _env| -> () {
+        check_answer(data, set_index, &cloned_set_for_answers);
+        ctx.request_update();
+    });
+
+    let clear = Button::new("Clear").on_click(move |ctx, data: &mut AppState, _env| -> () {
+        let message = String::from("Input Cleared");
+        data.str.clear();
+        let word_index = data.curr_indexes[set_index];
+        data.res[set_index][word_index] = message;
+        ctx.request_update();
+    });
+    let prev = Button::new("Prev").on_click(move |ctx, data: &mut AppState, _env| -> () {
+        show_prev_card(data, set_index);
+        ctx.request_update();
+    });
+    let next = Button::new("Next").on_click(move |ctx, data: &mut AppState, _env| -> () {
+        show_next_card(data, set_index);
+        ctx.request_update();
+    });
+
+    let show_answer =
+        Button::new("Show Answer").on_click(move |ctx, data: &mut AppState, _env| -> () {
+            show_answer(data, set_index, &cloned_set_for_show_answers);
             ctx.request_update();
-            ctx.window().close();
-            ctx.new_window(new_win);
         });
-    Flex::column()
+
+    let hide_answer =
+        Button::new("Hide Answer").on_click(move |ctx, data: &mut AppState, _env| -> () {
+            data.answer_to_show.clear();
+            ctx.request_update();
+        });
+
+    let eval_results = Button::new("Calculate Score").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.answer_to_show.clear();
+            submit_answers(data, set_index, set_name.clone(), StudyMode::Learn, None);
+        },
+    );
+
+    let res_label = Label::dynamic(move |data: &AppState, _| {
+        let word_index = data.curr_indexes[set_index];
+        data.res[set_index][word_index].clone()
+    })
+    .with_text_size(24.0);
+
+    let show_answer_label = ViewSwitcher::new(
+        |data: &AppState, _env| data.answer_to_show.clone(),
+        |answer, _data, _env| {
+            Box::new(ruby_label(format!("[{}]", answer), 24.0, Color::AQUA, true))
+        },
+    );
+
+    let index_label = Label::dynamic(move |data: &AppState, _| {
+        format!(
+            "{} / {}\n",
+            data.get_card_position(set_index) + 1,
+            data.get_card_order(set_index).len()
+        )
+    })
+    .with_text_size(24.0);
+
+    let inputs = Flex::row()
+        .with_child(prev)
+        .with_child(enter)
+        .with_child(clear)
+        .with_child(next);
+
+    let answer_toggle = Flex::row().with_child(show_answer).with_child(hide_answer);
+
+    let play = Button::new("Play").on_click(move |_ctx, data: &mut AppState, _env| -> () {
+        let word_index = data.curr_indexes[set_index];
+        let card = cloned_set_for_play.get_card(word_index);
+        play_card_prompt(&cloned_set_for_play, &card, roles);
+    });
+    let auto_play = Checkbox::new("Auto-Play Words").lens(AppState::auto_play);
+    let engine_label = Label::new(format!("Speech: {}", audio::get_engine_name()))
+        .with_text_size(16.0)
+        .with_text_color(Color::GRAY);
+    let audio_row = Flex::row()
+        .with_child(play)
+        .with_spacer(10.0)
+        .with_child(auto_play)
+        .with_spacer(10.0)
+        .with_child(engine_label);
+
+    let return_to_main = Button::new("Return to Study Sets List").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.answer_to_show.clear();
+            data.nav.home();
+        },
+    );
+
+    let card = Flex::column()
+        .with_child(index_label)
+        .with_child(image)
         .with_child(word_label)
         .with_spacer(10.0)
-        .with_child(word)
-        .with_spacer(50.0)
-        .with_child(word_ans_label)
+        .with_child(audio_row)
         .with_spacer(10.0)
-        .with_child(word_ans)
-        .with_spacer(50.0)
-        .with_child(word_remark_label)
+        .with_child(remarks_label)
+        .with_spacer(20.0);
+    let card = card
+        .with_child(text_box)
+        .with_spacer(20.0)
+        .with_child(inputs)
+        .with_spacer(20.0)
+        .with_child(answer_toggle)
+        .with_spacer(20.0)
+        .with_child(show_answer_label)
+        .with_spacer(20.0)
+        .with_child(res_label)
+        .with_spacer(10.0)
+        .with_child(eval_results);
+    card.with_spacer(20.0)
+        .with_child(return_to_main)
+        .with_spacer(20.0)
+        .with_child(shortcuts_label)
+        .controller(study_keys)
+        .controller(session_saver)
+}
+
+fn grade_card(data: &mut AppState, set_index: usize, set_name: String, grade: Grade) {
+    let word_index = data.curr_indexes[set_index];
+    data.res[set_index][word_index] = grade.get_label();
+    let position = data.get_card_position(set_index);
+    if position + 1 >= data.get_card_order(set_index).len() {
+        submit_answers(data, set_index, set_name, StudyMode::Flashcards, None);
+    } else {
+        show_next_card(data, set_index);
+    }
+}
+
+// Shows the word, then its answer and remarks to grade from memory, for cards that are
+// too long to type
+fn flashcards_page_builder(set_index: usize, study_set: StudySet) -> impl Widget<AppState> {
+    let set_name = study_set.get_set_name();
+    let session_saver = SaveSession::new(set_index, set_name.clone(), StudyMode::Flashcards);
+    let roles = study_set.get_schema().get_roles(StudyMode::Flashcards);
+    let image = card_image(set_index, study_set.clone());
+    // clone 1 for each purpose
+    let cloned_set_for_words = study_set.clone();
+    let cloned_set_for_answers = study_set.clone();
+    let name_for_grades = set_name.clone();
+    let word_label = ViewSwitcher::new(
+        move |data: &AppState, _env| data.curr_indexes[set_index],
+        move |word_index, _data, _env| {
+            let word = get_card_prompt(&cloned_set_for_words.get_card(*word_index), roles);
+            Box::new(ruby_label(word, 32.0, theme::TEXT_COLOR, true))
+        },
+    );
+    let answer_side = ViewSwitcher::new(
+        move |data: &AppState, _env| (data.curr_indexes[set_index], data.card_flipped),
+        move |(word_index, card_flipped), _data, _env| -> Box<dyn Widget<AppState>> {
+            if !*card_flipped {
+                let flip = Button::new("Show Answer").on_click(
+                    |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                        data.card_flipped = true;
+                    },
+                );
+                return Box::new(flip);
+            }
+            let card = cloned_set_for_answers.get_card(*word_index);
+            let mut grade_row = Flex::row();
+            for grade in Grade::get_all() {
+                let name_for_grade = name_for_grades.clone();
+                let grade_button = Button::new(grade.get_label()).on_click(
+                    move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                        grade_card(data, set_index, name_for_grade.clone(), grade);
+                    },
+                );
+                grade_row = grade_row.with_child(grade_button).with_spacer(10.0);
+            }
+            let answer_title = String::from("Answer:");
+            let remarks_title = String::from("Remarks:");
+            Box::new(
+                Flex::column()
+                    .with_child(titled_ruby_label(
+                        answer_title,
+                        get_card_answer(&card, roles),
+                        Color::SILVER,
+                    ))
+                    .with_spacer(10.0)
+                    .with_child(titled_ruby_label(
+                        remarks_title,
+                        get_card_hint(&card, roles),
+                        Color::OLIVE,
+                    ))
+                    .with_spacer(20.0)
+                    .with_child(grade_row),
+            )
+        },
+    );
+    let grade_label = Label::dynamic(move |data: &AppState, _| {
+        let word_index = data.curr_indexes[set_index];
+        let grade = &data.res[set_index][word_index];
+        if grade.is_empty() {
+            String::new()
+        } else {
+            format!("Graded: {}", grade)
+        }
+    })
+    .with_text_size(24.0);
+    let index_label = Label::dynamic(move |data: &AppState, _| {
+        format!(
+            "{} / {}\n",
+            data.get_card_position(set_index) + 1,
+            data.get_card_order(set_index).len()
+        )
+    })
+    .with_text_size(24.0);
+
+    let prev = Button::new("Prev").on_click(move |ctx, data: &mut AppState, _env| -> () {
+        show_prev_card(data, set_index);
+        ctx.request_update();
+    });
+    let next = Button::new("Next").on_click(move |ctx, data: &mut AppState, _env| -> () {
+        show_next_card(data, set_index);
+        ctx.request_update();
+    });
+    let inputs = Flex::row().with_child(prev).with_child(next);
+
+    let eval_results = Button::new("Finish").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            submit_answers(
+                data,
+                set_index,
+                set_name.clone(),
+                StudyMode::Flashcards,
+                None,
+            );
+        },
+    );
+
+    let return_to_main = Button::new("Return to Study Sets List").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.card_flipped = false;
+            data.nav.home();
+        },
+    );
+
+    Flex::column()
+        .with_child(index_label)
+        .with_child(image)
+        .with_child(word_label)
+        .with_spacer(30.0)
+        .with_child(answer_side)
+        .with_spacer(20.0)
+        .with_child(grade_label)
+        .with_spacer(20.0)
+        .with_child(inputs)
+        .with_spacer(20.0)
+        .with_child(eval_results)
+        .with_spacer(20.0)
+        .with_child(return_to_main)
+        .controller(session_saver)
+}
+
+// Answers with furigana also accept the text without it, or just the reading
+fn is_correct_answer(input: &str, ans: &str) -> bool {
+    input == ans
+        || furigana::has_ruby(ans)
+            && (input == furigana::strip_ruby(ans) || input == furigana::to_reading(ans))
+}
+
+// What the user is shown, and so which answers are accepted
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum QuizMode {
+    // the word is shown, its answer is typed
+    Written,
+    // the word is heard, the word or its answer is typed
+    Listening,
+}
+
+impl QuizMode {
+    fn for_study(study_mode: StudyMode) -> QuizMode {
+        match study_mode {
+            StudyMode::Listen => QuizMode::Listening,
+            _ => QuizMode::Written,
+        }
+    }
+}
+
+// What is shown when studying a card: cloze sentences have their answers blanked out
+fn get_card_prompt(card: &Card, roles: FieldRoles) -> String {
+    match card.get_card_type() {
+        CardType::Basic => card.get_field(roles.get_prompt()),
+        CardType::Cloze => cloze::to_blanks(&card.get_word()),
+    }
+}
+
+// What is typed for a card: the answers of a cloze card, in order
+fn get_card_answer(card: &Card, roles: FieldRoles) -> String {
+    match card.get_card_type() {
+        CardType::Basic => card.get_field(roles.get_answer()),
+        CardType::Cloze => cloze::get_deletions(&card.get_word()).join("、"),
+    }
+}
+
+fn get_card_hint(card: &Card, roles: FieldRoles) -> String {
+    roles
+        .get_hint()
+        .map_or(String::new(), |hint| card.get_field(hint))
+}
+
+// Whether each blank of a cloze card was filled in correctly
+fn get_cloze_results(input: &str, card: &Card) -> Vec<bool> {
+    let deletions = cloze::get_deletions(&card.get_word());
+    let answers = cloze::split_answers(input, deletions.len());
+    deletions
+        .iter()
+        .enumerate()
+        .map(|(i, deletion)| {
+            answers
+                .get(i)
+                .is_some_and(|answer| is_correct_answer(answer, deletion))
+        })
+        .collect()
+}
+
+// What is played in listen mode, with any cloze blanks filled in
+fn get_heard_text(card: &Card, roles: FieldRoles) -> String {
+    cloze::fill_deletions(&card.get_field(roles.get_prompt()))
+}
+
+fn is_correct_card_answer(input: &str, card: &Card, mode: QuizMode, roles: FieldRoles) -> bool {
+    let is_correct_written = match card.get_card_type() {
+        CardType::Basic => is_correct_answer(input, &card.get_field(roles.get_answer())),
+        CardType::Cloze => {
+            let blanks = get_cloze_results(input, card);
+            !blanks.is_empty() && blanks.iter().all(|correct| *correct)
+        }
+    };
+    match mode {
+        QuizMode::Written => is_correct_written,
+        QuizMode::Listening => {
+            is_correct_written || is_correct_answer(input, &get_heard_text(card, roles))
+        }
+    }
+}
+
+fn get_scores(
+    user_answers: Vec<String>,
+    card_ids: Vec<usize>,
+    study_set: StudySet,
+    mode: QuizMode,
+    roles: FieldRoles,
+) -> usize {
+    let mut score = 0;
+    for i in card_ids {
+        if is_correct_card_answer(&user_answers[i], &study_set.get_card(i), mode, roles) {
+            score += 1;
+        }
+    }
+    score
+}
+
+// Self-graded for flashcards, otherwise Good for a correct answer and Again for a wrong one
+fn get_card_grade(
+    user_answer: &str,
+    card: &Card,
+    study_mode: StudyMode,
+    roles: FieldRoles,
+) -> Option<Grade> {
+    match study_mode {
+        StudyMode::Flashcards => Grade::from_label(user_answer),
+        _ if is_correct_card_answer(user_answer, card, QuizMode::for_study(study_mode), roles) => {
+            Some(Grade::Good)
+        }
+        _ => Some(Grade::Again),
+    }
+}
+
+fn get_grade_color(grade: Option<Grade>) -> Color {
+    match grade {
+        Some(Grade::Again) => Color::MAROON,
+        Some(Grade::Hard) => Color::YELLOW,
+        Some(Grade::Good) => Color::LIME,
+        Some(Grade::Easy) => Color::AQUA,
+        None => Color::GRAY,
+    }
+}
+
+// Spellings of the answer a wrong answer is compared with, without furigana markup
+fn get_expected_answers(card: &Card, mode: QuizMode, roles: FieldRoles) -> Vec<String> {
+    let mut texts = vec![get_card_answer(card, roles)];
+    if mode == QuizMode::Listening {
+        texts.push(get_heard_text(card, roles));
+    }
+    let mut expected = Vec::new();
+    for text in texts {
+        if furigana::has_ruby(&text) {
+            expected.push(furigana::strip_ruby(&text));
+            expected.push(furigana::to_reading(&text));
+        } else {
+            expected.push(text);
+        }
+    }
+    expected
+}
+
+// The typed answer with the expected characters it is missing, against whichever spelling is closest
+fn answer_diff_label(
+    input: &str,
+    card: &Card,
+    mode: QuizMode,
+    roles: FieldRoles,
+) -> Flex<AppState> {
+    let segments = get_expected_answers(card, mode, roles)
+        .iter()
+        .map(|expected| diff::diff_chars(input, expected))
+        .min_by_key(|segments| diff::count_differences(segments))
+        .unwrap_or_default();
+    let mut row = Flex::row().with_child(Label::new("[").with_text_size(24.0));
+    for segment in segments {
+        let color = match segment.get_kind() {
+            DiffKind::Same => Color::SILVER,
+            DiffKind::Extra => Color::RED,
+            DiffKind::Missing => Color::YELLOW,
+        };
+        let text = match segment.get_kind() {
+            DiffKind::Missing => format!("({})", segment.get_text()),
+            _ => segment.get_text(),
+        };
+        row.add_child(Label::new(text).with_text_size(24.0).with_text_color(color));
+    }
+    row.with_child(Label::new("]").with_text_size(24.0))
+}
+
+fn result_page_builder(
+    study_set: StudySet,
+    user_answers: Vec<String>,
+    card_ids: Vec<usize>,
+    study_mode: StudyMode,
+    card_times: Vec<f64>,
+    time_limits: Option<TimeLimits>,
+    folder_sets_left: Vec<String>,
+) -> impl Widget<AppState> {
+    let mode = QuizMode::for_study(study_mode);
+    let roles = study_set.get_schema().get_roles(study_mode);
+    let test_name = study_set.get_set_name();
+    let lesson_label: Align<AppState> = Label::new(test_name.clone())
+        .with_text_size(32.0)
+        .with_text_color(Color::TEAL)
+        .center();
+    let score = match study_mode {
+        StudyMode::Flashcards => {
+            let grade_counts: Vec<String> = Grade::get_all()
+                .into_iter()
+                .map(|grade| {
+                    let count = card_ids
+                        .iter()
+                        .filter(|id| Grade::from_label(&user_answers[**id]) == Some(grade))
+                        .count();
+                    format!("{}: {}", grade.get_label(), count)
+                })
+                .collect();
+            grade_counts.join("    ")
+        }
+        _ => format!(
+            "You Scored: {}/{}",
+            get_scores(
+                user_answers.clone(),
+                card_ids.clone(),
+                study_set.clone(),
+                mode,
+                roles
+            ),
+            card_ids.len()
+        ),
+    };
+    let score_label = Label::new(score)
+        .with_text_size(32.0)
+        .with_text_color(Color::AQUA);
+    let mut list: Flex<AppState> = Flex::column()
+        .with_child(lesson_label)
+        .with_spacer(30.0)
+        .with_child(score_label);
+    if !card_times.is_empty() {
+        let total_time: f64 = card_times.iter().sum();
+        let time_label = Label::new(format!("Total Time: {:.1}s", total_time))
+            .with_text_size(24.0)
+            .with_text_color(Color::AQUA);
+        list = list.with_spacer(10.0).with_child(time_label);
+    }
+    let mut missed_ids = Vec::new();
+    for i in card_ids {
+        let word_title = String::from("Word:");
+        let word_label = titled_ruby_label(
+            word_title,
+            get_card_prompt(&study_set.get_card(i), roles),
+            Color::FUCHSIA,
+        );
+        let mut word_row: Flex<AppState> = Flex::row().with_child(word_label.padding(25.0));
+        if study_mode == StudyMode::Flashcards {
+            let grade = Grade::from_label(&user_answers[i]);
+            if grade.is_none_or(|grade| grade == Grade::Again) {
+                missed_ids.push(i);
+            }
+            let grade_label: Label<AppState> = Label::new(format!(
+                "Your Grade:\n[{}]",
+                grade.map_or(String::from("Not Graded"), |grade| grade.get_label())
+            ))
+            .with_text_size(24.0)
+            .with_text_color(get_grade_color(grade));
+            word_row = word_row.with_child(grade_label.padding(25.0));
+        } else if is_correct_card_answer(&user_answers[i], &study_set.get_card(i), mode, roles) {
+            let user_ans = format!("Your Answer:\n[{}]", user_answers[i]);
+            let correct_label: Label<AppState> = Label::new(user_ans)
+                .with_text_size(24.0)
+                .with_text_color(Color::LIME);
+            word_row = word_row.with_child(correct_label.padding(25.0));
+        } else {
+            missed_ids.push(i);
+            let card = study_set.get_card(i);
+            let mut wrong_label = Flex::column()
+                .with_child(
+                    Label::new("Your Answer:")
+                        .with_text_size(24.0)
+                        .with_text_color(Color::MAROON),
+                )
+                .with_child(answer_diff_label(&user_answers[i], &card, mode, roles));
+            if card.get_card_type() == CardType::Cloze {
+                let blanks = get_cloze_results(&user_answers[i], &card);
+                let blanks_label = Label::new(format!(
+                    "{}/{} blanks correct",
+                    blanks.iter().filter(|correct| **correct).count(),
+                    blanks.len()
+                ))
+                .with_text_size(20.0)
+                .with_text_color(Color::MAROON);
+                wrong_label = wrong_label.with_child(blanks_label);
+            }
+            word_row = word_row.with_child(wrong_label.padding(25.0));
+        }
+        let answer_title = String::from("Correct Answer:");
+        let answer_label = titled_ruby_label(
+            answer_title,
+            get_card_answer(&study_set.get_card(i), roles),
+            Color::SILVER,
+        );
+        word_row = word_row.with_child(answer_label);
+        if let Some(card_time) = card_times.get(i) {
+            let time_label = Label::new(format!("Time:\n[{:.1}s]", card_time))
+                .with_text_size(24.0)
+                .with_text_color(Color::SILVER);
+            word_row = word_row.with_child(time_label.padding(25.0));
+        }
+        list = list.with_child(word_row);
+    }
+    let return_to_main = Button::new("Return to Study Sets List").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.nav.home();
+        },
+    );
+    if !missed_ids.is_empty() {
+        let set_name = study_set.get_set_name();
+        let retry_button = Button::new(format!("Retry Incorrect Only ({})", missed_ids.len()))
+            .on_click(
+                move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                    let set_index = match data.catalogue.get_id_by_name(set_name.clone()) {
+                        Some(set_index) => set_index,
+                        None => return,
+                    };
+                    for id in &missed_ids {
+                        data.input_str[set_index][*id].clear();
+                        data.res[set_index][*id].clear();
+                    }
+                    data.card_orders[set_index] = missed_ids.clone();
+                    data.curr_indexes[set_index] = missed_ids[0];
+                    data.str.clear();
+                    data.answer_to_show.clear();
+                    // a timed test is retried with the same time limits
+                    match time_limits {
+                        Some(time_limits) => {
+                            data.card_times = vec![0.0; data.input_str[set_index].len()];
+                            data.nav
+                                .replace(Route::TimedTest(set_name.clone(), time_limits));
+                        }
+                        None => data
+                            .nav
+                            .replace(Route::for_study(study_mode, set_name.clone())),
+                    }
+                },
+            );
+        list = list.with_spacer(30.0).with_child(retry_button);
+    }
+    if let Some(next_set_name) = folder_sets_left.first() {
+        let next_button = Button::new(format!(
+            "Next in Folder: {} ({} sets left)",
+            next_set_name,
+            folder_sets_left.len()
+        ))
+        .on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                if let Some(route) = next_folder_set(data, study_mode) {
+                    data.str.clear();
+                    data.answer_to_show.clear();
+                    data.nav.replace(route);
+                }
+            },
+        );
+        list = list.with_spacer(30.0).with_child(next_button);
+    }
+    list = list.with_spacer(30.0).with_child(return_to_main);
+    Scroll::new(list.padding(20.0))
+        .vertical()
+        .controller(KeepScroll::new())
+}
+
+// The text typed for the set's field at the given position, see FieldSchema
+fn get_field_to_add(data: &AppState, field: usize) -> String {
+    match field {
+        0 => data.word_to_add.clone(),
+        1 => data.word_ans_to_add.clone(),
+        2 => data.word_remark_to_add.clone(),
+        _ => data
+            .extra_fields_to_add
+            .get(field - 3)
+            .cloned()
+            .unwrap_or_default(),
+    }
+}
+
+fn field_to_add_lens(field: usize) -> impl Lens<AppState, String> {
+    druid::lens::Map::new(
+        move |data: &AppState| get_field_to_add(data, field),
+        move |data: &mut AppState, text: String| match field {
+            0 => data.word_to_add = text,
+            1 => data.word_ans_to_add = text,
+            2 => data.word_remark_to_add = text,
+            _ => {
+                if data.extra_fields_to_add.len() < field - 2 {
+                    data.extra_fields_to_add.resize(field - 2, String::new());
+                }
+                data.extra_fields_to_add[field - 3] = text;
+            }
+        },
+    )
+}
+
+// The second field takes romaji input, as it holds the answer in sets without a schema
+fn field_text_box(
+    field: usize,
+    placeholder: String,
+    kana_input: KanaInput,
+) -> Box<dyn Widget<AppState>> {
+    if field == 1 {
+        Box::new(
+            answer_text_box(placeholder, kana_input)
+                .fix_width(300.0)
+                .lens(field_to_add_lens(field)),
+        )
+    } else {
+        Box::new(
+            TextBox::new()
+                .with_placeholder(placeholder)
+                .with_text_size(24.0)
+                .fix_width(300.0)
+                .lens(field_to_add_lens(field)),
+        )
+    }
+}
+
+fn add_word_page_builder(set_id: usize, study_set: StudySet) -> impl Widget<AppState> {
+    let set_name = study_set.get_set_name();
+    let schema = study_set.get_schema();
+    let kana_input = study_set.get_kana_input();
+    let first_field = schema.get_field_name(0);
+    let mut field_inputs = Flex::column();
+    for (field, field_name) in schema.get_fields().into_iter().enumerate() {
+        match field {
+            0 => {}
+            1 => field_inputs = field_inputs.with_spacer(40.0),
+            _ => field_inputs = field_inputs.with_spacer(50.0),
+        }
+        let (title, placeholder) = match field {
+            0 => (
+                format!("New {}", field_name),
+                format!("Enter {}", field_name),
+            ),
+            _ => (
+                format!("{} for New {}", field_name, first_field),
+                format!("Enter {} for {}", field_name, first_field),
+            ),
+        };
+        field_inputs = field_inputs
+            .with_child(Label::new(title).with_text_size(32.0))
+            .with_spacer(10.0)
+            .with_child(field_text_box(field, placeholder, kana_input));
+        if field == 0 {
+            let cloze_label = Label::dynamic(|data: &AppState, _env| {
+                let num_of_deletions = cloze::get_deletions(&data.word_to_add).len();
+                if num_of_deletions == 0 {
+                    String::new()
+                } else {
+                    format!("Cloze card with {} blank(s)", num_of_deletions)
+                }
+            })
+            .with_text_size(20.0)
+            .with_text_color(Color::TEAL);
+            field_inputs = field_inputs.with_spacer(10.0).with_child(cloze_label);
+        }
+    }
+    let duplicate_finder = DuplicateFinder::new(Storage::read_data());
+    let duplicate_label = Label::dynamic(move |data: &AppState, _env| -> String {
+        if !data.warn_duplicates {
+            return String::new();
+        }
+        let existing_cards = duplicate_finder
+            .get_existing_cards(data.word_to_add.clone(), data.word_ans_to_add.clone());
+        if existing_cards.is_empty() {
+            return String::new();
+        }
+        let mut lines = vec![String::from("Similar words already exist:")];
+        for (field, card_ref) in existing_cards.iter().take(MAX_DUPLICATE_WARNINGS) {
+            let card = card_ref.get_card();
+            lines.push(format!(
+                "{} Word {} [{}] (same {})",
+                card_ref.get_set_name(),
+                card.get_id() + 1,
+                card.get_word(),
+                field.get_label()
+            ));
+        }
+        lines.join("\n")
+    })
+    .with_text_size(20.0)
+    .with_text_color(Color::YELLOW);
+    let duplicate_toggle =
+        Checkbox::new("Warn About Similar Words").lens(AppState::warn_duplicates);
+    let save_button =
+        Button::new("Add to Set").on_click(move |ctx, data: &mut AppState, _env| -> () {
+            let mut target_set = match Storage::read_set_file(set_name.clone()) {
+                Ok(target_set) => target_set,
+                Err(_) => return,
+            };
+            let mut new_card = Card::new(
+                target_set.get_num_of_cards(),
+                data.word_to_add.clone(),
+                data.word_ans_to_add.clone(),
+                data.word_remark_to_add.clone(),
+            );
+            new_card.set_card_type(cloze::get_card_type(&data.word_to_add));
+            let num_of_extra_fields = target_set
+                .get_schema()
+                .get_num_of_fields()
+                .saturating_sub(3);
+            let mut extra_fields = data.extra_fields_to_add.clone();
+            extra_fields.resize(num_of_extra_fields, String::new());
+            new_card.set_extra_fields(extra_fields);
+            target_set.add_card(new_card);
+            // clear data
+            data.extra_fields_to_add.clear();
+            data.word_remark_to_add.clear();
+            data.word_ans_to_add.clear();
+            data.word_to_add.clear();
+            data.catalogue.update_set(set_id, target_set);
+            Storage::update_inventory(data.catalogue.clone());
+            data.res[set_id].push(String::new());
+            data.input_str[set_id].push(String::new());
+            data.nav.back();
+            ctx.request_update();
+        });
+    Flex::column()
+        .with_child(field_inputs)
+        .with_spacer(30.0)
+        .with_child(duplicate_toggle)
+        .with_spacer(10.0)
+        .with_child(duplicate_label)
+        .with_spacer(30.0)
+        .with_child(save_button)
+        .center()
+}
+
+fn place_holder_helper(prev: String, input: String) -> String {
+    if is_valid(input.clone()) {
+        let temp = &input;
+        return temp.trim().to_string();
+    }
+    prev
+}
+
+fn edit_word_page_builder(
+    set_id: usize,
+    word_id: usize,
+    curr_fields: Vec<String>,
+    curr_audio: Option<String>,
+    curr_image: Option<String>,
+    study_set: StudySet,
+) -> impl Widget<AppState> {
+    let set_name = study_set.get_set_name();
+    let schema = study_set.get_schema();
+    let kana_input = study_set.get_kana_input();
+    let first_field = schema.get_field_name(0);
+    let mut field_inputs = Flex::column();
+    for (field, field_name) in schema.get_fields().into_iter().enumerate() {
+        let title = match field {
+            0 => format!("{} {}", field_name, word_id + 1),
+            _ => format!("{} for {}", field_name, first_field),
+        };
+        field_inputs = field_inputs
+            .with_child(Label::new(title).with_text_size(32.0))
+            .with_spacer(10.0)
+            .with_child(field_text_box(
+                field,
+                curr_fields[field].clone(),
+                kana_input,
+            ))
+            .with_spacer(50.0);
+    }
+
+    let media_path = TextBox::new()
+        .with_placeholder("Enter Path to Audio or Image File")
+        .with_text_size(24.0)
+        .fix_width(300.0)
+        .lens(AppState::media_path_to_add);
+    let mut media_rows = Flex::column();
+    for (kind, curr_file) in [
+        (MediaKind::Audio, curr_audio.clone()),
+        (MediaKind::Image, curr_image.clone()),
+    ] {
+        let media_label = Label::new(format!(
+            "{}: {}",
+            kind.get_label(),
+            curr_file.unwrap_or(String::from("None"))
+        ))
+        .with_text_size(24.0);
+        let name_for_attach = set_name.clone();
+        let attach_button = Button::new(format!("Attach {}", kind.get_label())).on_click(
+            move |_ctx, data: &mut AppState, _env| {
+                // paths copied from a file manager may come quoted
+                let source_path = PathBuf::from(data.media_path_to_add.trim().trim_matches('"'));
+                match Storage::add_media_file(name_for_attach.clone(), &source_path) {
+                    Ok(file_name) => {
+                        data.media_path_to_add.clear();
+                        data.media_message.clear();
+                        set_card_media(
+                            data,
+                            set_id,
+                            name_for_attach.clone(),
+                            word_id,
+                            kind,
+                            Some(file_name),
+                        );
+                    }
+                    Err(err) => data.media_message = err,
+                }
+            },
+        );
+        let name_for_remove = set_name.clone();
+        let remove_button = Button::new(format!("Remove {}", kind.get_label())).on_click(
+            move |_ctx, data: &mut AppState, _env| {
+                set_card_media(data, set_id, name_for_remove.clone(), word_id, kind, None);
+            },
+        );
+        let mut media_row = Flex::row()
+            .with_child(media_label)
+            .with_spacer(10.0)
+            .with_child(attach_button)
+            .with_spacer(10.0)
+            .with_child(remove_button);
+        if kind == MediaKind::Audio {
+            let name_for_play = set_name.clone();
+            let play_button =
+                Button::new("Play").on_click(move |_ctx, _data: &mut AppState, _env| {
+                    if let Ok(study_set) = Storage::read_set_file(name_for_play.clone()) {
+                        audio::play_card(name_for_play.clone(), &study_set.get_card(word_id));
+                    }
+                });
+            let name_for_record = set_name.clone();
+            let name_for_label = set_name.clone();
+            let record_button = Button::dynamic(move |data: &AppState, _env| {
+                if data.recording == Some((name_for_label.clone(), word_id)) {
+                    String::from("Stop Recording")
+                } else {
+                    String::from("Record")
+                }
+            })
+            .on_click(move |_ctx, data: &mut AppState, _env| {
+                let this_card = Some((name_for_record.clone(), word_id));
+                if data.recording != this_card {
+                    let started = Storage::get_new_recording_path(name_for_record.clone())
+                        .and_then(|path| audio::start_recording(&path));
+                    match started {
+                        Ok(()) => {
+                            data.recording = this_card;
+                            data.media_message = String::from("Recording...");
+                        }
+                        Err(err) => data.media_message = err,
+                    }
+                    return;
+                }
+                data.recording = None;
+                match audio::stop_recording() {
+                    Ok(path) => {
+                        data.media_message.clear();
+                        let file_name = path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string());
+                        set_card_media(
+                            data,
+                            set_id,
+                            name_for_record.clone(),
+                            word_id,
+                            kind,
+                            file_name,
+                        );
+                    }
+                    Err(err) => data.media_message = err,
+                }
+            });
+            media_row = media_row
+                .with_spacer(10.0)
+                .with_child(play_button)
+                .with_spacer(10.0)
+                .with_child(record_button);
+        }
+        media_rows = media_rows.with_child(media_row).with_spacer(10.0);
+    }
+    let media_message_label = Label::dynamic(|data: &AppState, _env| data.media_message.clone())
+        .with_text_size(24.0)
+        .with_text_color(Color::YELLOW);
+
+    let save_button =
+        Button::new("Save Changes").on_click(move |ctx, data: &mut AppState, _env| -> () {
+            let mut target_set = match Storage::read_set_file(set_name.clone()) {
+                Ok(target_set) => target_set,
+                Err(_) => return,
+            };
+            let new_fields: Vec<String> = curr_fields
+                .iter()
+                .enumerate()
+                .map(|(field, curr_field)| {
+                    place_holder_helper(curr_field.clone(), get_field_to_add(data, field))
+                })
+                .collect();
+            let mut new_card = Card::new(
+                word_id,
+                new_fields[0].clone(),
+                new_fields[1].clone(),
+                new_fields[2].clone(),
+            );
+            new_card.set_card_type(cloze::get_card_type(&new_fields[0]));
+            new_card.set_extra_fields(new_fields[3..].to_vec());
+            new_card.set_audio(curr_audio.clone());
+            new_card.set_image(curr_image.clone());
+            let curr_card = target_set.get_card(word_id);
+            new_card.set_tags(curr_card.get_tags());
+            new_card.set_flags(curr_card.get_flags());
+            target_set.replace_card(word_id, new_card);
+            // clear data
+            data.extra_fields_to_add.clear();
+            data.word_remark_to_add.clear();
+            data.word_ans_to_add.clear();
+            data.word_to_add.clear();
+            data.catalogue.update_set(set_id, target_set);
+            Storage::update_inventory(data.catalogue.clone());
+            if set_id == data.res.len() {
+                data.res.push(Vec::new());
+                data.input_str.push(Vec::new());
+            }
+            data.res[set_id].push(String::new());
+            data.input_str[set_id].push(String::new());
+            data.nav.back();
+            ctx.request_update();
+        });
+    Flex::column()
+        .with_child(field_inputs)
+        .with_child(media_path)
+        .with_spacer(10.0)
+        .with_child(media_rows)
+        .with_child(media_message_label)
+        .with_spacer(50.0)
+        .with_child(save_button)
+        .center()
+}
+
+fn grid_cell_lens(row: usize, column: usize) -> impl Lens<AppState, String> {
+    druid::lens::Map::new(
+        move |data: &AppState| {
+            data.grid_rows
+                .get(row)
+                .and_then(|cells| cells.get(column))
+                .cloned()
+                .unwrap_or_default()
+        },
+        move |data: &mut AppState, text: String| {
+            if let Some(cell) = data
+                .grid_rows
+                .get_mut(row)
+                .and_then(|cells| cells.get_mut(column))
+            {
+                if *cell != text {
+                    *cell = text;
+                    data.grid_edited = true;
+                }
+            }
+        },
+    )
+}
+
+// Adds rows copied from a spreadsheet to the end of the grid
+fn paste_grid_rows(data: &mut AppState, num_of_columns: usize) {
+    let text = Application::global().clipboard().get_string();
+    let rows = grid::parse_table(&text.unwrap_or_default());
+    if rows.is_empty() {
+        data.grid_message = String::from("Nothing to paste, copy rows from a spreadsheet first");
+        return;
+    }
+    let num_of_rows = rows.len();
+    let is_cut = rows.iter().any(|row| row.len() > num_of_columns);
+    for mut row in rows {
+        row.resize(num_of_columns, String::new());
+        data.grid_rows.push(row);
+        data.grid_card_ids.push(None);
+    }
+    data.grid_edited = true;
+    data.grid_message = if is_cut {
+        format!(
+            "Pasted {} rows, leaving out cells past the last of the {} columns",
+            num_of_rows, num_of_columns
+        )
+    } else {
+        format!("Pasted {} rows", num_of_rows)
+    };
+    data.nav.refresh();
+}
+
+fn save_grid(data: &mut AppState, set_id: usize, set_name: String) {
+    let mut target_set = match Storage::read_set_file(set_name.clone()) {
+        Ok(target_set) => target_set,
+        Err(err) => {
+            data.grid_message = err;
+            return;
+        }
+    };
+    match grid::build_cards(&target_set, &data.grid_rows, &data.grid_card_ids) {
+        Ok(cards) => {
+            let num_of_cards = cards.len();
+            target_set.set_cards(cards);
+            data.catalogue.update_set(set_id, target_set);
+            Storage::update_inventory(data.catalogue.clone());
+            forget_card_positions(data, set_id, set_name.clone());
+            data.load_grid(set_name);
+            data.grid_message = format!("Saved {} words", num_of_cards);
+            data.nav.refresh();
+        }
+        Err(err) => data.grid_message = err,
+    }
+}
+
+fn grid_page_builder(
+    set_id: usize,
+    set_name: String,
+    schema: FieldSchema,
+    kana_input: KanaInput,
+    num_of_rows: usize,
+    num_of_columns: usize,
+) -> impl Widget<AppState> {
+    let add_row_button = Button::new("Add Row").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.grid_rows.push(vec![String::new(); num_of_columns]);
+            data.grid_card_ids.push(None);
+            data.grid_edited = true;
+            data.nav.refresh();
+        },
+    );
+    let paste_button = Button::new("Paste Rows").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            paste_grid_rows(data, num_of_columns);
+        },
+    );
+    let delete_rows_button = Button::new("Delete Selected Rows").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            let mut selected = data.grid_selected.clone();
+            selected.sort();
+            for row in selected.iter().rev() {
+                if *row < data.grid_rows.len() {
+                    data.grid_rows.remove(*row);
+                    data.grid_card_ids.remove(*row);
+                }
+            }
+            data.grid_edited = data.grid_edited || !selected.is_empty();
+            data.grid_selected.clear();
+            data.nav.refresh();
+        },
+    );
+    let name_for_save = set_name.clone();
+    let save_button = Button::new("Save All").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            save_grid(data, set_id, name_for_save.clone());
+        },
+    );
+    let name_for_discard = set_name.clone();
+    let discard_button = Button::new("Discard Changes").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.load_grid(name_for_discard.clone());
+            data.grid_message.clear();
+            data.nav.refresh();
+        },
+    );
+    let buttons_row = Flex::row()
+        .with_child(add_row_button)
+        .with_spacer(10.0)
+        .with_child(paste_button)
+        .with_spacer(10.0)
+        .with_child(delete_rows_button)
+        .with_spacer(30.0)
+        .with_child(save_button)
+        .with_spacer(10.0)
+        .with_child(discard_button);
+    let status_label = Label::dynamic(|data: &AppState, _env| -> String {
+        let edited = if data.grid_edited {
+            "Unsaved Changes"
+        } else {
+            "No Unsaved Changes"
+        };
+        format!("{} Rows Selected, {}", data.grid_selected.len(), edited)
+    })
+    .with_text_size(20.0);
+    let message_label = Label::dynamic(|data: &AppState, _env| data.grid_message.clone())
+        .with_text_size(20.0)
+        .with_text_color(Color::YELLOW);
+
+    // each column can be emptied in the selected rows, as an empty cell clears its field
+    let mut header_row = Flex::row().with_child(SizedBox::empty().width(80.0));
+    for column in 0..num_of_columns {
+        let clear_button = Button::new("Clear Selected").on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                for row in data.grid_selected.clone() {
+                    if let Some(cell) = data
+                        .grid_rows
+                        .get_mut(row)
+                        .and_then(|cells| cells.get_mut(column))
+                    {
+                        if !cell.is_empty() {
+                            cell.clear();
+                            data.grid_edited = true;
+                        }
+                    }
+                }
+            },
+        );
+        let header = Flex::column()
+            .with_child(
+                Label::new(grid::get_column_name(&schema, column))
+                    .with_text_size(24.0)
+                    .with_text_color(FIELD_COLORS[column % FIELD_COLORS.len()]),
+            )
+            .with_spacer(5.0)
+            .with_child(clear_button)
+            .fix_width(260.0);
+        header_row = header_row.with_child(header);
+    }
+
+    let mut rows = Flex::column().with_child(header_row).with_spacer(10.0);
+    for row in 0..num_of_rows {
+        let select_checkbox = Checkbox::new(format!("{}", row + 1)).lens(druid::lens::Map::new(
+            move |data: &AppState| data.grid_selected.contains(&row),
+            move |data: &mut AppState, selected: bool| {
+                data.grid_selected
+                    .retain(|selected_row| *selected_row != row);
+                if selected {
+                    data.grid_selected.push(row);
+                }
+            },
+        ));
+        let mut cells_row = Flex::row().with_child(select_checkbox.fix_width(80.0));
+        for column in 0..num_of_columns {
+            // the second field takes romaji input, as on the add word page
+            let cell: Box<dyn Widget<AppState>> = if column == 1 {
+                Box::new(
+                    answer_text_box(String::new(), kana_input)
+                        .fix_width(250.0)
+                        .lens(grid_cell_lens(row, column)),
+                )
+            } else {
+                Box::new(
+                    TextBox::new()
+                        .with_text_size(24.0)
+                        .fix_width(250.0)
+                        .lens(grid_cell_lens(row, column)),
+                )
+            };
+            cells_row = cells_row.with_child(cell).with_spacer(10.0);
+        }
+        rows = rows.with_child(cells_row).with_spacer(5.0);
+    }
+
+    let page = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(buttons_row)
+        .with_spacer(10.0)
+        .with_child(status_label)
+        .with_child(message_label)
+        .with_spacer(20.0)
+        .with_child(rows);
+    Scroll::new(page.padding(40.0)).controller(KeepScroll::new())
+}
+
+// Saves the card's media file straight away and rebuilds its edit page
+fn set_card_media(
+    data: &mut AppState,
+    set_id: usize,
+    set_name: String,
+    word_id: usize,
+    kind: MediaKind,
+    file_name: Option<String>,
+) {
+    update_card(data, set_id, set_name, word_id, |card| {
+        card.set_media(kind, file_name.clone())
+    });
+}
+
+// Saves a change to one card straight away and rebuilds the page
+fn update_card(
+    data: &mut AppState,
+    set_id: usize,
+    set_name: String,
+    word_id: usize,
+    change: impl Fn(&mut Card),
+) {
+    let mut target_set = match Storage::read_set_file(set_name) {
+        Ok(target_set) => target_set,
+        Err(_) => return,
+    };
+    let mut card = target_set.get_card(word_id);
+    change(&mut card);
+    target_set.replace_card(word_id, card);
+    data.catalogue.update_set(set_id, target_set);
+    Storage::update_inventory(data.catalogue.clone());
+    data.nav.refresh();
+}
+
+// Each set's history is read once here, see AppState::set_stats
+fn read_set_stats(catalogue: &Catalogue) -> HashMap<String, SetStats> {
+    catalogue
+        .get_all_names()
+        .into_iter()
+        .map(|set_name| {
+            let set_history = Storage::read_history_file(set_name.clone());
+            (set_name, SetStats::new(&set_history))
+        })
+        .collect()
+}
+
+// Answers, orders, sessions and ticked cards refer to cards by position, so they are
+// dropped once the cards of a set have moved
+fn forget_card_positions(data: &mut AppState, set_id: usize, set_name: String) {
+    let num_of_cards = data
+        .catalogue
+        .get_item_by_id(set_id)
+        .pop()
+        .map_or(0, |item| item.get_num_of_cards());
+    if set_id < data.input_str.len() {
+        data.input_str[set_id] = vec![String::new(); num_of_cards];
+        data.res[set_id] = vec![String::new(); num_of_cards];
+        data.card_orders[set_id].clear();
+        data.curr_indexes[set_id] = 0;
+    }
+    Storage::delete_session_file(set_name.clone());
+    if data.selected_set == set_name {
+        data.selected_cards.clear();
+    }
+}
+
+fn forget_sets_card_positions(data: &mut AppState, set_names: Vec<String>) {
+    for set_name in set_names {
+        if let Some(set_id) = data.catalogue.get_id_by_name(set_name.clone()) {
+            forget_card_positions(data, set_id, set_name);
+        }
+    }
+}
+
+// Moves a card to another position in its set, e.g. one earlier or later
+fn move_card(data: &mut AppState, set_id: usize, set_name: String, from: usize, to: usize) {
+    let mut target_set = match Storage::read_set_file(set_name.clone()) {
+        Ok(target_set) => target_set,
+        Err(_) => return,
+    };
+    target_set.move_card(from, to);
+    data.catalogue.update_set(set_id, target_set);
+    Storage::update_inventory(data.catalogue.clone());
+    forget_card_positions(data, set_id, set_name);
+    data.nav.refresh();
+}
+
+// Moves or copies the cards ticked on the view page to the set named in the box
+fn transfer_selected_cards(
+    data: &mut AppState,
+    set_id: usize,
+    set_name: String,
+    keep_originals: bool,
+) {
+    if data.selected_set != set_name || data.selected_cards.is_empty() {
+        data.card_message = String::from("Select cards to move or copy first");
+        return;
+    }
+    let target_name = data.card_target_set.trim().to_string();
+    let target_id = match data.catalogue.get_id_by_name(target_name.clone()) {
+        Some(target_id) => target_id,
+        None => {
+            data.card_message = format!("Set [{}] not found", target_name);
+            return;
+        }
+    };
+    let card_ids = data.selected_cards.clone();
+    let prev_catalogue = data.catalogue.clone();
+    match data
+        .catalogue
+        .transfer_cards(set_id, target_id, card_ids, keep_originals)
+    {
+        Ok(num_of_cards) => {
+            Storage::update_inventory(data.catalogue.clone());
+            data.sync_with_catalogue(&prev_catalogue);
+            forget_card_positions(data, target_id, target_name.clone());
+            if !keep_originals {
+                forget_card_positions(data, set_id, set_name);
+            }
+            let action = if keep_originals { "Copied" } else { "Moved" };
+            data.card_message = format!("{} {} cards to [{}]", action, num_of_cards, target_name);
+            data.selected_cards.clear();
+            data.nav.refresh();
+        }
+        Err(err) => data.card_message = err,
+    }
+}
+
+fn view_page_builder(
+    lesson_id: usize,
+    lesson_name: String,
+    cards: Vec<Card>,
+    tags: Vec<String>,
+    schema: FieldSchema,
+) -> impl Widget<AppState> {
+    let return_to_main = Button::new("Return to Study Sets List").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.nav.home();
+        },
+    );
+
+    let prev_set = Button::new("Prev Set").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            if lesson_id >= 1 {
+                let target_id = lesson_id - 1;
+                let item = data.catalogue.get_item_by_id(target_id);
+                data.nav.replace(Route::View(item[0].get_name()));
+            }
+        },
+    );
+
+    let next_set = Button::new("Next Set").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            if lesson_id + 1 < data.catalogue.get_num_of_items() {
+                let target_id = lesson_id + 1;
+                let item = data.catalogue.get_item_by_id(target_id);
+                data.nav.replace(Route::View(item[0].get_name()));
+            }
+        },
+    );
+
+    let set_nav = Flex::row()
+        .with_child(prev_set)
+        .with_flex_spacer(10.0)
+        .with_child(next_set);
+
+    let lesson_label: Align<AppState> = Label::new(lesson_name.clone())
+        .with_text_size(32.0)
+        .with_text_color(Color::TEAL)
+        .center();
+    let mut list: Flex<AppState> = Flex::column()
+        .with_child(set_nav)
+        .with_spacer(30.0)
+        .with_child(return_to_main.align_left())
+        .with_spacer(30.0)
+        .with_child(lesson_label);
+
+    let mut tag_row = Flex::row();
+    for tag in tags {
+        let tag_label = Label::new(tag)
+            .with_text_size(20.0)
+            .with_text_color(Color::rgba(0.5, 0.3, 0.7, 1.0))
+            .padding(3.0)
+            .border(Color::YELLOW, 1.0);
+        tag_row = tag_row.with_child(tag_label).with_spacer(5.0);
+    }
+    list = list.with_spacer(20.0).with_child(tag_row);
+
+    let name_for_add = lesson_name.clone();
+    let add_word_button = Button::new("Add Word").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.nav.push(Route::AddWord(name_for_add.clone()));
+        },
+    );
+    let name_for_grid = lesson_name.clone();
+    let edit_all_button = Button::new("Edit All Words").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.load_grid(name_for_grid.clone());
+            data.grid_message.clear();
+            data.nav.push(Route::EditAllWords(name_for_grid.clone()));
+        },
+    );
+    let name_for_replace = lesson_name.clone();
+    let replace_button = Button::new("Find and Replace").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            open_replace_page(data, ReplaceScope::OneSet, name_for_replace.clone());
+        },
+    );
+    list = list.with_spacer(30.0).with_child(
+        Flex::row()
+            .with_child(add_word_button)
+            .with_spacer(10.0)
+            .with_child(edit_all_button)
+            .with_spacer(10.0)
+            .with_child(replace_button),
+    );
+    let card_tag_input = TextBox::new()
+        .with_placeholder("Card Tag to Add")
+        .with_text_size(20.0)
+        .fix_width(300.0)
+        .lens(AppState::new_card_tag);
+    list = list.with_spacer(10.0).with_child(card_tag_input);
+
+    let name_for_count = lesson_name.clone();
+    let selected_label = Label::dynamic(move |data: &AppState, _env| -> String {
+        let num_selected = if data.selected_set == name_for_count {
+            data.selected_cards.len()
+        } else {
+            0
+        };
+        format!("{} Cards Selected", num_selected)
+    })
+    .with_text_size(20.0);
+    let name_for_select_all = lesson_name.clone();
+    let all_card_ids: Vec<usize> = cards.iter().map(|card| card.get_id()).collect();
+    let select_all_button = Button::new("Select All").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.selected_set = name_for_select_all.clone();
+            data.selected_cards = all_card_ids.clone();
+        },
+    );
+    let clear_selection_button = Button::new("Clear Selection").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.selected_cards.clear();
+        },
+    );
+    let target_set_input = TextBox::new()
+        .with_placeholder("Set to move or copy to")
+        .with_text_size(20.0)
+        .fix_width(300.0)
+        .lens(AppState::card_target_set);
+    let name_for_move = lesson_name.clone();
+    let move_cards_button = Button::new("Move Selected").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            transfer_selected_cards(data, lesson_id, name_for_move.clone(), false);
+        },
+    );
+    let name_for_copy = lesson_name.clone();
+    let copy_cards_button = Button::new("Copy Selected").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            transfer_selected_cards(data, lesson_id, name_for_copy.clone(), true);
+        },
+    );
+    let selection_row = Flex::row()
+        .with_child(selected_label)
+        .with_spacer(10.0)
+        .with_child(select_all_button)
+        .with_spacer(10.0)
+        .with_child(clear_selection_button)
+        .with_spacer(20.0)
+        .with_child(target_set_input)
+        .with_spacer(10.0)
+        .with_child(move_cards_button)
+        .with_spacer(10.0)
+        .with_child(copy_cards_button);
+    let card_message_label = Label::dynamic(|data: &AppState, _env| data.card_message.clone())
+        .with_text_size(20.0)
+        .with_text_color(Color::YELLOW);
+    list = list
+        .with_spacer(10.0)
+        .with_child(Scroll::new(selection_row).horizontal())
+        .with_child(card_message_label);
+    let num_of_cards = cards.len();
+    let mut new_cards = cards.clone();
+    new_cards.reverse();
+    for card in new_cards {
+        let card_id = card.get_id();
+        let card_to_play = card.clone();
+        let name_for_play = lesson_name.clone();
+        let ans_to_play = get_card_answer(&card, schema.get_roles(StudyMode::Learn));
+        let name_for_delete = lesson_name.clone();
+        let delete_word_button = Button::new("Delete").on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                let mut target_set = match Storage::read_set_file(name_for_delete.clone()) {
+                    Ok(target_set) => target_set,
+                    Err(_) => return,
+                };
+                target_set.delete_card(card_id);
+                data.catalogue.update_set(lesson_id, target_set);
+                Storage::update_inventory(data.catalogue.clone());
+                forget_card_positions(data, lesson_id, name_for_delete.clone());
+                data.nav.refresh();
+            },
+        );
+        let name_for_edit = lesson_name.clone();
+        let edit_word_button = Button::new("Edit").on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                data.nav
+                    .push(Route::EditWord(name_for_edit.clone(), card_id));
+            },
+        );
+        let name_for_select = lesson_name.clone();
+        let name_for_toggle = lesson_name.clone();
+        let select_checkbox = Checkbox::new("Select").lens(druid::lens::Map::new(
+            move |data: &AppState| {
+                data.selected_set == name_for_select && data.selected_cards.contains(&card_id)
+            },
+            move |data: &mut AppState, selected: bool| {
+                if data.selected_set != name_for_toggle {
+                    data.selected_set = name_for_toggle.clone();
+                    data.selected_cards.clear();
+                }
+                data.selected_cards.retain(|id| *id != card_id);
+                if selected {
+                    data.selected_cards.push(card_id);
+                }
+            },
+        ));
+        let mut word_row: Flex<AppState> = Flex::column().with_child(select_checkbox.align_left());
+        for (field, field_name) in schema.get_fields().into_iter().enumerate() {
+            let (title, text) = match field {
+                0 => (
+                    format!("{} {}:", field_name, card_id + 1),
+                    cloze::fill_deletions(&card.get_word()),
+                ),
+                _ => (format!("{}:", field_name), card.get_field(field)),
+            };
+            let color = FIELD_COLORS[field % FIELD_COLORS.len()];
+            word_row = word_row.with_child(titled_ruby_label(title, text, color).align_left());
+        }
+        if card.get_card_type() == CardType::Cloze {
+            let blanks_title = String::from("Blanks:");
+            let blanks = cloze::get_deletions(&card.get_word()).join("、");
+            let blanks_label = titled_ruby_label(blanks_title, blanks, Color::YELLOW);
+            word_row = word_row.with_child(blanks_label.align_left());
+        }
+        let mut flags_row = Flex::row();
+        for flag in CardFlag::get_all() {
+            let mark = if card.has_flag(flag) { "[x]" } else { "[ ]" };
+            let name_for_flag = lesson_name.clone();
+            let flag_button = Button::new(format!("{} {}", mark, flag.get_label())).on_click(
+                move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                    update_card(data, lesson_id, name_for_flag.clone(), card_id, |card| {
+                        card.toggle_flag(flag)
+                    });
+                },
+            );
+            flags_row = flags_row.with_child(flag_button).with_spacer(10.0);
+        }
+        let mut card_tags_row = Flex::row().with_child(
+            Label::new("Card Tags:")
+                .with_text_size(20.0)
+                .with_text_color(Color::LIME),
+        );
+        for tag in card.get_tags() {
+            let name_for_tag = lesson_name.clone();
+            let tag_label = Label::new(tag.clone())
+                .with_text_size(20.0)
+                .with_text_color(Color::LIME);
+            let delete_tag_button = Button::new("x").on_click(
+                move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                    update_card(data, lesson_id, name_for_tag.clone(), card_id, |card| {
+                        card.delete_tag(tag.clone())
+                    });
+                },
+            );
+            card_tags_row = card_tags_row
+                .with_spacer(10.0)
+                .with_child(tag_label)
+                .with_child(delete_tag_button);
+        }
+        let name_for_add_tag = lesson_name.clone();
+        let add_tag_button = Button::new("Add Tag").on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                let tag = tags::normalize_tag(&data.new_card_tag);
+                if is_valid(tag.clone()) {
+                    update_card(data, lesson_id, name_for_add_tag.clone(), card_id, |card| {
+                        card.add_tag(tag.clone())
+                    });
+                }
+            },
+        );
+        card_tags_row = card_tags_row.with_spacer(10.0).with_child(add_tag_button);
+        word_row = word_row
+            .with_child(flags_row.align_left())
+            .with_spacer(5.0)
+            .with_child(card_tags_row.align_left())
+            .with_spacer(5.0);
+        let play_word_button = Button::new("Play Word").on_click(move |_ctx, _data, _env| {
+            audio::play_card(name_for_play.clone(), &card_to_play);
+        });
+        let play_ans_button = Button::new("Play Answer").on_click(move |_ctx, _data, _env| {
+            let _ = audio::speak(&ans_to_play);
+        });
+        let name_for_earlier = lesson_name.clone();
+        let earlier_button = Button::new("Move Earlier").on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                if card_id > 0 {
+                    move_card(
+                        data,
+                        lesson_id,
+                        name_for_earlier.clone(),
+                        card_id,
+                        card_id - 1,
+                    );
+                }
+            },
+        );
+        let name_for_later = lesson_name.clone();
+        let later_button = Button::new("Move Later").on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                if card_id + 1 < num_of_cards {
+                    move_card(
+                        data,
+                        lesson_id,
+                        name_for_later.clone(),
+                        card_id,
+                        card_id + 1,
+                    );
+                }
+            },
+        );
+        let buttons_row = Flex::row()
+            .with_child(edit_word_button)
+            .with_spacer(10.0)
+            .with_child(delete_word_button)
+            .with_spacer(10.0)
+            .with_child(play_word_button)
+            .with_spacer(10.0)
+            .with_child(play_ans_button)
+            .with_spacer(20.0)
+            .with_child(earlier_button)
+            .with_spacer(10.0)
+            .with_child(later_button);
+        word_row = word_row.with_child(buttons_row);
+        list = list.with_child(
+            word_row
+                .padding(20.0)
+                .border(Color::YELLOW, 1.0)
+                .padding(5.0),
+        );
+    }
+    Scroll::new(list.padding(40.0))
+        .vertical()
+        .controller(KeepScroll::new())
+}
+
+// Whether a card is studied, given the card filters ticked on the study sets list
+fn is_card_studied(data: &AppState, card: &Card) -> bool {
+    (!data.starred_only || card.has_flag(CardFlag::Starred))
+        && !(data.skip_suspended && card.has_flag(CardFlag::Suspended))
+}
+
+// Limits study of the set to the cards that pass the card filters, leaving any order in
+// progress alone when every card passes; false if no card does
+fn filter_study_cards(data: &mut AppState, set_index: usize, set_name: String) -> bool {
+    let cards = match Storage::read_set_file(set_name) {
+        Ok(study_set) => study_set.get_all_cards(),
+        Err(_) => return false,
+    };
+    let card_ids: Vec<usize> = cards
+        .iter()
+        .filter(|card| is_card_studied(data, card))
+        .map(|card| card.get_id())
+        .collect();
+    if card_ids.is_empty() {
+        return false;
+    }
+    if card_ids.len() < cards.len() && data.card_orders[set_index] != card_ids {
+        data.card_orders[set_index] = card_ids.clone();
+        data.curr_indexes[set_index] = card_ids[0];
+        data.str = data.input_str[set_index][card_ids[0]].clone();
+    }
+    true
+}
+
+fn list_page_builder(
+    mut items: Vec<ListItem>,
+    tags: Vec<String>,
+    set_stats: HashMap<String, SetStats>,
+    folder_browser: Option<impl Widget<AppState> + 'static>,
+) -> impl Widget<AppState> {
+    let num_of_items = items.len();
+    let settings = Storage::read_settings_file();
+    let tag_colors = settings.get_tag_colors();
+    let list_sort = settings.get_list_sort();
+    sort::sort_list_items(&mut items, list_sort, &set_stats);
+    let mut list: Flex<AppState> = Flex::column();
+    let filter_label = Label::new("Filter by tags")
+        .with_text_size(32.0)
+        .with_text_color(Color::PURPLE);
+    let mut tags_list: Flex<AppState> = Flex::row().with_spacer(10.0);
+    for tag in &tags {
+        let curr_tag = tag.clone();
+        let curr_tag_for_filter = curr_tag.clone();
+        let filter_button = Button::new(curr_tag.clone())
+            .background(Painter::new(move |ctx, data: &AppState, _env| {
+                let bounds = ctx.size().to_rect();
+                if data.current_filter.contains(&curr_tag_for_filter) {
+                    ctx.fill(bounds, &SELECTED_TAG_COLOR);
+                } else {
+                    ctx.fill(bounds, &UNSELECTED_TAG_COLOR);
+                }
+            }))
+            .on_click(
+                move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                    if data.current_filter.contains(&curr_tag) {
+                        data.current_filter.remove(&curr_tag);
+                    } else {
+                        data.current_filter.insert(curr_tag.clone());
+                    }
+                },
+            );
+        tags_list = tags_list.with_child(filter_button).with_spacer(10.0);
+    }
+    let inner_tags_list = Scroll::new(tags_list.padding(20.0).center()).horizontal();
+    list.add_child(filter_label);
+    list.add_child(inner_tags_list);
+
+    let match_all = Button::new("Match All").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            if !data.current_filter.is_empty() {
+                let filter = data.current_filter.clone();
+                data.current_filter.clear();
+                data.nav.replace(Route::List(ListFilter::MatchAll(filter)));
+            }
+        },
+    );
+    let match_any = Button::new("Match Any").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            if !data.current_filter.is_empty() {
+                let filter = data.current_filter.clone();
+                data.current_filter.clear();
+                data.nav.replace(Route::List(ListFilter::MatchAny(filter)));
+            }
+        },
+    );
+
+    let all_sets = Button::new("See All Sets").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            if data.catalogue.get_num_of_items() != num_of_items {
+                data.current_filter.clear();
+                data.nav.replace(Route::List(ListFilter::All));
+            }
+        },
+    );
+
+    let untagged_sets = Button::new("See All Untagged Sets").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.current_filter.clear();
+            data.nav.replace(Route::List(ListFilter::Untagged));
+        },
+    );
+
+    let browse_folders = Button::new("Browse Folders").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.current_filter.clear();
+            data.folder_message.clear();
+            data.nav
+                .replace(Route::List(ListFilter::Folder(String::new())));
+        },
+    );
+
+    let mut filter_buttons = Flex::row();
+    filter_buttons = filter_buttons
+        .with_child(match_all)
+        .with_spacer(10.0)
+        .with_child(match_any)
+        .with_spacer(10.0)
+        .with_child(all_sets)
+        .with_spacer(10.0)
+        .with_child(untagged_sets)
+        .with_spacer(10.0)
+        .with_child(browse_folders);
+
+    list.add_child(filter_buttons);
+
+    let query_input = TextBox::new()
+        .with_placeholder("e.g. LAJ1 AND (kanji OR vocab) AND NOT reading")
+        .with_text_size(20.0)
+        .fix_width(500.0)
+        .lens(AppState::list_query);
+    let apply_query = Button::new("Apply Query").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            if query::parse_query(&data.list_query).is_ok() {
+                data.current_filter.clear();
+                let text = data.list_query.trim().to_string();
+                data.nav.replace(Route::List(ListFilter::Query(text)));
+            }
+        },
+    );
+    let query_error_label = Label::dynamic(|data: &AppState, _env| -> String {
+        if data.list_query.trim().is_empty() {
+            return String::new();
+        }
+        match query::parse_query(&data.list_query) {
+            Ok(_) => String::new(),
+            Err(err) => err,
+        }
+    })
+    .with_text_size(20.0)
+    .with_text_color(Color::YELLOW);
+    let query_name_input = TextBox::new()
+        .with_placeholder("Query Name")
+        .with_text_size(20.0)
+        .fix_width(200.0)
+        .lens(AppState::query_name);
+    let save_query = Button::new("Save Query").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            if is_valid(data.query_name.clone()) && query::parse_query(&data.list_query).is_ok() {
+                let mut settings = Storage::read_settings_file();
+                settings.save_query(SavedQuery::new(
+                    data.query_name.trim().to_string(),
+                    data.list_query.trim().to_string(),
+                ));
+                Storage::update_settings(settings);
+                data.query_name.clear();
+                data.nav.refresh();
+            }
+        },
+    );
+    let query_row = Flex::row()
+        .with_child(query_input)
+        .with_spacer(10.0)
+        .with_child(apply_query)
+        .with_spacer(20.0)
+        .with_child(query_name_input)
+        .with_spacer(10.0)
+        .with_child(save_query);
+    let mut saved_queries_row = Flex::row();
+    for saved_query in Storage::read_settings_file().get_saved_queries() {
+        let query_to_apply = saved_query.get_query();
+        let query_button = Button::new(saved_query.get_name()).on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                data.current_filter.clear();
+                data.list_query = query_to_apply.clone();
+                data.nav
+                    .replace(Route::List(ListFilter::Query(query_to_apply.clone())));
+            },
+        );
+        let name_for_delete = saved_query.get_name();
+        let delete_button = Button::new("x").on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                let mut settings = Storage::read_settings_file();
+                settings.delete_query(name_for_delete.clone());
+                Storage::update_settings(settings);
+                data.nav.refresh();
+            },
+        );
+        saved_queries_row = saved_queries_row
+            .with_child(query_button)
+            .with_child(delete_button)
+            .with_spacer(10.0);
+    }
+    list = list
+        .with_spacer(10.0)
+        .with_child(query_row)
+        .with_child(query_error_label)
+        .with_child(Scroll::new(saved_queries_row).horizontal());
+
+    let search_button = Button::new("Search Cards").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.nav.push(Route::Search);
+        },
+    );
+    let replace_button = Button::new("Find and Replace").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            open_replace_page(data, ReplaceScope::AllSets, String::new());
+        },
+    );
+    let duplicates_button = Button::new("Find Duplicates").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.nav.push(Route::Duplicates);
+        },
+    );
+    let tags_button = Button::new("Manage Tags").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.tag_message.clear();
+            data.nav.push(Route::Tags);
+        },
+    );
+    let starred_toggle = Checkbox::new("Starred Cards Only").lens(AppState::starred_only);
+    let suspended_toggle = Checkbox::new("Skip Suspended Cards").lens(AppState::skip_suspended);
+    let tool_buttons = Flex::row()
+        .with_child(search_button)
+        .with_spacer(10.0)
+        .with_child(replace_button)
+        .with_spacer(10.0)
+        .with_child(duplicates_button)
+        .with_spacer(10.0)
+        .with_child(tags_button)
+        .with_spacer(20.0)
+        .with_child(starred_toggle)
         .with_spacer(10.0)
-        .with_child(word_remark)
-        .with_spacer(50.0)
-        .with_child(save_button)
-        .center()
-}
-
-fn place_holder_helper(prev: String, input: String) -> String {
-    if is_valid(input.clone()) {
-        let temp = &input;
-        return temp.trim().to_string();
-    }
-    return prev;
-}
+        .with_child(suspended_toggle);
+    list = list.with_spacer(10.0).with_child(tool_buttons);
 
-fn edit_word_page_builder(
-    set_id: usize,
-    word_id: usize,
-    curr_word: String,
-    curr_ans: String,
-    curr_remarks: String,
-    set_name: String,
-) -> impl Widget<AppState> {
-    let word_label = Label::new(String::from(format!("Word {}", word_id + 1))).with_text_size(32.0);
-    let word = TextBox::new()
-        .with_placeholder(curr_word.clone())
-        .with_text_size(24.0)
-        .fix_width(300.0)
-        .lens(AppState::word_to_add);
-    let word_ans_label = Label::new(String::from("Answer for Word")).with_text_size(32.0);
-    let word_ans = TextBox::new()
-        .with_placeholder(curr_ans.clone())
-        .with_text_size(24.0)
-        .fix_width(300.0)
-        .lens(AppState::word_ans_to_add);
-    let word_remark_label = Label::new(String::from("Remarks for Word")).with_text_size(32.0);
-    let word_remark = TextBox::new()
-        .with_placeholder(curr_remarks.clone())
-        .with_text_size(24.0)
-        .fix_width(300.0)
-        .lens(AppState::word_remark_to_add);
-    let save_button =
-        Button::new("Save Changes").on_click(move |ctx, data: &mut AppState, _env| -> () {
-            let mut target_set = Storage::read_set_file(set_name.clone());
-            let new_card = Card::new(
-                word_id,
-                place_holder_helper(curr_word.clone(), data.word_to_add.clone()),
-                place_holder_helper(curr_ans.clone(), data.word_ans_to_add.clone()),
-                place_holder_helper(curr_remarks.clone(), data.word_remark_to_add.clone()),
+    let mut sort_row = Flex::row().with_child(Label::new("Sort by").with_text_size(20.0));
+    for key in SortKey::get_all() {
+        let is_current = key == list_sort.get_key();
+        let sort_label = match (is_current, list_sort.is_descending()) {
+            (true, false) => format!("{} ↑", key.get_label()),
+            (true, true) => format!("{} ↓", key.get_label()),
+            (false, _) => key.get_label(),
+        };
+        let sort_button = Button::new(sort_label)
+            .background(Painter::new(move |ctx, _data: &AppState, _env| {
+                let bounds = ctx.size().to_rect();
+                if is_current {
+                    ctx.fill(bounds, &SELECTED_TAG_COLOR);
+                } else {
+                    ctx.fill(bounds, &UNSELECTED_TAG_COLOR);
+                }
+            }))
+            .on_click(
+                move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                    let mut settings = Storage::read_settings_file();
+                    // picking the current order again flips it
+                    let descending = is_current && !list_sort.is_descending();
+                    settings.set_list_sort(ListSort::new(key, descending));
+                    Storage::update_settings(settings);
+                    data.nav.refresh();
+                },
             );
-            target_set.replace_card(word_id, new_card);
-            let window_title = target_set.get_set_name();
-            let lesson_name = window_title.clone();
-            let new_win = WindowDesc::new(view_page_builder(
-                set_id,
-                lesson_name,
-                target_set.get_all_cards(),
-                target_set.get_all_tags(),
-            ))
-            .title(window_title);
-            // clear data
-            data.word_remark_to_add.clear();
-            data.word_ans_to_add.clear();
-            data.word_to_add.clear();
-            data.catalogue.update_set(set_id, target_set);
-            Storage::update_inventory(data.catalogue.clone());
-            if set_id == data.res.len() {
-                data.res.push(Vec::new());
-                data.input_str.push(Vec::new());
-            }
-            data.res[set_id].push(String::new());
-            data.input_str[set_id].push(String::new());
-            ctx.request_update();
-            ctx.window().close();
-            ctx.new_window(new_win);
-        });
-    Flex::column()
-        .with_child(word_label)
-        .with_spacer(10.0)
-        .with_child(word)
-        .with_spacer(50.0)
-        .with_child(word_ans_label)
+        sort_row = sort_row.with_spacer(10.0).with_child(sort_button);
+    }
+    let is_grouped = settings.is_grouped_by_tag();
+    let group_button = Button::new(if is_grouped {
+        "Group by Tag: On"
+    } else {
+        "Group by Tag: Off"
+    })
+    .on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            let mut settings = Storage::read_settings_file();
+            settings.set_group_by_tag(!is_grouped);
+            Storage::update_settings(settings);
+            data.nav.refresh();
+        },
+    );
+    sort_row = sort_row.with_spacer(20.0).with_child(group_button);
+    list = list
         .with_spacer(10.0)
-        .with_child(word_ans)
-        .with_spacer(50.0)
-        .with_child(word_remark_label)
+        .with_child(Scroll::new(sort_row).horizontal());
+    if let Some(folder_browser) = folder_browser {
+        list = list
+            .with_spacer(10.0)
+            .with_child(folder_browser.border(Color::TEAL, 2.0));
+    }
+    if settings.is_grouped_by_tag() {
+        // sets are listed under each tag they have
+        for tag in tags {
+            let group_items: Vec<ListItem> = items
+                .iter()
+                .filter(|item| item.get_all_tags().contains(&tag))
+                .cloned()
+                .collect();
+            if !group_items.is_empty() {
+                list.add_child(tag_group_builder(tag, group_items, &tag_colors, &set_stats));
+            }
+        }
+        let untagged_items: Vec<ListItem> = items
+            .iter()
+            .filter(|item| item.get_all_tags().is_empty())
+            .cloned()
+            .collect();
+        if !untagged_items.is_empty() {
+            list.add_child(tag_group_builder(
+                String::new(),
+                untagged_items,
+                &tag_colors,
+                &set_stats,
+            ));
+        }
+    } else {
+        for item in items {
+            let stats = set_stats.get(&item.get_name()).copied();
+            list.add_child(study_set_builder(item, &tag_colors, stats));
+        }
+    }
+    let add_set_button = Button::new("Add Set").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            // sets are added to the folder being browsed
+            data.new_set_folder = match data.nav.get_route() {
+                Route::List(ListFilter::Folder(folder)) => folder,
+                _ => String::new(),
+            };
+            data.nav.push(Route::AddSet);
+        },
+    );
+    list = list
         .with_spacer(10.0)
-        .with_child(word_remark)
-        .with_spacer(50.0)
-        .with_child(save_button)
-        .center()
+        .with_child(add_set_button.center())
+        .with_spacer(30.0);
+    let scroll = Scroll::new(list).vertical().controller(KeepScroll::new());
+    Align::right(scroll)
 }
 
-fn view_page_builder(
-    lesson_id: usize,
-    lesson_name: String,
-    cards: Vec<Card>,
-    tags: Vec<String>,
+fn study_set_builder(
+    item: ListItem,
+    tag_colors: &[TagColor],
+    stats: Option<SetStats>,
 ) -> impl Widget<AppState> {
-    let return_to_main = Button::new("Return to Study Sets List").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-            let new_win = WindowDesc::new(list_page_builder(
-                data.catalogue.get_inventory(),
-                data.catalogue.get_all_tags(),
-            ))
-            .title(MAIN_TITLE);
-            ctx.window().close();
-            ctx.new_window(new_win);
+    let id = item.get_id();
+    let num_of_cards = item.get_num_of_cards();
+    let name_for_view = item.get_name();
+    let name_for_learn = item.get_name();
+    let name_for_test = item.get_name();
+    let name_for_hard_test = name_for_test.clone();
+    let name_for_listen = name_for_test.clone();
+    let name_for_timed_test = name_for_test.clone();
+    let name_for_flashcards = name_for_test.clone();
+    let name_for_edit = item.get_name();
+    let name_for_resume = item.get_name();
+    let mut section = Flex::column();
+    let set_name_label = Label::new(item.get_name()).with_text_size(24.0);
+    section.add_child(set_name_label);
+    let stats = stats.unwrap_or_default();
+    let last_studied = match stats.get_last_studied() {
+        Some(finished_at) => format!("last studied {}", history::format_days_ago(finished_at)),
+        None => String::from("never studied"),
+    };
+    let accuracy = stats.get_accuracy().map_or(String::new(), |accuracy| {
+        format!(", {:.0}% correct", accuracy * 100.0)
+    });
+    let stats_label = Label::new(format!(
+        "{} cards, {}{}",
+        num_of_cards, last_studied, accuracy
+    ))
+    .with_text_size(16.0)
+    .with_text_color(Color::SILVER);
+    section = section.with_spacer(5.0).with_child(stats_label);
+    if !item.get_folder().is_empty() {
+        let folder_label = Label::new(format!(
+            "Folder: {}",
+            folders::get_display_name(&item.get_folder())
+        ))
+        .with_text_size(16.0)
+        .with_text_color(Color::TEAL);
+        section = section.with_spacer(5.0).with_child(folder_label);
+    }
+    for tag in item.get_all_tags() {
+        let tag_color = get_color_code(&tag, tag_colors);
+        let tag_label = Label::new(tag).with_text_color(tag_color);
+        section = section.with_spacer(5.0).with_child(tag_label);
+    }
+    let view_button = Button::new("View").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.nav.push(Route::View(name_for_view.clone()));
         },
     );
-
-    let prev_set = Button::new("Prev Set").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-            if lesson_id >= 1 {
-                let target_id = lesson_id - 1;
-                let item = data.catalogue.get_item_by_id(target_id);
-                let target_set = Storage::read_set_file(item[0].get_name());
-                let new_win = WindowDesc::new(view_page_builder(
-                    target_id,
-                    target_set.get_set_name(),
-                    target_set.get_all_cards(),
-                    target_set.get_all_tags(),
-                ))
-                .title(MAIN_TITLE);
-                ctx.window().close();
-                ctx.new_window(new_win);
+    let learn_button = Button::new("Learn").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            let study_set = match Storage::read_set_file(name_for_learn.clone()) {
+                Ok(study_set) => study_set,
+                Err(_) => return,
+            };
+            if study_set.get_num_of_cards() > 0
+                && filter_study_cards(data, id, name_for_learn.clone())
+            {
+                data.nav.push(Route::Learn(study_set.get_set_name()));
             }
         },
     );
-
-    let next_set = Button::new("Next Set").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-            if lesson_id + 1 < data.catalogue.get_num_of_items() {
-                let target_id = lesson_id + 1;
-                let item = data.catalogue.get_item_by_id(target_id);
-                let target_set = Storage::read_set_file(item[0].get_name());
-                let new_win = WindowDesc::new(view_page_builder(
-                    target_id,
-                    target_set.get_set_name(),
-                    target_set.get_all_cards(),
-                    target_set.get_all_tags(),
-                ))
-                .title(MAIN_TITLE);
-                ctx.window().close();
-                ctx.new_window(new_win);
+    let test_button = Button::new("Test").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            if num_of_cards > 0 && filter_study_cards(data, id, name_for_test.clone()) {
+                data.nav.push(Route::Test(name_for_test.clone()));
+            }
+        },
+    );
+    let hard_test_button = Button::new("Test No Remarks").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            if num_of_cards > 0 && filter_study_cards(data, id, name_for_hard_test.clone()) {
+                data.nav
+                    .push(Route::TestNoRemarks(name_for_hard_test.clone()));
+            }
+        },
+    );
+    let timed_test_button = Button::new("Timed Test").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            if num_of_cards > 0 && filter_study_cards(data, id, name_for_timed_test.clone()) {
+                data.nav
+                    .push(Route::TimedTestSetup(name_for_timed_test.clone()));
+            }
+        },
+    );
+    let flashcards_button = Button::new("Flashcards").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            if num_of_cards > 0 && filter_study_cards(data, id, name_for_flashcards.clone()) {
+                // nothing is typed on flashcards
+                data.str.clear();
+                data.card_flipped = false;
+                data.nav
+                    .push(Route::Flashcards(name_for_flashcards.clone()));
+            }
+        },
+    );
+    let listen_button = Button::new("Listen").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            if num_of_cards > 0 && filter_study_cards(data, id, name_for_listen.clone()) {
+                data.nav.push(Route::Listen(name_for_listen.clone()));
             }
         },
     );
+    let delete_button = Button::new("Delete").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            let prev_catalogue = data.catalogue.clone();
+            data.catalogue.delete_item_by_id(id);
+            Storage::update_inventory(data.catalogue.clone());
+            data.sync_with_catalogue(&prev_catalogue);
+            data.nav.refresh();
+        },
+    );
 
-    let set_nav = Flex::row()
-        .with_child(prev_set)
-        .with_flex_spacer(10.0)
-        .with_child(next_set);
+    let edit_setname_button = Button::new("Edit").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.new_set_folder.clear();
+            data.nav.push(Route::EditSet(name_for_edit.clone()));
+        },
+    );
+    let mut row = Flex::row();
+    let session = Storage::read_session_file(item.get_name())
+        .filter(|session| session.fits_set(num_of_cards));
+    if let Some(session) = session {
+        let resume_button = Button::new(format!(
+            "Resume {} ({} / {})",
+            session.get_mode().get_label(),
+            session.get_position() + 1,
+            session.get_order().len()
+        ))
+        .on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                resume_session(data, id, &session);
+                data.nav.push(Route::for_study(
+                    session.get_mode(),
+                    name_for_resume.clone(),
+                ));
+            },
+        );
+        row.add_child(resume_button);
+    }
+    row.add_child(view_button);
+    row.add_child(learn_button);
+    row.add_child(test_button);
+    row.add_child(hard_test_button);
+    row.add_child(timed_test_button);
+    row.add_child(flashcards_button);
+    row.add_child(listen_button);
+    row.add_child(delete_button);
+    row.add_child(edit_setname_button);
+    section = section.with_spacer(20.0).with_child(row);
+    section
+        .padding(30.0)
+        .border(Color::OLIVE, 2.0)
+        .padding(10.0)
+}
 
-    let lesson_label: Align<AppState> = Label::new(lesson_name.clone())
-        .with_text_size(32.0)
-        .with_text_color(Color::TEAL)
-        .center();
-    let mut list: Flex<AppState> = Flex::column()
-        .with_child(set_nav)
-        .with_spacer(30.0)
-        .with_child(return_to_main.align_left())
-        .with_spacer(30.0)
-        .with_child(lesson_label);
+// A section of the list for the sets with a tag, or with no tags if it is empty, that can be
+// collapsed to its header
+fn tag_group_builder(
+    tag: String,
+    items: Vec<ListItem>,
+    tag_colors: &[TagColor],
+    set_stats: &HashMap<String, SetStats>,
+) -> impl Widget<AppState> {
+    let title = if tag.is_empty() {
+        String::from("Untagged")
+    } else {
+        tag.clone()
+    };
+    let title_color = if tag.is_empty() {
+        Color::SILVER
+    } else {
+        get_color_code(&tag, tag_colors)
+    };
+    let title_label = Label::new(format!("{} ({})", title, items.len()))
+        .with_text_size(24.0)
+        .with_text_color(title_color);
+    let tag_for_arrow = tag.clone();
+    let tag_for_toggle = tag.clone();
+    let toggle_button = Button::dynamic(move |data: &AppState, _env| {
+        if data.collapsed_tags.contains(&tag_for_arrow) {
+            String::from("▶")
+        } else {
+            String::from("▼")
+        }
+    })
+    .on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| match data
+            .collapsed_tags
+            .iter()
+            .position(|collapsed| *collapsed == tag_for_toggle)
+        {
+            Some(i) => {
+                data.collapsed_tags.remove(i);
+            }
+            None => data.collapsed_tags.push(tag_for_toggle.clone()),
+        },
+    );
+    let mut sets = Flex::column();
+    for item in items {
+        let stats = set_stats.get(&item.get_name()).copied();
+        sets.add_child(study_set_builder(item, tag_colors, stats));
+    }
+    let header = Flex::row()
+        .with_child(toggle_button)
+        .with_spacer(10.0)
+        .with_child(title_label);
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_spacer(10.0)
+        .with_child(header.padding((10.0, 0.0)))
+        .with_child(Either::new(
+            move |data: &AppState, _env| !data.collapsed_tags.contains(&tag),
+            sets,
+            SizedBox::empty(),
+        ))
+}
 
-    let mut tag_row = Flex::row();
-    for tag in tags {
-        let tag_label = Label::new(tag)
-            .with_text_size(20.0)
-            .with_text_color(Color::rgba(0.5, 0.3, 0.7, 1.0))
-            .padding(3.0)
-            .border(Color::YELLOW, 1.0);
-        tag_row = tag_row.with_child(tag_label).with_spacer(5.0);
+// The next set of the folder being studied, ready to start in the given mode
+fn next_folder_set(data: &mut AppState, mode: StudyMode) -> Option<Route> {
+    while !data.folder_study.is_empty() {
+        let set_name = data.folder_study.remove(0);
+        if let Some(set_id) = data.catalogue.get_id_by_name(set_name.clone()) {
+            if filter_study_cards(data, set_id, set_name.clone()) {
+                data.folder_study_set = set_name.clone();
+                return Some(Route::for_study(mode, set_name));
+            }
+        }
     }
-    list = list.with_spacer(20.0).with_child(tag_row);
+    data.folder_study_set.clear();
+    None
+}
 
-    let name_for_add = lesson_name.clone();
-    let add_word_button = Button::new("Add Word").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, _data: &mut AppState, _env| {
-            let new_win = WindowDesc::new(add_word_page_builder(lesson_id, name_for_add.clone()))
-                .title(name_for_add.clone());
-            ctx.window().close();
-            ctx.new_window(new_win);
+// Studies every set in the folder and its subfolders one after another
+fn start_folder_study(data: &mut AppState, folder: String, mode: StudyMode) {
+    let mut items: Vec<ListItem> = folders::get_items_under_folder(&data.catalogue, &folder)
+        .into_iter()
+        .filter(|item| item.get_num_of_cards() > 0)
+        .collect();
+    items.sort_by(|a, b| {
+        sort::natural_cmp(&a.get_folder(), &b.get_folder())
+            .then_with(|| sort::natural_cmp(&a.get_name(), &b.get_name()))
+    });
+    data.folder_study = items.iter().map(|item| item.get_name()).collect();
+    match next_folder_set(data, mode) {
+        Some(route) => {
+            data.folder_message.clear();
+            data.nav.push(route);
+        }
+        None => {
+            data.folder_message = format!(
+                "No cards to study in [{}]",
+                folders::get_display_name(&folder)
+            )
+        }
+    }
+}
+
+fn folder_study_buttons(folder: String) -> Flex<AppState> {
+    let folder_for_learn = folder.clone();
+    let learn_button = Button::new("Learn All").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            start_folder_study(data, folder_for_learn.clone(), StudyMode::Learn);
         },
     );
-    list = list.with_spacer(30.0).with_child(add_word_button);
-    let mut new_cards = cards.clone();
-    new_cards.reverse();
-    for card in new_cards {
-        let card_id = card.get_id();
-        let card_word = card.get_word();
-        let card_ans = card.get_ans();
-        let card_remarks = card.get_remarks();
-        let name_for_delete = lesson_name.clone();
-        let delete_word_button = Button::new("Delete").on_click(
-            move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-                let mut target_set = Storage::read_set_file(name_for_delete.clone());
-                target_set.delete_card(card_id);
-                let window_title = target_set.get_set_name();
-                let new_win = WindowDesc::new(view_page_builder(
-                    lesson_id,
-                    window_title.clone(),
-                    target_set.get_all_cards(),
-                    target_set.get_all_tags(),
-                ))
-                .title(window_title);
-                data.catalogue.update_set(lesson_id, target_set);
-                Storage::update_inventory(data.catalogue.clone());
-                ctx.window().close();
-                ctx.new_window(new_win);
+    let test_button = Button::new("Test All").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            start_folder_study(data, folder.clone(), StudyMode::Test);
+        },
+    );
+    Flex::row()
+        .with_child(learn_button)
+        .with_spacer(10.0)
+        .with_child(test_button)
+}
+
+// Subfolders of the folder being browsed on the study sets list, and actions on the folder
+fn folder_browser_builder(folder: String, catalogue: &Catalogue) -> impl Widget<AppState> {
+    let mut browser = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+    let mut title_row = Flex::row().with_child(
+        Label::new(format!("Folder: {}", folders::get_display_name(&folder)))
+            .with_text_size(24.0)
+            .with_text_color(Color::TEAL),
+    );
+    if !folder.is_empty() {
+        let parent = folders::get_parent(&folder);
+        let up_button = Button::new("Up").on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                data.folder_message.clear();
+                data.nav
+                    .replace(Route::List(ListFilter::Folder(parent.clone())));
             },
         );
-        let name_for_edit = lesson_name.clone();
-        let edit_word_button = Button::new("Edit").on_click(
-            move |ctx: &mut druid::EventCtx<'_, '_>, _data: &mut AppState, _env| {
-                let curr_card = Storage::read_set_file(name_for_edit.clone()).get_card(card_id);
-                let new_win = WindowDesc::new(edit_word_page_builder(
-                    lesson_id,
-                    card.get_id(),
-                    curr_card.get_word(),
-                    curr_card.get_ans(),
-                    curr_card.get_remarks(),
-                    name_for_edit.clone(),
+        title_row = title_row
+            .with_spacer(20.0)
+            .with_child(up_button)
+            .with_spacer(10.0)
+            .with_child(folder_study_buttons(folder.clone()));
+    }
+    browser.add_child(title_row);
+    for subfolder in folders::get_subfolders(&Storage::get_all_folders(), &folder) {
+        let num_of_sets = folders::get_items_under_folder(catalogue, &subfolder).len();
+        let folder_to_open = subfolder.clone();
+        let open_button = Button::new("Open").on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                data.folder_message.clear();
+                data.nav
+                    .replace(Route::List(ListFilter::Folder(folder_to_open.clone())));
+            },
+        );
+        let subfolder_row = Flex::row()
+            .with_spacer(20.0)
+            .with_child(
+                Label::new(format!(
+                    "{} ({} sets)",
+                    folders::get_folder_name(&subfolder),
+                    num_of_sets
                 ))
-                .title("Edit Word");
-                ctx.window().close();
-                ctx.new_window(new_win);
+                .with_text_size(20.0),
+            )
+            .with_spacer(10.0)
+            .with_child(open_button)
+            .with_spacer(10.0)
+            .with_child(folder_study_buttons(subfolder));
+        browser = browser.with_spacer(5.0).with_child(subfolder_row);
+    }
+    let new_folder_input = TextBox::new()
+        .with_placeholder("New Folder Name")
+        .with_text_size(20.0)
+        .fix_width(250.0)
+        .lens(AppState::new_folder_name);
+    let folder_for_add = folder.clone();
+    let add_folder_button = Button::new("Add Folder").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            match folders::normalize_folder(&data.new_folder_name) {
+                Ok(name) if name.is_empty() => {}
+                Ok(name) => {
+                    let new_folder = folders::join_folder(&folder_for_add, &name);
+                    match Storage::create_folder(&new_folder) {
+                        Ok(()) => {
+                            data.new_folder_name.clear();
+                            data.folder_message.clear();
+                            data.nav.refresh();
+                        }
+                        Err(err) => data.folder_message = err,
+                    }
+                }
+                Err(err) => data.folder_message = err,
+            }
+        },
+    );
+    let mut actions_row = Flex::row()
+        .with_child(new_folder_input)
+        .with_spacer(10.0)
+        .with_child(add_folder_button);
+    if !folder.is_empty() {
+        let move_folder_input = TextBox::new()
+            .with_placeholder(folder.clone())
+            .with_text_size(20.0)
+            .fix_width(250.0)
+            .lens(AppState::new_set_folder);
+        let folder_for_move = folder.clone();
+        let move_folder_button = Button::new("Rename / Move Folder").on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                let target = match folders::normalize_folder(&data.new_set_folder) {
+                    Ok(target) if target.is_empty() || target == folder_for_move => return,
+                    Ok(target) => target,
+                    Err(err) => {
+                        data.folder_message = err;
+                        return;
+                    }
+                };
+                match folders::move_folder_with_sets(&mut data.catalogue, &folder_for_move, &target)
+                {
+                    Ok(()) => {
+                        Storage::update_inventory(data.catalogue.clone());
+                        data.new_set_folder.clear();
+                        data.folder_message.clear();
+                        data.nav.replace(Route::List(ListFilter::Folder(target)));
+                    }
+                    Err(err) => data.folder_message = err,
+                }
             },
         );
-        let word = format!("Word {}:\n[{}]", card_id + 1, card_word);
-        let word_label: Label<AppState> = Label::new(word)
-            .with_text_size(24.0)
-            .with_text_color(Color::FUCHSIA);
-        let mut word_row: Flex<AppState> = Flex::column();
-        let expected_ans = format!("Correct Answer:\n[{}]", card_ans);
-        let answer_label: Label<AppState> = Label::new(expected_ans)
+        let folder_for_delete = folder.clone();
+        let delete_folder_button = Button::new("Delete Folder").on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                match Storage::delete_folder(&folder_for_delete) {
+                    Ok(()) => {
+                        data.folder_message.clear();
+                        data.nav
+                            .replace(Route::List(ListFilter::Folder(folders::get_parent(
+                                &folder_for_delete,
+                            ))));
+                    }
+                    Err(err) => data.folder_message = err,
+                }
+            },
+        );
+        actions_row = actions_row
+            .with_spacer(20.0)
+            .with_child(move_folder_input)
+            .with_spacer(10.0)
+            .with_child(move_folder_button)
+            .with_spacer(10.0)
+            .with_child(delete_folder_button);
+    }
+    let message_label = Label::dynamic(|data: &AppState, _env| data.folder_message.clone())
+        .with_text_size(20.0)
+        .with_text_color(Color::YELLOW);
+    browser
+        .with_spacer(10.0)
+        .with_child(actions_row)
+        .with_child(message_label)
+        .padding(10.0)
+}
+
+fn search_results_builder(results: Vec<SearchResult>) -> impl Widget<AppState> {
+    let mut list: Flex<AppState> = Flex::column();
+    let count_label = Label::new(format!("{} Matching Words", results.len()))
+        .with_text_size(24.0)
+        .with_text_color(Color::AQUA);
+    list.add_child(count_label);
+    for result in results.into_iter().take(MAX_SEARCH_RESULTS) {
+        let card = result.get_card();
+        let set_name = result.get_set_name();
+        let matched_fields: Vec<String> = result
+            .get_matched_fields()
+            .iter()
+            .map(|field| field.get_label())
+            .collect();
+        let set_label = Label::new(format!(
+            "{} (matched {})",
+            set_name,
+            matched_fields.join(", ")
+        ))
+        .with_text_size(20.0)
+        .with_text_color(Color::TEAL);
+        let word_label = Label::new(format!(
+            "Word {}:\n[{}]",
+            card.get_id() + 1,
+            card.get_word()
+        ))
+        .with_text_size(24.0)
+        .with_text_color(Color::FUCHSIA);
+        let answer_label = Label::new(format!("Correct Answer:\n[{}]", card.get_ans()))
             .with_text_size(24.0)
             .with_text_color(Color::SILVER);
-        let remarks = format!("Remarks:\n[{}]", card_remarks);
-        let remarks_label: Label<AppState> = Label::new(remarks)
+        let remarks_label = Label::new(format!("Remarks:\n[{}]", card.get_remarks()))
             .with_text_size(24.0)
             .with_text_color(Color::OLIVE);
+        let name_for_view = set_name.clone();
+        let view_button = Button::new("View Set").on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                data.nav.push(Route::View(name_for_view.clone()));
+            },
+        );
+        let name_for_edit = set_name.clone();
+        let word_id = card.get_id();
+        let edit_button = Button::new("Edit Word").on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                data.nav
+                    .push(Route::EditWord(name_for_edit.clone(), word_id));
+            },
+        );
         let buttons_row = Flex::row()
-            .with_child(edit_word_button)
+            .with_child(view_button)
             .with_spacer(10.0)
-            .with_child(delete_word_button);
-        word_row = word_row
+            .with_child(edit_button);
+        let word_row = Flex::column()
+            .with_child(set_label.align_left())
             .with_child(word_label.align_left())
             .with_child(answer_label.align_left())
             .with_child(remarks_label.align_left())
             .with_child(buttons_row);
-        list = list.with_child(
+        list.add_child(
             word_row
                 .padding(20.0)
                 .border(Color::YELLOW, 1.0)
                 .padding(5.0),
         );
     }
-    let scroll = Scroll::new(list.padding(40.0)).vertical();
-    scroll
+    list
+}
+
+fn search_page_builder() -> impl Widget<AppState> {
+    let index = SearchIndex::new(Storage::read_data());
+    let return_to_main = Button::new("Return to Study Sets List").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.nav.home();
+        },
+    );
+    let search_label = Label::new("Search Words, Answers and Remarks")
+        .with_text_size(32.0)
+        .with_text_color(Color::PURPLE);
+    let query_input = TextBox::new()
+        .with_placeholder("Enter text to search")
+        .with_text_size(24.0)
+        .fix_width(300.0)
+        .lens(AppState::search_query);
+    let card_tag_input = TextBox::new()
+        .with_placeholder("Only cards with this tag (optional)")
+        .with_text_size(24.0)
+        .fix_width(300.0)
+        .lens(AppState::search_card_tag);
+    let prefix_toggle =
+        Checkbox::new("Match Start of Words Only").lens(AppState::search_prefix_only);
+    let kana_toggle =
+        Checkbox::new("Ignore Romaji / Hiragana / Katakana").lens(AppState::search_ignore_kana);
+    let results = ViewSwitcher::new(
+        |data: &AppState, _env| {
+            (
+                data.search_query.clone(),
+                data.search_prefix_only,
+                data.search_ignore_kana,
+                data.search_card_tag.clone(),
+            )
+        },
+        move |(query, prefix_only, ignore_kana, card_tag),
+              _data,
+              _env|
+              -> Box<dyn Widget<AppState>> {
+            let mode = if *prefix_only {
+                MatchMode::Prefix
+            } else {
+                MatchMode::Substring
+            };
+            Box::new(search_results_builder(index.search(
+                query.clone(),
+                mode,
+                *ignore_kana,
+                card_tag.clone(),
+            )))
+        },
+    );
+    let list = Flex::column()
+        .with_child(return_to_main.align_left())
+        .with_spacer(30.0)
+        .with_child(search_label)
+        .with_spacer(20.0)
+        .with_child(query_input)
+        .with_spacer(10.0)
+        .with_child(card_tag_input)
+        .with_spacer(10.0)
+        .with_child(prefix_toggle)
+        .with_spacer(10.0)
+        .with_child(kana_toggle)
+        .with_spacer(30.0)
+        .with_child(results);
+    Scroll::new(list.padding(40.0))
+        .vertical()
+        .controller(KeepScroll::new())
+}
+
+fn open_replace_page(data: &mut AppState, scope: ReplaceScope, scope_text: String) {
+    data.replace_scope = scope;
+    data.replace_scope_text = scope_text;
+    data.replace_preview.clear();
+    data.replace_preview_key.clear();
+    data.replace_message.clear();
+    data.nav.push(Route::Replace);
+}
+
+fn get_replacement(data: &AppState) -> Replacement {
+    let fields = [
+        (CardField::Word, data.replace_in_word),
+        (CardField::Answer, data.replace_in_ans),
+        (CardField::Remarks, data.replace_in_remarks),
+    ]
+    .into_iter()
+    .filter(|(_, is_picked)| *is_picked)
+    .map(|(field, _)| field)
+    .collect();
+    Replacement::new(
+        data.replace_find.clone(),
+        data.replace_with.clone(),
+        data.replace_is_regex,
+        data.replace_match_case,
+        fields,
+    )
+}
+
+// Everything the preview depends on, so a replace is only made as previewed
+fn get_replace_key(data: &AppState) -> String {
+    format!(
+        "{:?} {:?} {}",
+        get_replacement(data),
+        data.replace_scope,
+        data.replace_scope_text
+    )
+}
+
+fn preview_replace(data: &mut AppState) {
+    let result = replace::get_scope_sets(
+        data.replace_scope,
+        &data.replace_scope_text,
+        &data.catalogue,
+    )
+    .and_then(|set_names| {
+        let study_sets = set_names
+            .into_iter()
+            .map(Storage::read_set_file)
+            .collect::<Result<Vec<StudySet>, String>>()?;
+        replace::find_changes(&get_replacement(data), &study_sets)
+    });
+    match result {
+        Ok(changes) => {
+            data.replace_message = format!("{} fields will be changed", changes.len());
+            data.replace_preview = changes;
+            data.replace_preview_key = get_replace_key(data);
+        }
+        Err(err) => {
+            data.replace_message = err;
+            data.replace_preview.clear();
+            data.replace_preview_key.clear();
+        }
+    }
+    data.nav.refresh();
 }
 
-fn list_page_builder(items: Vec<ListItem>, tags: Vec<String>) -> impl Widget<AppState> {
-    let num_of_items = items.len();
-    let mut list: Flex<AppState> = Flex::column();
-    let filter_label = Label::new("Filter by tags")
+fn apply_replace(data: &mut AppState) {
+    if data.replace_preview.is_empty() || data.replace_preview_key != get_replace_key(data) {
+        data.replace_message = String::from("Click Preview to see the changes first");
+        return;
+    }
+    match replace::apply_changes(&data.replace_preview, &data.catalogue) {
+        Ok(applied) => {
+            let num_skipped = data.replace_preview.len() - applied.len();
+            data.replace_message = if num_skipped == 0 {
+                format!("Changed {} fields", applied.len())
+            } else {
+                format!(
+                    "Changed {} fields, {} were edited since the preview and left alone",
+                    applied.len(),
+                    num_skipped
+                )
+            };
+            data.replace_undo = applied;
+            data.replace_preview.clear();
+            data.replace_preview_key.clear();
+            data.nav.refresh();
+        }
+        Err(err) => data.replace_message = err,
+    }
+}
+
+fn undo_replace(data: &mut AppState) {
+    if data.replace_undo.is_empty() {
+        data.replace_message = String::from("Nothing to undo");
+        return;
+    }
+    let changes: Vec<CardChange> = data
+        .replace_undo
+        .iter()
+        .map(|change| change.reversed())
+        .collect();
+    match replace::apply_changes(&changes, &data.catalogue) {
+        Ok(applied) => {
+            let num_skipped = changes.len() - applied.len();
+            data.replace_message = if num_skipped == 0 {
+                format!("Undid {} changes", applied.len())
+            } else {
+                format!(
+                    "Undid {} changes, {} fields were edited since and left alone",
+                    applied.len(),
+                    num_skipped
+                )
+            };
+            data.replace_undo.clear();
+            data.replace_preview.clear();
+            data.replace_preview_key.clear();
+            data.nav.refresh();
+        }
+        Err(err) => data.replace_message = err,
+    }
+}
+
+fn replace_page_builder(preview: Vec<CardChange>) -> impl Widget<AppState> {
+    let return_to_main = Button::new("Return to Study Sets List").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.nav.home();
+        },
+    );
+    let replace_label = Label::new("Find and Replace in Words, Answers and Remarks")
         .with_text_size(32.0)
         .with_text_color(Color::PURPLE);
-    let mut tags_list: Flex<AppState> = Flex::row().with_spacer(10.0);
-    for i in 0..tags.len() {
-        let curr_tag = tags[i].clone();
-        let curr_tag_for_filter = curr_tag.clone();
-        let filter_button = Button::new(curr_tag.clone())
+    let find_input = TextBox::new()
+        .with_placeholder("Find")
+        .with_text_size(24.0)
+        .fix_width(400.0)
+        .lens(AppState::replace_find);
+    let replace_input = TextBox::new()
+        .with_placeholder("Replace with")
+        .with_text_size(24.0)
+        .fix_width(400.0)
+        .lens(AppState::replace_with);
+    let options_row = Flex::row()
+        .with_child(Checkbox::new("Regular Expression").lens(AppState::replace_is_regex))
+        .with_spacer(10.0)
+        .with_child(Checkbox::new("Match Case").lens(AppState::replace_match_case));
+    let fields_row = Flex::row()
+        .with_child(Label::new("In").with_text_size(20.0))
+        .with_spacer(10.0)
+        .with_child(Checkbox::new("Word").lens(AppState::replace_in_word))
+        .with_spacer(10.0)
+        .with_child(Checkbox::new("Answer").lens(AppState::replace_in_ans))
+        .with_spacer(10.0)
+        .with_child(Checkbox::new("Remarks").lens(AppState::replace_in_remarks));
+    let mut scope_row = Flex::row().with_child(Label::new("Sets").with_text_size(20.0));
+    for scope in ReplaceScope::get_all() {
+        let scope_button = Button::new(scope.get_label())
             .background(Painter::new(move |ctx, data: &AppState, _env| {
                 let bounds = ctx.size().to_rect();
-                if data.current_filter.contains(&curr_tag_for_filter) {
+                if data.replace_scope == scope {
                     ctx.fill(bounds, &SELECTED_TAG_COLOR);
                 } else {
                     ctx.fill(bounds, &UNSELECTED_TAG_COLOR);
@@ -941,222 +4843,370 @@ fn list_page_builder(items: Vec<ListItem>, tags: Vec<String>) -> impl Widget<App
             }))
             .on_click(
                 move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-                    if data.current_filter.contains(&curr_tag) {
-                        data.current_filter.remove(&curr_tag);
-                    } else {
-                        data.current_filter.insert(curr_tag.clone());
-                    }
+                    data.replace_scope = scope;
                 },
             );
-        tags_list = tags_list.with_child(filter_button).with_spacer(10.0);
+        scope_row = scope_row.with_spacer(10.0).with_child(scope_button);
     }
-    let inner_tags_list = Scroll::new(tags_list.padding(20.0).center()).horizontal();
-    list.add_child(filter_label);
-    list.add_child(inner_tags_list);
-
-    let match_all = Button::new("Match All").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-            if !data.current_filter.is_empty() {
-                let new_win = WindowDesc::new(list_page_builder(
-                    data.catalogue
-                        .get_items_by_tags(data.current_filter.clone(), false),
-                    data.catalogue.get_all_tags(),
-                ))
-                .title(MAIN_TITLE);
-                data.current_filter.clear();
-                ctx.window().close();
-                ctx.new_window(new_win);
-            }
-        },
-    );
-    let match_any = Button::new("Match Any").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-            if !data.current_filter.is_empty() {
-                let new_win = WindowDesc::new(list_page_builder(
-                    data.catalogue
-                        .get_items_by_tags(data.current_filter.clone(), true),
-                    data.catalogue.get_all_tags(),
-                ))
-                .title(MAIN_TITLE);
-                data.current_filter.clear();
-                ctx.window().close();
-                ctx.new_window(new_win);
-            }
+    let scope_input = TextBox::new()
+        .with_placeholder("Set name, or a query such as Kanji AND NOT N5")
+        .with_text_size(24.0)
+        .fix_width(400.0)
+        .lens(AppState::replace_scope_text);
+    let preview_button = Button::new("Preview").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            preview_replace(data);
         },
     );
-
-    let all_sets = Button::new("See All Sets").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-            if data.catalogue.get_num_of_items() != num_of_items {
-                let new_win = WindowDesc::new(list_page_builder(
-                    data.catalogue.get_inventory(),
-                    data.catalogue.get_all_tags(),
-                ))
-                .title(MAIN_TITLE);
-                data.current_filter.clear();
-                ctx.window().close();
-                ctx.new_window(new_win);
-            }
+    let apply_button = Button::new("Replace All").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            apply_replace(data);
         },
     );
-
-    let untagged_sets = Button::new("See All Untagged Sets").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-            let new_win = WindowDesc::new(list_page_builder(
-                data.catalogue.get_all_untagged_study_sets(),
-                data.catalogue.get_all_tags(),
-            ))
-            .title(MAIN_TITLE);
-            data.current_filter.clear();
-            ctx.window().close();
-            ctx.new_window(new_win);
+    let undo_button = Button::dynamic(|data: &AppState, _env| {
+        format!("Undo Last Replace ({})", data.replace_undo.len())
+    })
+    .on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            undo_replace(data);
         },
     );
+    let buttons_row = Flex::row()
+        .with_child(preview_button)
+        .with_spacer(10.0)
+        .with_child(apply_button)
+        .with_spacer(30.0)
+        .with_child(undo_button);
+    let message_label = Label::dynamic(|data: &AppState, _env| data.replace_message.clone())
+        .with_text_size(24.0)
+        .with_text_color(Color::YELLOW);
 
-    let mut filter_buttons = Flex::row();
-    filter_buttons = filter_buttons
-        .with_child(match_all)
+    let mut list = Flex::column()
+        .with_child(return_to_main.align_left())
+        .with_spacer(30.0)
+        .with_child(replace_label)
+        .with_spacer(20.0)
+        .with_child(find_input)
         .with_spacer(10.0)
-        .with_child(match_any)
+        .with_child(replace_input)
         .with_spacer(10.0)
-        .with_child(all_sets)
+        .with_child(options_row)
         .with_spacer(10.0)
-        .with_child(untagged_sets);
-
-    list.add_child(filter_buttons);
-    for item in items {
-        let id = item.get_id();
-        let num_of_cards = item.get_num_of_cards();
-        let name_for_view = item.get_name();
-        let name_for_learn = item.get_name();
-        let name_for_test = item.get_name();
-        let name_for_hard_test = name_for_test.clone();
-        let name_for_edit = item.get_name();
-        let mut section = Flex::column();
-        let set_name_label = Label::new(item.get_name()).with_text_size(24.0);
-        section.add_child(set_name_label);
-        for tag in item.get_all_tags() {
-            let tag_label = Label::new(tag).with_text_color(Color::LIME);
-            section = section.with_spacer(5.0).with_child(tag_label);
-        }
-        let view_button = Button::new("View").on_click(
-            move |ctx: &mut druid::EventCtx<'_, '_>, _data: &mut AppState, _env| {
-                let study_set = Storage::read_set_file(name_for_view.clone());
-                let set_name = study_set.get_set_name();
-                let new_win = WindowDesc::new(view_page_builder(
-                    id,
-                    set_name.clone(),
-                    study_set.get_all_cards(),
-                    study_set.get_all_tags(),
-                ))
-                .title(set_name.clone());
-                ctx.window().close();
-                ctx.new_window(new_win);
-            },
-        );
-        let learn_button = Button::new("Learn").on_click(
-            move |ctx: &mut druid::EventCtx<'_, '_>, _data: &mut AppState, _env| {
-                let study_set = Storage::read_set_file(name_for_learn.clone());
-                if study_set.get_num_of_cards() > 0 {
-                    let new_win = WindowDesc::new(learn_page_builder(
-                        id,
-                        study_set.get_set_name(),
-                    ))
-                    .title(study_set.get_set_name());
-                    ctx.window().close();
-                    ctx.new_window(new_win);
-                }
-            },
+        .with_child(fields_row)
+        .with_spacer(10.0)
+        .with_child(scope_row)
+        .with_spacer(10.0)
+        .with_child(scope_input)
+        .with_spacer(20.0)
+        .with_child(buttons_row)
+        .with_spacer(10.0)
+        .with_child(message_label)
+        .with_spacer(20.0);
+    for change in preview.iter().take(MAX_SEARCH_RESULTS) {
+        let title = Label::new(format!(
+            "{} Word {} ({})",
+            change.get_set_name(),
+            change.get_card_id() + 1,
+            change.get_field().get_label()
+        ))
+        .with_text_size(20.0)
+        .with_text_color(Color::TEAL);
+        let change_column = Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(title)
+            .with_child(
+                Label::new(format!("- {}", change.get_before()))
+                    .with_text_size(20.0)
+                    .with_text_color(Color::RED),
+            )
+            .with_child(
+                Label::new(format!("+ {}", change.get_after()))
+                    .with_text_size(20.0)
+                    .with_text_color(Color::LIME),
+            )
+            .padding(10.0)
+            .border(Color::YELLOW, 1.0)
+            .padding(5.0);
+        list = list.with_child(change_column);
+    }
+    if preview.len() > MAX_SEARCH_RESULTS {
+        list = list.with_child(
+            Label::new(format!("and {} more", preview.len() - MAX_SEARCH_RESULTS))
+                .with_text_size(20.0),
         );
-        let test_button = Button::new("Test").on_click(
-            move |ctx: &mut druid::EventCtx<'_, '_>, _data: &mut AppState, _env| {
-                if num_of_cards > 0 {
-                    let new_win =
-                        WindowDesc::new(test_page_builder(id, name_for_test.clone()))
-                            .title(name_for_test.clone());
-                    ctx.window().close();
-                    ctx.new_window(new_win);
-                }
-            },
+    }
+    Scroll::new(list.padding(40.0))
+        .vertical()
+        .controller(KeepScroll::new())
+}
+
+fn duplicate_group_builder(group: DuplicateGroup) -> impl Widget<AppState> {
+    let cards = group.get_cards();
+    let group_label = Label::new(format!(
+        "Same {}: [{}]",
+        group.get_field().get_label(),
+        match group.get_field() {
+            CardField::Word => cards[0].get_card().get_word(),
+            _ => cards[0].get_card().get_ans(),
+        }
+    ))
+    .with_text_size(24.0)
+    .with_text_color(Color::TEAL);
+    let group_id = group.get_id();
+    let keep_button = Button::new("Keep All").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.kept_duplicates.push(group_id.clone());
+            data.nav.refresh();
+        },
+    );
+    let mut section = Flex::column()
+        .with_child(
+            Flex::row()
+                .with_child(group_label)
+                .with_spacer(20.0)
+                .with_child(keep_button),
+        )
+        .with_spacer(10.0);
+    for i in 0..cards.len() {
+        let card = cards[i].get_card();
+        let card_info = format!(
+            "{} Word {}:\n[{}] [{}]\nRemarks: [{}]",
+            cards[i].get_set_name(),
+            card.get_id() + 1,
+            card.get_word(),
+            card.get_ans(),
+            card.get_remarks()
         );
-        let hard_test_button = Button::new("Test No Remarks").on_click(
-            move |ctx: &mut druid::EventCtx<'_, '_>, _data: &mut AppState, _env| {
-                if num_of_cards > 0 {
-                    let new_win =
-                        WindowDesc::new(test_no_remarks_page_builder(id, name_for_hard_test.clone()))
-                            .title(name_for_hard_test.clone());
-                    ctx.window().close();
-                    ctx.new_window(new_win);
-                }
+        let card_label: Label<AppState> = Label::new(card_info)
+            .with_text_size(20.0)
+            .with_text_color(Color::SILVER);
+        let target = cards[i].clone();
+        let others: Vec<CardRef> = cards
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, card_ref)| card_ref.clone())
+            .collect();
+        let merge_button = Button::new("Merge Into This").on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                let prev_catalogue = data.catalogue.clone();
+                let changed_sets =
+                    duplicates::merge_cards(&mut data.catalogue, target.clone(), others.clone());
+                data.sync_with_catalogue(&prev_catalogue);
+                forget_sets_card_positions(data, changed_sets);
+                data.nav.refresh();
             },
         );
+        let to_delete = cards[i].clone();
         let delete_button = Button::new("Delete").on_click(
-            move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-                data.catalogue.delete_item_by_id(id.clone());
-                Storage::update_inventory(data.catalogue.clone());
-                let new_win = WindowDesc::new(list_page_builder(
-                    data.catalogue.get_inventory(),
-                    data.catalogue.get_all_tags(),
-                ))
-                .title(MAIN_TITLE);
-                ctx.window().close();
-                ctx.new_window(new_win);
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                let prev_catalogue = data.catalogue.clone();
+                let changed_sets =
+                    duplicates::delete_cards(&mut data.catalogue, vec![to_delete.clone()]);
+                data.sync_with_catalogue(&prev_catalogue);
+                forget_sets_card_positions(data, changed_sets);
+                data.nav.refresh();
             },
         );
+        let buttons_row = Flex::row()
+            .with_child(merge_button)
+            .with_spacer(10.0)
+            .with_child(delete_button);
+        section = section
+            .with_child(card_label.align_left())
+            .with_child(buttons_row.align_left())
+            .with_spacer(10.0);
+    }
+    section
+        .padding(20.0)
+        .border(Color::YELLOW, 1.0)
+        .padding(5.0)
+}
 
-        let edit_setname_button = Button::new("Edit").on_click(
-            move |ctx: &mut druid::EventCtx<'_, '_>, _data: &mut AppState, _env| {
-                let study_set = Storage::read_set_file(name_for_edit.clone());
-                let new_win = WindowDesc::new(edit_set_page_builder(
-                    id,
-                    study_set.get_set_name(),
-                    study_set.get_all_tags(),
-                ))
-                .title("Edit Set Name & Tags");
-                ctx.window().close();
-                ctx.new_window(new_win);
-            },
-        );
-        let mut row = Flex::row();
-        row.add_child(view_button);
-        row.add_child(learn_button);
-        row.add_child(test_button);
-        row.add_child(hard_test_button);
-        row.add_child(delete_button);
-        row.add_child(edit_setname_button);
-        section = section.with_spacer(20.0).with_child(row);
-        list.add_child(
-            section
-                .padding(30.0)
-                .border(Color::OLIVE, 2.0)
-                .padding(10.0),
-        );
+fn duplicates_page_builder(kept_duplicates: Vec<String>) -> impl Widget<AppState> {
+    let return_to_main = Button::new("Return to Study Sets List").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.nav.home();
+        },
+    );
+    let groups: Vec<DuplicateGroup> = DuplicateFinder::new(Storage::read_data())
+        .get_duplicate_groups()
+        .into_iter()
+        .filter(|group| !kept_duplicates.contains(&group.get_id()))
+        .collect();
+    let title_label = Label::new(format!("{} Groups of Similar Words", groups.len()))
+        .with_text_size(32.0)
+        .with_text_color(Color::PURPLE);
+    let mut list: Flex<AppState> = Flex::column()
+        .with_child(return_to_main.align_left())
+        .with_spacer(30.0)
+        .with_child(title_label)
+        .with_spacer(20.0);
+    for group in groups {
+        list.add_child(duplicate_group_builder(group));
     }
-    let add_set_button = Button::new("Add Set").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, _data: &mut AppState, _env| {
-            let new_win = WindowDesc::new(add_set_page_builder()).title("Add New Set");
-            ctx.window().close();
-            ctx.new_window(new_win);
+    Scroll::new(list.padding(40.0))
+        .vertical()
+        .controller(KeepScroll::new())
+}
+
+fn tags_page_builder(catalogue: &Catalogue) -> impl Widget<AppState> {
+    let tag_colors = Storage::read_settings_file().get_tag_colors();
+    let return_to_main = Button::new("Return to Study Sets List").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.nav.home();
         },
     );
-    list = list
+    let tags_label = Label::new("Manage Tags")
+        .with_text_size(32.0)
+        .with_text_color(Color::PURPLE);
+    let selected_label = Label::dynamic(|data: &AppState, _env| -> String {
+        if data.tag_to_manage.is_empty() {
+            return String::from("Select a tag below to rename, merge or delete it in every set");
+        }
+        format!("Selected: {}", data.tag_to_manage)
+    })
+    .with_text_size(24.0)
+    .with_text_color(Color::YELLOW);
+    let new_name_input = TextBox::new()
+        .with_placeholder("New Name, e.g. LAJ/1/kanji")
+        .with_text_size(24.0)
+        .fix_width(300.0)
+        .lens(AppState::new_tag_name);
+    let rename_button = Button::new("Rename / Merge").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            let from = data.tag_to_manage.clone();
+            let to = tags::normalize_tag(&data.new_tag_name);
+            if from.is_empty() || to.is_empty() || from == to {
+                return;
+            }
+            // renaming onto a tag that is already used merges the two
+            let is_merge = tags::get_all_tags(&data.catalogue).contains(&to);
+            let num_of_sets = tags::rename_tag_in_sets(&mut data.catalogue, &from, &to);
+            Storage::update_inventory(data.catalogue.clone());
+            let mut settings = Storage::read_settings_file();
+            settings.rename_tag(&from, &to);
+            Storage::update_settings(settings);
+            data.tag_message = if is_merge {
+                format!("Merged [{}] into [{}] in {} sets", from, to, num_of_sets)
+            } else {
+                format!("Renamed [{}] to [{}] in {} sets", from, to, num_of_sets)
+            };
+            data.tag_to_manage = to;
+            data.new_tag_name.clear();
+            data.current_filter.clear();
+            data.nav.refresh();
+        },
+    );
+    let delete_button = Button::new("Delete Tag").on_click(
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            let tag = data.tag_to_manage.clone();
+            if tag.is_empty() {
+                return;
+            }
+            let num_of_sets = tags::delete_tag_from_sets(&mut data.catalogue, &tag);
+            Storage::update_inventory(data.catalogue.clone());
+            let mut settings = Storage::read_settings_file();
+            settings.delete_tag(&tag);
+            Storage::update_settings(settings);
+            data.tag_message = format!("Deleted [{}] from {} sets", tag, num_of_sets);
+            data.tag_to_manage.clear();
+            data.current_filter.clear();
+            data.nav.refresh();
+        },
+    );
+    let message_label = Label::dynamic(|data: &AppState, _env| data.tag_message.clone())
+        .with_text_size(20.0)
+        .with_text_color(Color::YELLOW);
+    let mut tags_list = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+    for tag in tags::get_all_tags(catalogue) {
+        let num_of_sets =
+            tags::get_items_by_tags(catalogue, &HashSet::from([tag.clone()]), true).len();
+        let own_color = tag_colors
+            .iter()
+            .find(|tag_color| tag_color.get_tag() == tag)
+            .map(|tag_color| tag_color.get_color());
+        let tag_for_select = tag.clone();
+        let tag_for_painter = tag.clone();
+        let select_button = Button::new(tags::get_leaf(&tag))
+            .background(Painter::new(move |ctx, data: &AppState, _env| {
+                let bounds = ctx.size().to_rect();
+                if data.tag_to_manage == tag_for_painter {
+                    ctx.fill(bounds, &SELECTED_TAG_COLOR);
+                } else {
+                    ctx.fill(bounds, &UNSELECTED_TAG_COLOR);
+                }
+            }))
+            .on_click(
+                move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                    data.tag_to_manage = tag_for_select.clone();
+                },
+            );
+        let tag_label = Label::new(format!("{} ({} sets)", tag, num_of_sets))
+            .with_text_size(20.0)
+            .with_text_color(get_color_code(&tag, &tag_colors));
+        let tag_for_color = tag.clone();
+        let color_button = Button::new(format!(
+            "Colour: {}",
+            own_color.clone().unwrap_or(String::from("None"))
+        ))
+        .on_click(
+            move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+                let mut settings = Storage::read_settings_file();
+                settings.set_tag_color(
+                    tag_for_color.clone(),
+                    tags::next_color_name(own_color.clone()),
+                );
+                Storage::update_settings(settings);
+                data.nav.refresh();
+            },
+        );
+        let tag_row = Flex::row()
+            // nested tags are indented under their parents
+            .with_spacer(10.0 + 30.0 * tags::get_depth(&tag) as f64)
+            .with_child(select_button)
+            .with_spacer(10.0)
+            .with_child(tag_label)
+            .with_spacer(10.0)
+            .with_child(color_button);
+        tags_list = tags_list.with_child(tag_row).with_spacer(5.0);
+    }
+    if tags::get_all_tags(catalogue).is_empty() {
+        tags_list.add_child(Label::new("No Study Set has any tags yet").with_text_size(24.0));
+    }
+    let list = Flex::column()
+        .with_child(return_to_main.align_left())
+        .with_spacer(30.0)
+        .with_child(tags_label)
+        .with_spacer(20.0)
+        .with_child(selected_label)
         .with_spacer(10.0)
-        .with_child(add_set_button.center())
-        .with_spacer(30.0);
-    let scroll = Scroll::new(list).vertical();
-    let aligned_widget = Align::right(scroll);
-    aligned_widget
+        .with_child(
+            Flex::row()
+                .with_child(new_name_input)
+                .with_spacer(10.0)
+                .with_child(rename_button)
+                .with_spacer(10.0)
+                .with_child(delete_button),
+        )
+        .with_spacer(10.0)
+        .with_child(message_label)
+        .with_spacer(30.0)
+        .with_child(tags_list.align_left());
+    Scroll::new(list.padding(40.0))
+        .vertical()
+        .controller(KeepScroll::new())
 }
 
 fn add_set_page_builder() -> impl Widget<AppState> {
     let error_label = Label::dynamic(|data: &AppState, _env| -> String {
-        if !is_valid(data.new_set_name.clone()) {
-            return String::from("Set Name Cannot Be Empty");
+        if let Err(err) = check_set_name(&data.new_set_name, &data.catalogue.get_all_names()) {
+            return err;
         }
-        if has_duplicate(data.new_set_name.clone(), data.catalogue.get_all_names()) {
-            return format!("Set [{}] already exists!", data.new_set_name.trim());
+        if let Err(err) = folders::normalize_folder(&data.new_set_folder) {
+            return err;
         }
-        return String::from("Please input Set Name and Tag(Optional)");
+        String::from("Please input Set Name, Tag and Folder(Optional)")
     })
     .with_text_size(32.0)
     .with_text_color(Color::YELLOW);
@@ -1166,37 +5216,47 @@ fn add_set_page_builder() -> impl Widget<AppState> {
         .fix_width(300.0)
         .lens(AppState::new_set_name);
     let set_tag_input = TextBox::new()
-        .with_placeholder("Enter Tag")
+        .with_placeholder("Enter Tags, e.g. LAJ/1/kanji, N5")
         .with_text_size(24.0)
         .fix_width(300.0)
         .lens(AppState::new_set_tag);
-    let save_button = Button::new("Add Set").on_click(move |ctx, data: &mut AppState, _env| {
+    let set_folder_input = TextBox::new()
+        .with_placeholder("Enter Folder, e.g. LAJ 1/Lessons")
+        .with_text_size(24.0)
+        .fix_width(300.0)
+        .lens(AppState::new_set_folder);
+    let save_button = Button::new("Add Set").on_click(move |_ctx, data: &mut AppState, _env| {
         let set_name = &data.new_set_name;
         let set_tag = &data.new_set_tag;
-        if is_valid(set_name.clone())
-            && !has_duplicate(set_name.clone(), data.catalogue.get_all_names())
-        {
+        let set_folder = match folders::normalize_folder(&data.new_set_folder) {
+            Ok(set_folder) => set_folder,
+            Err(_) => return,
+        };
+        if check_set_name(set_name, &data.catalogue.get_all_names()).is_ok() {
             let mut new_set = StudySet::new(
                 data.catalogue.get_num_of_items(),
-                set_name.trim().to_string()
+                set_name.trim().to_string(),
             );
-            if !set_tag.clone().is_empty() {
-                new_set.add_tag(set_tag.trim().to_string());
+            for tag in tags::parse_tags(set_tag) {
+                new_set.add_tag(tag);
             }
-            data.catalogue.add_study_set(new_set, data.catalogue.get_num_of_items());
+            data.catalogue.add_study_set(
+                new_set,
+                data.catalogue.get_num_of_items(),
+                set_folder.clone(),
+            );
             Storage::update_inventory(data.catalogue.clone());
             data.input_str.push(Vec::new());
             data.res.push(Vec::new());
             data.curr_indexes.push(0);
-            let new_win = WindowDesc::new(list_page_builder(
-                data.catalogue.get_inventory(),
-                data.catalogue.get_all_tags(),
-            ))
-            .title(MAIN_TITLE);
             data.new_set_name.clear();
             data.new_set_tag.clear();
-            ctx.window().close();
-            ctx.new_window(new_win);
+            data.new_set_folder.clear();
+            data.nav.home();
+            if !set_folder.is_empty() {
+                data.nav
+                    .replace(Route::List(ListFilter::Folder(set_folder)));
+            }
         }
     });
     Flex::column()
@@ -1206,24 +5266,104 @@ fn add_set_page_builder() -> impl Widget<AppState> {
         .with_spacer(50.0)
         .with_child(set_tag_input)
         .with_spacer(50.0)
+        .with_child(set_folder_input)
+        .with_spacer(50.0)
         .with_child(save_button)
         .center()
 }
 
+// Saves the set's fields straight away and rebuilds its edit page
+fn save_set_schema(data: &mut AppState, set_id: usize, set_name: String, schema: FieldSchema) {
+    let mut target_set = match Storage::read_set_file(set_name) {
+        Ok(target_set) => target_set,
+        Err(_) => return,
+    };
+    target_set.set_schema(schema);
+    data.catalogue.update_set(set_id, target_set);
+    Storage::update_inventory(data.catalogue.clone());
+    data.nav.refresh();
+}
+
+fn merge_sets(data: &mut AppState, set_name: String) {
+    let set_id = match data.catalogue.get_id_by_name(set_name.clone()) {
+        Some(set_id) => set_id,
+        None => return,
+    };
+    let set_names: Vec<String> = data
+        .sets_to_merge
+        .split(',')
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
+    let prev_catalogue = data.catalogue.clone();
+    match split::merge_sets(
+        &mut data.catalogue,
+        set_id,
+        set_names,
+        data.skip_duplicates_on_merge,
+    ) {
+        Ok(num_added) => {
+            Storage::update_inventory(data.catalogue.clone());
+            data.sync_with_catalogue(&prev_catalogue);
+            if let Some(set_id) = data.catalogue.get_id_by_name(set_name.clone()) {
+                forget_card_positions(data, set_id, set_name.clone());
+            }
+            data.sets_to_merge.clear();
+            data.media_message = format!("Merged {} cards into [{}]", num_added, set_name);
+            data.nav.refresh();
+        }
+        Err(err) => data.media_message = err,
+    }
+}
+
+fn split_set(data: &mut AppState, set_name: String, by_tag: bool) {
+    let set_id = match data.catalogue.get_id_by_name(set_name.clone()) {
+        Some(set_id) => set_id,
+        None => return,
+    };
+    let study_set = match Storage::read_set_file(set_name.clone()) {
+        Ok(study_set) => study_set,
+        Err(err) => {
+            data.media_message = err;
+            return;
+        }
+    };
+    let parts = if by_tag {
+        split::parse_tag_parts(&data.split_input, &study_set)
+    } else {
+        split::parse_range_parts(&data.split_input, &study_set)
+    };
+    let prev_catalogue = data.catalogue.clone();
+    match parts.and_then(|parts| split::split_set(&mut data.catalogue, set_id, parts)) {
+        Ok(num_of_sets) => {
+            Storage::update_inventory(data.catalogue.clone());
+            data.sync_with_catalogue(&prev_catalogue);
+            data.split_input.clear();
+            data.media_message = format!("Split [{}] into {} sets", set_name, num_of_sets);
+            match data.catalogue.get_id_by_name(set_name.clone()) {
+                Some(set_id) => {
+                    forget_card_positions(data, set_id, set_name);
+                    data.nav.refresh();
+                }
+                // every card went into a new set
+                None => data.nav.home(),
+            }
+        }
+        Err(err) => data.media_message = err,
+    }
+}
+
 fn edit_set_page_builder(
     set_id: usize,
     curr_name: String,
     curr_tags: Vec<String>,
+    curr_kana_input: KanaInput,
+    curr_schema: FieldSchema,
+    curr_folder: String,
 ) -> impl Widget<AppState> {
     let return_to_main = Button::new("Return to Study Sets List").on_click(
-        move |ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
-            let new_win = WindowDesc::new(list_page_builder(
-                data.catalogue.get_inventory(),
-                data.catalogue.get_all_tags(),
-            ))
-            .title(MAIN_TITLE);
-            ctx.window().close();
-            ctx.new_window(new_win);
+        move |_ctx: &mut druid::EventCtx<'_, '_>, data: &mut AppState, _env| {
+            data.nav.home();
         },
     );
     let curr_set_name = curr_name.clone();
@@ -1233,7 +5373,7 @@ fn edit_set_page_builder(
         {
             return format!("Set [{}] already exists!", data.new_set_name.trim());
         }
-        return String::from("Please Enter New Set Name and Additional Tag(Optional)");
+        String::from("Please Enter New Set Name and Additional Tag(Optional)")
     })
     .with_text_size(32.0)
     .with_text_color(Color::YELLOW);
@@ -1243,7 +5383,7 @@ fn edit_set_page_builder(
         .fix_width(300.0)
         .lens(AppState::new_set_name);
     let set_tag_input = TextBox::new()
-        .with_placeholder("Add Tag")
+        .with_placeholder("Add Tags, e.g. LAJ/1/kanji, N5")
         .with_text_size(24.0)
         .fix_width(300.0)
         .lens(AppState::new_set_tag);
@@ -1251,59 +5391,219 @@ fn edit_set_page_builder(
     let curr_tag_label = Label::new("Tags:")
         .with_text_size(32.0)
         .with_text_color(Color::YELLOW);
+    let tag_colors = Storage::read_settings_file().get_tag_colors();
     let mut tag_row = Flex::row();
     for tag in curr_tags {
         let mut tag_box = Flex::column();
         let tag_label = Label::new(tag.clone())
             .with_text_size(20.0)
-            .with_text_color(Color::rgba(0.5, 0.3, 0.7, 1.0))
+            .with_text_color(get_color_code(&tag, &tag_colors))
             .border(Color::YELLOW, 1.0);
         let lesson_name = curr_name.clone();
         let delete_tag_button =
-            Button::new("Delete Tag").on_click(move |ctx, data: &mut AppState, _env| {
-                let mut target_set = Storage::read_set_file(lesson_name.clone());
+            Button::new("Delete Tag").on_click(move |_ctx, data: &mut AppState, _env| {
+                let mut target_set = match Storage::read_set_file(lesson_name.clone()) {
+                    Ok(target_set) => target_set,
+                    Err(_) => return,
+                };
                 target_set.delete_tag(tag.clone());
-                let cloned_set = target_set.clone();
                 data.catalogue.update_set(set_id, target_set);
                 Storage::update_inventory(data.catalogue.clone());
-                let new_win = WindowDesc::new(edit_set_page_builder(
-                    set_id,
-                    cloned_set.get_set_name(),
-                    cloned_set.get_all_tags(),
-                ))
-                .title("Edit Set Name & Tags");
-                ctx.new_window(new_win);
-                ctx.window().close();
+                data.nav.refresh();
             });
         tag_box = tag_box.with_child(tag_label).with_child(delete_tag_button);
         tag_row = tag_row.with_child(tag_box).with_spacer(5.0);
     }
     let tags_scroll = Scroll::new(tag_row);
 
+    let kana_set_name = curr_name.clone();
+    let kana_input_button = Button::new(format!("Romaji Input: {}", curr_kana_input.get_label()))
+        .on_click(move |_ctx, data: &mut AppState, _env| {
+            let mut target_set = match Storage::read_set_file(kana_set_name.clone()) {
+                Ok(target_set) => target_set,
+                Err(_) => return,
+            };
+            target_set.set_kana_input(curr_kana_input.next());
+            data.catalogue.update_set(set_id, target_set);
+            Storage::update_inventory(data.catalogue.clone());
+            data.nav.refresh();
+        });
+
+    let folder_label = Label::new(format!(
+        "Folder: {}",
+        folders::get_display_name(&curr_folder)
+    ))
+    .with_text_size(32.0)
+    .with_text_color(Color::YELLOW);
+    let folder_input = TextBox::new()
+        .with_placeholder("Folder to move to, empty for Top Level")
+        .with_text_size(24.0)
+        .fix_width(400.0)
+        .lens(AppState::new_set_folder);
+    let move_button =
+        Button::new("Move to Folder").on_click(move |_ctx, data: &mut AppState, _env| {
+            let result = folders::normalize_folder(&data.new_set_folder)
+                .and_then(|folder| data.catalogue.move_set(set_id, folder));
+            match result {
+                Ok(()) => {
+                    Storage::update_inventory(data.catalogue.clone());
+                    data.new_set_folder.clear();
+                    data.media_message.clear();
+                    data.nav.refresh();
+                }
+                Err(err) => data.media_message = err,
+            }
+        });
+
+    let merge_split_label = Label::new("Merge and Split:")
+        .with_text_size(32.0)
+        .with_text_color(Color::YELLOW);
+    let merge_input = TextBox::new()
+        .with_placeholder("Sets to merge into this one, e.g. Unit 17B, Unit 18")
+        .with_text_size(24.0)
+        .fix_width(600.0)
+        .lens(AppState::sets_to_merge);
+    let skip_duplicates_checkbox =
+        Checkbox::new("Skip Duplicate Words").lens(AppState::skip_duplicates_on_merge);
+    let name_for_merge = curr_name.clone();
+    let merge_button =
+        Button::new("Merge Into This Set").on_click(move |_ctx, data: &mut AppState, _env| {
+            merge_sets(data, name_for_merge.clone());
+        });
+    let split_input = TextBox::new()
+        .with_placeholder("e.g. 1-20 = Unit 17A, 21-40 or kanji, vocab")
+        .with_text_size(24.0)
+        .fix_width(600.0)
+        .lens(AppState::split_input);
+    let name_for_range_split = curr_name.clone();
+    let split_range_button =
+        Button::new("Split by Card Range").on_click(move |_ctx, data: &mut AppState, _env| {
+            split_set(data, name_for_range_split.clone(), false);
+        });
+    let name_for_tag_split = curr_name.clone();
+    let split_tag_button =
+        Button::new("Split by Card Tag").on_click(move |_ctx, data: &mut AppState, _env| {
+            split_set(data, name_for_tag_split.clone(), true);
+        });
+
+    let fields_label = Label::new("Fields:")
+        .with_text_size(32.0)
+        .with_text_color(Color::YELLOW);
+    let fields_input = TextBox::new()
+        .with_placeholder(curr_schema.get_fields().join(", "))
+        .with_text_size(24.0)
+        .fix_width(600.0)
+        .lens(AppState::new_set_fields);
+    let name_for_fields = curr_name.clone();
+    let schema_for_fields = curr_schema.clone();
+    let save_fields_button =
+        Button::new("Save Fields").on_click(move |_ctx, data: &mut AppState, _env| {
+            let fields: Vec<String> = data
+                .new_set_fields
+                .split(',')
+                .map(|field| field.trim().to_string())
+                .filter(|field| !field.is_empty())
+                .collect();
+            if fields.len() < 2 {
+                data.media_message = String::from("A set needs at least 2 fields");
+                return;
+            }
+            let mut schema = schema_for_fields.clone();
+            schema.set_fields(fields);
+            save_set_schema(data, set_id, name_for_fields.clone(), schema);
+            data.new_set_fields.clear();
+            data.media_message.clear();
+        });
+    let mut roles_rows = Flex::column();
+    for mode in StudyMode::get_all() {
+        let roles = curr_schema.get_roles(mode);
+        let num_of_fields = curr_schema.get_num_of_fields();
+        // each button moves its role on to the next field
+        let next_roles = [
+            FieldRoles::new(
+                (roles.get_prompt() + 1) % num_of_fields,
+                roles.get_answer(),
+                roles.get_hint(),
+            ),
+            FieldRoles::new(
+                roles.get_prompt(),
+                (roles.get_answer() + 1) % num_of_fields,
+                roles.get_hint(),
+            ),
+            FieldRoles::new(
+                roles.get_prompt(),
+                roles.get_answer(),
+                match roles.get_hint() {
+                    None => Some(0),
+                    Some(hint) if hint + 1 < num_of_fields => Some(hint + 1),
+                    Some(_) => None,
+                },
+            ),
+        ];
+        let hint_name = roles.get_hint().map_or(String::from("None"), |hint| {
+            curr_schema.get_field_name(hint)
+        });
+        let button_labels = [
+            format!("Prompt: {}", curr_schema.get_field_name(roles.get_prompt())),
+            format!("Answer: {}", curr_schema.get_field_name(roles.get_answer())),
+            format!("Hint: {}", hint_name),
+        ];
+        let mut roles_row = Flex::row().with_child(
+            Label::new(mode.get_label())
+                .with_text_size(24.0)
+                .fix_width(200.0),
+        );
+        for (button_label, new_roles) in button_labels.into_iter().zip(next_roles) {
+            let name_for_roles = curr_name.clone();
+            let schema_for_roles = curr_schema.clone();
+            let roles_button =
+                Button::new(button_label).on_click(move |_ctx, data: &mut AppState, _env| {
+                    let mut schema = schema_for_roles.clone();
+                    schema.set_roles(mode, new_roles);
+                    save_set_schema(data, set_id, name_for_roles.clone(), schema);
+                });
+            roles_row = roles_row.with_spacer(10.0).with_child(roles_button);
+        }
+        roles_rows = roles_rows.with_child(roles_row).with_spacer(5.0);
+    }
+
+    let name_for_export = curr_name.clone();
+    let export_button =
+        Button::new("Export Set").on_click(move |_ctx, data: &mut AppState, _env| {
+            data.media_message = match Storage::export_set(name_for_export.clone()) {
+                Ok(export_dir) => format!("Exported to [{}]", export_dir.display()),
+                Err(err) => err,
+            };
+        });
+    let export_message_label = Label::dynamic(|data: &AppState, _env| data.media_message.clone())
+        .with_text_size(24.0)
+        .with_text_color(Color::YELLOW);
+
     let save_button =
-        Button::new("Save Changes").on_click(move |ctx, data: &mut AppState, _env| {
+        Button::new("Save Changes").on_click(move |_ctx, data: &mut AppState, _env| {
             let new_set_name = place_holder_helper(curr_name.clone(), data.new_set_name.clone());
             if new_set_name == curr_name
                 || !has_duplicate(new_set_name.clone(), data.catalogue.get_all_names())
             {
                 let set_tag = &data.new_set_tag;
                 let item = data.catalogue.get_item_by_id(set_id);
-                let mut target_set = Storage::read_set_file(item[0].get_name());
-                target_set.rename_set(new_set_name);
-                if is_valid(set_tag.clone()) {
-                    target_set.add_tag(set_tag.trim().to_string());
+                let mut target_set = match Storage::read_set_file(item[0].get_name()) {
+                    Ok(target_set) => target_set,
+                    Err(_) => return,
+                };
+                target_set.rename_set(new_set_name.clone());
+                for tag in tags::parse_tags(set_tag) {
+                    target_set.add_tag(tag);
                 }
                 data.catalogue.update_set(set_id, target_set);
                 Storage::update_inventory(data.catalogue.clone());
-                let new_win = WindowDesc::new(list_page_builder(
-                    data.catalogue.get_inventory(),
-                    data.catalogue.get_all_tags(),
-                ))
-                .title(MAIN_TITLE);
+                // the history file was renamed with the set
+                if let Some(stats) = data.set_stats.remove(&curr_name) {
+                    data.set_stats.insert(new_set_name, stats);
+                }
                 data.new_set_name.clear();
                 data.new_set_tag.clear();
-                ctx.window().close();
-                ctx.new_window(new_win);
+                data.nav.home();
             }
         });
     Flex::column()
@@ -1319,25 +5619,73 @@ fn edit_set_page_builder(
         .with_spacer(10.0)
         .with_child(tags_scroll)
         .with_spacer(50.0)
+        .with_child(kana_input_button)
+        .with_spacer(50.0)
+        .with_child(folder_label)
+        .with_spacer(10.0)
+        .with_child(
+            Flex::row()
+                .with_child(folder_input)
+                .with_spacer(10.0)
+                .with_child(move_button),
+        )
+        .with_spacer(50.0)
+        .with_child(merge_split_label)
+        .with_spacer(10.0)
+        .with_child(
+            Flex::row()
+                .with_child(merge_input)
+                .with_spacer(10.0)
+                .with_child(skip_duplicates_checkbox)
+                .with_spacer(10.0)
+                .with_child(merge_button),
+        )
+        .with_spacer(10.0)
+        .with_child(
+            Flex::row()
+                .with_child(split_input)
+                .with_spacer(10.0)
+                .with_child(split_range_button)
+                .with_spacer(10.0)
+                .with_child(split_tag_button),
+        )
+        .with_spacer(50.0)
+        .with_child(fields_label)
+        .with_spacer(10.0)
+        .with_child(
+            Flex::row()
+                .with_child(fields_input)
+                .with_spacer(10.0)
+                .with_child(save_fields_button),
+        )
+        .with_spacer(10.0)
+        .with_child(roles_rows)
+        .with_spacer(50.0)
+        .with_child(export_button)
+        .with_spacer(10.0)
+        .with_child(export_message_label)
+        .with_spacer(50.0)
         .with_child(save_button)
         .center()
 }
 
 pub fn main() {
     Storage::set_up();
-    Storage::inventory_check();
-    let catalouge = Storage::read_inventory_file();
-    let main_window = WindowDesc::new(list_page_builder(
-        catalouge.get_inventory(),
-        catalouge.get_all_tags(),
-    ))
-    .title(MAIN_TITLE);
-    AppLauncher::with_window(main_window)
+    let unreadable_sets = Storage::inventory_check();
+    audio::set_engine(audio::get_default_engine());
+    let main_window = WindowDesc::new(app_root()).title(MAIN_TITLE);
+    let launcher = AppLauncher::with_window(main_window)
         // .log_to_console()
+        .delegate(Delegate)
         .configure_env(|env, _state| {
             env.set(theme::BUTTON_DARK, Color::rgba8(100, 100, 120, 0));
             env.set(theme::BUTTON_LIGHT, Color::rgba8(100, 100, 100, 100));
+        });
+    watcher::watch_data_dir(launcher.get_external_handle());
+    launcher
+        .launch(AppState {
+            unreadable_sets,
+            ..AppState::default()
         })
-        .launch(AppState::default())
         .unwrap();
 }
@@ -0,0 +1,253 @@
+use std::collections::HashSet;
+
+use druid::Data;
+
+use crate::storage::StudyMode;
+use crate::timer::TimeLimits;
+use crate::MAIN_TITLE;
+
+// Which study sets the list page shows
+#[derive(Clone, Debug, PartialEq)]
+pub enum ListFilter {
+    All,
+    MatchAll(HashSet<String>),
+    MatchAny(HashSet<String>),
+    Untagged,
+    // see query::parse_query
+    Query(String),
+    // sets directly in the folder, with its subfolders, see folders.rs
+    Folder(String),
+}
+
+// Every page of the app, with what it needs to be rebuilt
+#[derive(Clone, Debug, PartialEq)]
+pub enum Route {
+    List(ListFilter),
+    View(String),
+    Learn(String),
+    Test(String),
+    TestNoRemarks(String),
+    Listen(String),
+    Flashcards(String),
+    TimedTestSetup(String),
+    TimedTest(String, TimeLimits),
+    Results {
+        set_name: String,
+        // answers for every card in the set by card id, or grades for flashcards
+        user_answers: Vec<String>,
+        // ids of the cards that were asked, in order
+        card_ids: Vec<usize>,
+        study_mode: StudyMode,
+        // seconds spent on each card, if timed
+        card_times: Vec<f64>,
+        // the limits of a timed test, so it can be retried with them
+        time_limits: Option<TimeLimits>,
+    },
+    AddWord(String),
+    EditWord(String, usize),
+    // every card of the set in one grid, see grid.rs
+    EditAllWords(String),
+    AddSet,
+    EditSet(String),
+    Search,
+    Replace,
+    Duplicates,
+    Tags,
+}
+
+impl Route {
+    pub fn for_study(mode: StudyMode, set_name: String) -> Route {
+        match mode {
+            StudyMode::Learn => Route::Learn(set_name),
+            StudyMode::Test => Route::Test(set_name),
+            StudyMode::TestNoRemarks => Route::TestNoRemarks(set_name),
+            StudyMode::Listen => Route::Listen(set_name),
+            StudyMode::Flashcards => Route::Flashcards(set_name),
+        }
+    }
+
+    // The set whose cards are being answered on this page, if any
+    pub fn get_studied_set(&self) -> Option<String> {
+        match self {
+            Route::Learn(set_name)
+            | Route::Test(set_name)
+            | Route::TestNoRemarks(set_name)
+            | Route::Listen(set_name)
+            | Route::Flashcards(set_name)
+            | Route::TimedTest(set_name, _) => Some(set_name.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn get_title(&self) -> String {
+        match self {
+            Route::List(_) => String::from(MAIN_TITLE),
+            Route::View(set_name)
+            | Route::Learn(set_name)
+            | Route::Test(set_name)
+            | Route::TestNoRemarks(set_name)
+            | Route::Listen(set_name)
+            | Route::Flashcards(set_name)
+            | Route::TimedTestSetup(set_name)
+            | Route::TimedTest(set_name, _)
+            | Route::AddWord(set_name) => set_name.clone(),
+            Route::Results { .. } => String::from("Results"),
+            Route::EditWord(_, _) => String::from("Edit Word"),
+            Route::EditAllWords(set_name) => format!("Edit All Words: {}", set_name),
+            Route::AddSet => String::from("Add New Set"),
+            Route::EditSet(_) => String::from("Edit Set Name & Tags"),
+            Route::Search => String::from("Search Cards"),
+            Route::Replace => String::from("Find and Replace"),
+            Route::Duplicates => String::from("Duplicate Words"),
+            Route::Tags => String::from("Manage Tags"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct NavEntry {
+    route: Route,
+    // restored when coming back to the page
+    scroll_offset: f64,
+}
+
+impl NavEntry {
+    fn new(route: Route) -> NavEntry {
+        NavEntry {
+            route,
+            scroll_offset: 0.0,
+        }
+    }
+}
+
+// Pages visited since the study sets list, the last one is shown
+#[derive(Clone, Debug, PartialEq)]
+pub struct NavStack {
+    entries: Vec<NavEntry>,
+    // bumped on every navigation, so the page is rebuilt even for the same route
+    version: usize,
+}
+
+impl Data for NavStack {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl NavStack {
+    pub fn new() -> NavStack {
+        NavStack {
+            entries: vec![NavEntry::new(Route::List(ListFilter::All))],
+            version: 0,
+        }
+    }
+
+    pub fn get_route(&self) -> Route {
+        self.entries[self.entries.len() - 1].route.clone()
+    }
+
+    pub fn get_version(&self) -> usize {
+        self.version
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.entries.len() > 1
+    }
+
+    pub fn get_scroll_offset(&self) -> f64 {
+        self.entries[self.entries.len() - 1].scroll_offset
+    }
+
+    pub fn set_scroll_offset(&mut self, offset: f64) {
+        let last = self.entries.len() - 1;
+        self.entries[last].scroll_offset = offset;
+    }
+
+    pub fn push(&mut self, route: Route) {
+        self.entries.push(NavEntry::new(route));
+        self.version += 1;
+    }
+
+    // Shows another page in place of the current one, keeping the scroll position if it is a refresh
+    pub fn replace(&mut self, route: Route) {
+        let last = self.entries.len() - 1;
+        if self.entries[last].route != route {
+            self.entries[last] = NavEntry::new(route);
+        }
+        self.version += 1;
+    }
+
+    // Rebuilds the current page, e.g. after its set has been changed
+    pub fn refresh(&mut self) {
+        self.version += 1;
+    }
+
+    pub fn back(&mut self) {
+        if self.can_go_back() {
+            self.entries.pop();
+        }
+        self.version += 1;
+    }
+
+    // Back to the unfiltered study sets list
+    pub fn home(&mut self) {
+        self.entries.truncate(1);
+        self.replace(Route::List(ListFilter::All));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(set_name: &str) -> Route {
+        Route::View(String::from(set_name))
+    }
+
+    #[test]
+    fn back_returns_to_previous_page_with_its_scroll() {
+        let mut nav = NavStack::new();
+        nav.set_scroll_offset(120.0);
+        nav.push(view("Kanji Unit 17B"));
+        nav.push(Route::EditWord(String::from("Kanji Unit 17B"), 3));
+        nav.back();
+        assert_eq!(nav.get_route(), view("Kanji Unit 17B"));
+        nav.back();
+        assert_eq!(nav.get_route(), Route::List(ListFilter::All));
+        assert_eq!(nav.get_scroll_offset(), 120.0);
+        assert!(!nav.can_go_back());
+    }
+
+    #[test]
+    fn back_stays_on_list_page() {
+        let mut nav = NavStack::new();
+        let version = nav.get_version();
+        nav.back();
+        assert_eq!(nav.get_route(), Route::List(ListFilter::All));
+        // the list is still rebuilt
+        assert!(nav.get_version() > version);
+    }
+
+    #[test]
+    fn home_clears_pages_and_filter() {
+        let mut nav = NavStack::new();
+        nav.replace(Route::List(ListFilter::Untagged));
+        nav.push(view("Kanji Unit 18"));
+        nav.push(Route::Learn(String::from("Kanji Unit 18")));
+        nav.home();
+        assert_eq!(nav.get_route(), Route::List(ListFilter::All));
+        assert!(!nav.can_go_back());
+    }
+
+    #[test]
+    fn replace_keeps_scroll_of_same_route() {
+        let mut nav = NavStack::new();
+        nav.push(view("Kanji Unit 18"));
+        nav.set_scroll_offset(40.0);
+        nav.replace(view("Kanji Unit 18"));
+        assert_eq!(nav.get_scroll_offset(), 40.0);
+        nav.replace(view("Kanji Unit 17B"));
+        assert_eq!(nav.get_scroll_offset(), 0.0);
+        assert!(nav.can_go_back());
+    }
+}
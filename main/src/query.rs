@@ -0,0 +1,357 @@
+// Queries picking study sets from the list, e.g. "LAJ1 AND (kanji OR vocab) AND NOT reading".
+// Besides tags, "name:kanji" matches set names containing "kanji" and "cards:10..20" sets
+// with 10 to 20 cards (also "cards:>10", "cards:<=5", "cards:10.." or "cards:12").
+// Terms next to each other without AND or OR must all match. Text with spaces is quoted,
+// e.g. "JLPT N5" or name:"Lesson 1".
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::{Catalogue, ListItem};
+use crate::tags;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Query {
+    Tag(String),
+    // lower cased
+    Name(String),
+    Cards {
+        min: Option<usize>,
+        max: Option<usize>,
+    },
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+}
+
+impl Query {
+    pub fn matches(&self, item: &ListItem) -> bool {
+        match self {
+            // "LAJ" also matches sets tagged "LAJ/1/kanji"
+            Query::Tag(tag) => tags::has_tag(&item.get_all_tags(), tag),
+            Query::Name(name) => item.get_name().to_lowercase().contains(name),
+            Query::Cards { min, max } => {
+                let num_of_cards = item.get_num_of_cards();
+                min.is_none_or(|min| num_of_cards >= min)
+                    && max.is_none_or(|max| num_of_cards <= max)
+            }
+            Query::Not(query) => !query.matches(item),
+            Query::And(left, right) => left.matches(item) && right.matches(item),
+            Query::Or(left, right) => left.matches(item) || right.matches(item),
+        }
+    }
+}
+
+// A query kept in the settings file under a name
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SavedQuery {
+    name: String,
+    query: String,
+}
+
+impl SavedQuery {
+    pub fn new(name: String, query: String) -> SavedQuery {
+        SavedQuery { name, query }
+    }
+
+    pub fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn get_query(&self) -> String {
+        self.query.clone()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    // a tag or term, with any quotes taken off, quoted ones are never keywords
+    Text(String),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' {
+            chars.next();
+            tokens.push(Token::Open);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(Token::Close);
+        } else {
+            let mut word = String::new();
+            let mut is_quoted = false;
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '(' || c == ')' {
+                    break;
+                }
+                chars.next();
+                if c != '"' {
+                    word.push(c);
+                    continue;
+                }
+                is_quoted = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(String::from("Missing closing quote")),
+                    }
+                }
+            }
+            if is_quoted {
+                tokens.push(Token::Text(word));
+                continue;
+            }
+            tokens.push(match word.to_uppercase().as_str() {
+                "AND" => Token::And,
+                "OR" => Token::Or,
+                "NOT" => Token::Not,
+                _ => Token::Text(word),
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_count(text: &str) -> Result<usize, String> {
+    text.trim()
+        .parse()
+        .map_err(|_| format!("Invalid card count [{}]", text))
+}
+
+// "10..20", "10..", "..20", ">10", ">=10", "<10", "<=10" or "10"
+fn parse_card_range(range: &str) -> Result<Query, String> {
+    let (min, max) = if let Some((min, max)) = range.split_once("..") {
+        let min = if min.is_empty() {
+            None
+        } else {
+            Some(parse_count(min)?)
+        };
+        let max = if max.is_empty() {
+            None
+        } else {
+            Some(parse_count(max)?)
+        };
+        (min, max)
+    } else if let Some(min) = range.strip_prefix(">=") {
+        (Some(parse_count(min)?), None)
+    } else if let Some(min) = range.strip_prefix('>') {
+        (Some(parse_count(min)? + 1), None)
+    } else if let Some(max) = range.strip_prefix("<=") {
+        (None, Some(parse_count(max)?))
+    } else if let Some(max) = range.strip_prefix('<') {
+        match parse_count(max)? {
+            0 => return Err(String::from("No set has fewer than 0 cards")),
+            max => (None, Some(max - 1)),
+        }
+    } else {
+        let count = parse_count(range)?;
+        (Some(count), Some(count))
+    };
+    Ok(Query::Cards { min, max })
+}
+
+fn parse_term(text: &str) -> Result<Query, String> {
+    let lower = text.to_lowercase();
+    if let Some(name) = lower.strip_prefix("name:") {
+        Ok(Query::Name(name.to_string()))
+    } else if let Some(range) = lower.strip_prefix("cards:") {
+        parse_card_range(range)
+    } else {
+        Ok(Query::Tag(text.to_string()))
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn parse_or(&mut self) -> Result<Query, String> {
+        let mut query = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.position += 1;
+            query = Query::Or(Box::new(query), Box::new(self.parse_and()?));
+        }
+        Ok(query)
+    }
+
+    fn parse_and(&mut self) -> Result<Query, String> {
+        let mut query = self.parse_not()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => self.position += 1,
+                // terms side by side
+                Some(Token::Open) | Some(Token::Not) | Some(Token::Text(_)) => {}
+                _ => return Ok(query),
+            }
+            query = Query::And(Box::new(query), Box::new(self.parse_not()?));
+        }
+    }
+
+    fn parse_not(&mut self) -> Result<Query, String> {
+        if self.peek() == Some(&Token::Not) {
+            self.position += 1;
+            return Ok(Query::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_term()
+    }
+
+    fn parse_term(&mut self) -> Result<Query, String> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        match token {
+            Some(Token::Open) => {
+                let query = self.parse_or()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(String::from("Missing closing bracket"));
+                }
+                self.position += 1;
+                Ok(query)
+            }
+            Some(Token::Text(text)) => parse_term(&text),
+            Some(Token::Close) => Err(String::from("Unexpected closing bracket")),
+            Some(_) => Err(String::from("AND, OR and NOT need a tag on each side")),
+            None => Err(String::from("Query ends too early")),
+        }
+    }
+}
+
+pub fn parse_query(text: &str) -> Result<Query, String> {
+    let tokens = tokenize(text)?;
+    if tokens.is_empty() {
+        return Err(String::from("Enter a query"));
+    }
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+    };
+    let query = parser.parse_or()?;
+    if parser.position < parser.tokens.len() {
+        return Err(String::from("Unexpected closing bracket"));
+    }
+    Ok(query)
+}
+
+pub fn get_items_by_query(catalogue: &Catalogue, query: &Query) -> Vec<ListItem> {
+    catalogue
+        .get_inventory()
+        .into_iter()
+        .filter(|item| query.matches(item))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(tag: &str) -> Box<Query> {
+        Box::new(Query::Tag(tag.to_string()))
+    }
+
+    fn make_item(name: &str, tags: &[&str], num_of_cards: usize) -> ListItem {
+        serde_json::from_value(serde_json::json!({
+            "item_id": 0,
+            "item_name": name,
+            "item_tags": tags,
+            "num_of_cards": num_of_cards,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            parse_query("a OR b AND NOT c"),
+            Ok(Query::Or(
+                tag("a"),
+                Box::new(Query::And(tag("b"), Box::new(Query::Not(tag("c")))))
+            ))
+        );
+        assert_eq!(
+            parse_query("(a or b) c"),
+            Ok(Query::And(
+                Box::new(Query::Or(tag("a"), tag("b"))),
+                tag("c")
+            ))
+        );
+        assert_eq!(
+            parse_query("not not a"),
+            Ok(Query::Not(Box::new(Query::Not(tag("a")))))
+        );
+    }
+
+    #[test]
+    fn parses_names_card_counts_and_quotes() {
+        assert_eq!(
+            parse_query("name:\"Lesson 1\""),
+            Ok(Query::Name(String::from("lesson 1")))
+        );
+        assert_eq!(
+            parse_query("\"JLPT N5\" \"OR\""),
+            Ok(Query::And(tag("JLPT N5"), tag("OR")))
+        );
+        for (text, min, max) in [
+            ("cards:10..20", Some(10), Some(20)),
+            ("cards:10..", Some(10), None),
+            ("cards:..20", None, Some(20)),
+            ("cards:>10", Some(11), None),
+            ("cards:>=10", Some(10), None),
+            ("cards:<10", None, Some(9)),
+            ("cards:<=10", None, Some(10)),
+            ("CARDS:12", Some(12), Some(12)),
+        ] {
+            assert_eq!(parse_query(text), Ok(Query::Cards { min, max }), "{}", text);
+        }
+    }
+
+    #[test]
+    fn reports_invalid_queries() {
+        for (text, err) in [
+            ("  ", "Enter a query"),
+            ("(a OR b", "Missing closing bracket"),
+            ("a)", "Unexpected closing bracket"),
+            ("()", "Unexpected closing bracket"),
+            ("a AND", "Query ends too early"),
+            ("a AND OR b", "AND, OR and NOT need a tag on each side"),
+            ("name:\"Lesson 1", "Missing closing quote"),
+            ("cards:ten", "Invalid card count [ten]"),
+            ("cards:<0", "No set has fewer than 0 cards"),
+        ] {
+            assert_eq!(parse_query(text), Err(err.to_string()), "{}", text);
+        }
+    }
+
+    #[test]
+    fn picks_matching_sets() {
+        let catalogue = Catalogue::new(vec![
+            make_item("Lesson 1", &["LAJ/1/kanji"], 12),
+            make_item("Lesson 2", &["LAJ/2/vocab"], 30),
+            make_item("Verbs", &["N5"], 5),
+        ]);
+        let names = |text: &str| -> Vec<String> {
+            get_items_by_query(&catalogue, &parse_query(text).unwrap())
+                .iter()
+                .map(|item| item.get_name())
+                .collect()
+        };
+        assert_eq!(names("laj"), vec!["Lesson 1", "Lesson 2"]);
+        assert_eq!(names("LAJ AND NOT laj/2"), vec!["Lesson 1"]);
+        assert_eq!(names("n5 OR cards:>20"), vec!["Lesson 2", "Verbs"]);
+        assert_eq!(names("name:lesson cards:..12"), vec!["Lesson 1"]);
+        assert!(names("LAJ/1/kanji/extra").is_empty());
+    }
+}
@@ -0,0 +1,450 @@
+// Find and replace over the word, answer and remarks of cards, in one set, the sets matching
+// a query (see query.rs) or every set. Changes are previewed first, then written together,
+// and the last replace can be undone.
+
+use std::collections::HashMap;
+
+use druid::Data;
+use regex::{NoExpand, Regex, RegexBuilder};
+
+use crate::cloze;
+use crate::query;
+use crate::search::CardField;
+use crate::storage::{Catalogue, Storage, StudySet};
+
+// The fields that can be replaced in, in the order of the card's fields
+pub const REPLACE_FIELDS: [CardField; 3] = [CardField::Word, CardField::Answer, CardField::Remarks];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplaceScope {
+    AllSets,
+    // the set named in the scope box
+    OneSet,
+    // the sets matching the query in the scope box, e.g. a tag
+    Query,
+}
+
+impl Data for ReplaceScope {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl ReplaceScope {
+    pub fn get_all() -> Vec<ReplaceScope> {
+        vec![
+            ReplaceScope::AllSets,
+            ReplaceScope::OneSet,
+            ReplaceScope::Query,
+        ]
+    }
+
+    pub fn get_label(&self) -> String {
+        match self {
+            ReplaceScope::AllSets => String::from("All Sets"),
+            ReplaceScope::OneSet => String::from("One Set"),
+            ReplaceScope::Query => String::from("Sets Matching Query"),
+        }
+    }
+}
+
+// What to find and what to put in its place. Without `is_regex` the text is matched as is,
+// and "$" in the replacement is kept; with it, "$1" puts in the first group matched
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replacement {
+    find: String,
+    replace_with: String,
+    is_regex: bool,
+    match_case: bool,
+    fields: Vec<CardField>,
+}
+
+impl Replacement {
+    pub fn new(
+        find: String,
+        replace_with: String,
+        is_regex: bool,
+        match_case: bool,
+        fields: Vec<CardField>,
+    ) -> Replacement {
+        Replacement {
+            find,
+            replace_with,
+            is_regex,
+            match_case,
+            fields,
+        }
+    }
+
+    fn get_regex(&self) -> Result<Regex, String> {
+        if self.find.is_empty() {
+            return Err(String::from("Enter text to find"));
+        }
+        if self.fields.is_empty() {
+            return Err(String::from("Pick at least one field to replace in"));
+        }
+        let pattern = if self.is_regex {
+            self.find.clone()
+        } else {
+            regex::escape(&self.find)
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.match_case)
+            .build()
+            .map_err(|err| format!("Invalid pattern: {}", err))
+    }
+}
+
+// One field of one card, as it is and as it will be
+#[derive(Clone, Debug, PartialEq)]
+pub struct CardChange {
+    set_name: String,
+    card_id: usize,
+    field: CardField,
+    before: String,
+    after: String,
+}
+
+impl CardChange {
+    pub fn get_set_name(&self) -> String {
+        self.set_name.clone()
+    }
+
+    pub fn get_card_id(&self) -> usize {
+        self.card_id
+    }
+
+    pub fn get_field(&self) -> CardField {
+        self.field
+    }
+
+    pub fn get_before(&self) -> String {
+        self.before.clone()
+    }
+
+    pub fn get_after(&self) -> String {
+        self.after.clone()
+    }
+
+    // The change that puts the field back
+    pub fn reversed(&self) -> CardChange {
+        CardChange {
+            before: self.after.clone(),
+            after: self.before.clone(),
+            ..self.clone()
+        }
+    }
+}
+
+fn get_field_index(field: CardField) -> usize {
+    REPLACE_FIELDS
+        .iter()
+        .position(|replace_field| *replace_field == field)
+        .unwrap_or(0)
+}
+
+// The sets to replace in, by name
+pub fn get_scope_sets(
+    scope: ReplaceScope,
+    scope_text: &str,
+    catalogue: &Catalogue,
+) -> Result<Vec<String>, String> {
+    let items = match scope {
+        ReplaceScope::AllSets => catalogue.get_inventory(),
+        ReplaceScope::OneSet => match catalogue.get_id_by_name(scope_text.trim().to_string()) {
+            Some(set_id) => catalogue.get_item_by_id(set_id),
+            None => return Err(format!("Set [{}] not found", scope_text.trim())),
+        },
+        ReplaceScope::Query => {
+            query::get_items_by_query(catalogue, &query::parse_query(scope_text)?)
+        }
+    };
+    if items.is_empty() {
+        return Err(String::from("No set matches the query"));
+    }
+    Ok(items.iter().map(|item| item.get_name()).collect())
+}
+
+pub fn find_changes(
+    replacement: &Replacement,
+    study_sets: &[StudySet],
+) -> Result<Vec<CardChange>, String> {
+    let regex = replacement.get_regex()?;
+    let mut changes = Vec::new();
+    for study_set in study_sets {
+        for card in study_set.get_all_cards() {
+            for field in &replacement.fields {
+                let before = card.get_field(get_field_index(*field));
+                let after = if replacement.is_regex {
+                    regex.replace_all(&before, replacement.replace_with.as_str())
+                } else {
+                    regex.replace_all(&before, NoExpand(&replacement.replace_with))
+                };
+                if after != before {
+                    changes.push(CardChange {
+                        set_name: study_set.get_set_name(),
+                        card_id: card.get_id(),
+                        field: *field,
+                        before: before.clone(),
+                        after: after.to_string(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(changes)
+}
+
+// Writes the changes, all set files together or none of them, see change_cards.
+// Returns the changes made
+pub fn apply_changes(
+    changes: &[CardChange],
+    catalogue: &Catalogue,
+) -> Result<Vec<CardChange>, String> {
+    let mut study_sets: HashMap<String, StudySet> = HashMap::new();
+    for change in changes {
+        // the set may have been renamed or deleted since
+        if catalogue.get_id_by_name(change.set_name.clone()).is_some()
+            && !study_sets.contains_key(&change.set_name)
+        {
+            study_sets.insert(
+                change.set_name.clone(),
+                Storage::read_set_file(change.set_name.clone())?,
+            );
+        }
+    }
+    let applied = change_cards(changes, &mut study_sets);
+    let changed_sets: Vec<StudySet> = study_sets.into_values().collect();
+    Storage::update_set_files(&changed_sets)?;
+    Ok(applied)
+}
+
+// Makes the changes to the sets, by name. A change is skipped if its set is not given or
+// the field no longer holds the text it was found with, e.g. the card was edited since.
+// Returns the changes made
+fn change_cards(
+    changes: &[CardChange],
+    study_sets: &mut HashMap<String, StudySet>,
+) -> Vec<CardChange> {
+    let mut applied = Vec::new();
+    for change in changes {
+        let study_set = match study_sets.get_mut(&change.set_name) {
+            Some(study_set) => study_set,
+            None => continue,
+        };
+        if change.card_id >= study_set.get_num_of_cards() {
+            continue;
+        }
+        let mut card = study_set.get_card(change.card_id);
+        let field = get_field_index(change.field);
+        if card.get_field(field) != change.before {
+            continue;
+        }
+        card.set_field(field, change.after.clone());
+        if change.field == CardField::Word {
+            card.set_card_type(cloze::get_card_type(&change.after));
+        }
+        study_set.replace_card(change.card_id, card);
+        applied.push(change.clone());
+    }
+    applied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Card, CardType};
+
+    fn make_set(name: &str, cards: &[(&str, &str, &str)]) -> StudySet {
+        let mut study_set = StudySet::new(0, name.to_string());
+        for (word, ans, remarks) in cards {
+            study_set.add_card(Card::new(
+                study_set.get_num_of_cards(),
+                word.to_string(),
+                ans.to_string(),
+                remarks.to_string(),
+            ));
+        }
+        study_set
+    }
+
+    fn get_fields(study_set: &StudySet) -> Vec<(String, String, String)> {
+        study_set
+            .get_all_cards()
+            .iter()
+            .map(|card| (card.get_word(), card.get_ans(), card.get_remarks()))
+            .collect()
+    }
+
+    fn by_name(study_sets: Vec<StudySet>) -> HashMap<String, StudySet> {
+        study_sets
+            .into_iter()
+            .map(|study_set| (study_set.get_set_name(), study_set))
+            .collect()
+    }
+
+    #[test]
+    fn previews_applies_and_undoes() {
+        let study_sets = vec![
+            make_set("Animals", &[("ねこ", "Cat", "a cat"), ("いぬ", "dog", "")]),
+            make_set("Pets", &[("こねこ", "kitten", "young cat")]),
+        ];
+        let replacement = Replacement::new(
+            String::from("cat"),
+            String::from("neko"),
+            false,
+            false,
+            vec![CardField::Answer, CardField::Remarks],
+        );
+        let changes = find_changes(&replacement, &study_sets).unwrap();
+        let found: Vec<(String, usize, CardField, String)> = changes
+            .iter()
+            .map(|change| {
+                (
+                    change.get_set_name(),
+                    change.get_card_id(),
+                    change.get_field(),
+                    change.get_after(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    String::from("Animals"),
+                    0,
+                    CardField::Answer,
+                    String::from("neko")
+                ),
+                (
+                    String::from("Animals"),
+                    0,
+                    CardField::Remarks,
+                    String::from("a neko")
+                ),
+                (
+                    String::from("Pets"),
+                    0,
+                    CardField::Remarks,
+                    String::from("young neko")
+                ),
+            ]
+        );
+        let mut changed = by_name(study_sets.clone());
+        assert_eq!(change_cards(&changes, &mut changed), changes);
+        assert_eq!(
+            get_fields(&changed["Animals"]),
+            vec![
+                (
+                    String::from("ねこ"),
+                    String::from("neko"),
+                    String::from("a neko")
+                ),
+                (String::from("いぬ"), String::from("dog"), String::new()),
+            ]
+        );
+        let undo: Vec<CardChange> = changes.iter().map(|change| change.reversed()).collect();
+        assert_eq!(change_cards(&undo, &mut changed), undo);
+        for study_set in &study_sets {
+            assert_eq!(
+                get_fields(&changed[&study_set.get_set_name()]),
+                get_fields(study_set)
+            );
+        }
+    }
+
+    #[test]
+    fn skips_changes_to_fields_edited_since() {
+        let study_sets = vec![make_set(
+            "Animals",
+            &[("ねこ", "cat", ""), ("とり", "cat bird", "")],
+        )];
+        let replacement = Replacement::new(
+            String::from("cat"),
+            String::from("neko"),
+            false,
+            true,
+            vec![CardField::Answer],
+        );
+        let changes = find_changes(&replacement, &study_sets).unwrap();
+        assert_eq!(changes.len(), 2);
+        let mut edited = make_set("Animals", &[("ねこ", "kitty", "")]);
+        edited.add_card(Card::new(
+            1,
+            String::from("とり"),
+            String::from("cat bird"),
+            String::new(),
+        ));
+        let mut changed = by_name(vec![edited]);
+        assert_eq!(change_cards(&changes, &mut changed), changes[1..].to_vec());
+        assert_eq!(changed["Animals"].get_card(0).get_ans(), "kitty");
+        assert_eq!(changed["Animals"].get_card(1).get_ans(), "neko bird");
+        // the card or its set may be gone too
+        let mut changed = by_name(vec![make_set("Animals", &[("ねこ", "cat", "")])]);
+        assert_eq!(change_cards(&changes, &mut changed), changes[..1].to_vec());
+        assert!(change_cards(&changes, &mut HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn expands_groups_only_for_regex() {
+        let study_sets = vec![make_set("Names", &[("Tanaka-san", "", "")])];
+        let get_after = |find: &str, replace_with: &str, is_regex: bool| {
+            let replacement = Replacement::new(
+                find.to_string(),
+                replace_with.to_string(),
+                is_regex,
+                true,
+                vec![CardField::Word],
+            );
+            find_changes(&replacement, &study_sets).unwrap()[0].get_after()
+        };
+        assert_eq!(get_after(r"(\w+)-san", "$1-sama", true), "Tanaka-sama");
+        assert_eq!(get_after("-san", "$1", false), "Tanaka$1");
+    }
+
+    #[test]
+    fn word_changes_update_card_type() {
+        let study_sets = vec![make_set("Sentences", &[("わたしは学生です", "", "")])];
+        let replacement = Replacement::new(
+            String::from("学生"),
+            String::from("{{c1::学生}}"),
+            false,
+            true,
+            vec![CardField::Word],
+        );
+        let changes = find_changes(&replacement, &study_sets).unwrap();
+        let mut changed = by_name(study_sets);
+        change_cards(&changes, &mut changed);
+        assert_eq!(
+            changed["Sentences"].get_card(0).get_card_type(),
+            CardType::Cloze
+        );
+    }
+
+    #[test]
+    fn reports_invalid_replacements() {
+        let study_sets = vec![make_set("Animals", &[("ねこ", "cat", "")])];
+        for (find, fields, err) in [
+            ("", vec![CardField::Word], "Enter text to find"),
+            ("cat", vec![], "Pick at least one field to replace in"),
+        ] {
+            let replacement =
+                Replacement::new(find.to_string(), String::new(), false, false, fields);
+            assert_eq!(
+                find_changes(&replacement, &study_sets),
+                Err(err.to_string())
+            );
+        }
+        let replacement = Replacement::new(
+            String::from("(cat"),
+            String::new(),
+            true,
+            false,
+            vec![CardField::Answer],
+        );
+        assert!(find_changes(&replacement, &study_sets)
+            .unwrap_err()
+            .starts_with("Invalid pattern: "));
+    }
+}
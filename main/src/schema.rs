@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+use crate::storage::StudyMode;
+
+// Which fields a study mode shows as the prompt, expects as the answer and shows as a hint,
+// by position in the set's list of fields
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct FieldRoles {
+    prompt: usize,
+    answer: usize,
+    hint: Option<usize>,
+}
+
+impl FieldRoles {
+    pub fn new(prompt: usize, answer: usize, hint: Option<usize>) -> FieldRoles {
+        FieldRoles {
+            prompt,
+            answer,
+            hint,
+        }
+    }
+
+    // Word, Answer and Remarks, without remarks where the page never showed them
+    fn default_for(mode: StudyMode, num_of_fields: usize) -> FieldRoles {
+        let hint = match mode {
+            StudyMode::TestNoRemarks | StudyMode::Listen => None,
+            _ if num_of_fields > 2 => Some(2),
+            _ => None,
+        };
+        FieldRoles::new(0, 1.min(num_of_fields.saturating_sub(1)), hint)
+    }
+
+    pub fn get_prompt(&self) -> usize {
+        self.prompt
+    }
+
+    pub fn get_answer(&self) -> usize {
+        self.answer
+    }
+
+    pub fn get_hint(&self) -> Option<usize> {
+        self.hint
+    }
+
+    fn fits(&self, num_of_fields: usize) -> bool {
+        self.prompt < num_of_fields
+            && self.answer < num_of_fields
+            && self.hint.is_none_or(|hint| hint < num_of_fields)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+struct ModeRoles {
+    mode: StudyMode,
+    roles: FieldRoles,
+}
+
+// The fields every card of a set has, e.g. Kanji, On-reading, Kun-reading and Meaning.
+// The first three are kept in a card's word, answer and remarks, so sets saved before
+// schemas read as Word, Answer and Remarks
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FieldSchema {
+    fields: Vec<String>,
+    // modes without an entry use the first three fields
+    modes: Vec<ModeRoles>,
+}
+
+impl Default for FieldSchema {
+    fn default() -> FieldSchema {
+        FieldSchema {
+            fields: vec![
+                String::from("Word"),
+                String::from("Answer"),
+                String::from("Remarks"),
+            ],
+            modes: Vec::new(),
+        }
+    }
+}
+
+impl FieldSchema {
+    pub fn is_default(&self) -> bool {
+        *self == FieldSchema::default()
+    }
+
+    pub fn get_fields(&self) -> Vec<String> {
+        self.fields.clone()
+    }
+
+    pub fn get_num_of_fields(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn get_field_name(&self, field: usize) -> String {
+        self.fields.get(field).cloned().unwrap_or_default()
+    }
+
+    // Renames fields by position; cards keep the values of fields removed from the end
+    pub fn set_fields(&mut self, fields: Vec<String>) {
+        self.fields = fields;
+    }
+
+    // Roles pointing past the last field fall back to the defaults
+    pub fn get_roles(&self, mode: StudyMode) -> FieldRoles {
+        let num_of_fields = self.fields.len();
+        match self.modes.iter().find(|mode_roles| mode_roles.mode == mode) {
+            Some(mode_roles) if mode_roles.roles.fits(num_of_fields) => mode_roles.roles,
+            _ => FieldRoles::default_for(mode, num_of_fields),
+        }
+    }
+
+    pub fn set_roles(&mut self, mode: StudyMode, roles: FieldRoles) {
+        self.modes.retain(|mode_roles| mode_roles.mode != mode);
+        self.modes.push(ModeRoles { mode, roles });
+    }
+}
@@ -0,0 +1,296 @@
+use crate::kana;
+use crate::storage::{Card, StudySet};
+use crate::tags;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CardField {
+    Word,
+    Answer,
+    Remarks,
+    Tags,
+}
+
+impl CardField {
+    pub fn get_label(&self) -> String {
+        match self {
+            CardField::Word => String::from("Word"),
+            CardField::Answer => String::from("Answer"),
+            CardField::Remarks => String::from("Remarks"),
+            CardField::Tags => String::from("Card Tag"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MatchMode {
+    // query appears anywhere in the field
+    Substring,
+    // field, or a word in the field, starts with the query
+    Prefix,
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    set_name: String,
+    card: Card,
+    matched_fields: Vec<CardField>,
+}
+
+impl SearchResult {
+    pub fn get_set_name(&self) -> String {
+        self.set_name.clone()
+    }
+
+    pub fn get_card(&self) -> Card {
+        self.card.clone()
+    }
+
+    pub fn get_matched_fields(&self) -> Vec<CardField> {
+        self.matched_fields.clone()
+    }
+}
+
+struct IndexedText {
+    field: CardField,
+    plain: String,
+    kana: String,
+}
+
+struct IndexEntry {
+    set_name: String,
+    card: Card,
+    texts: Vec<IndexedText>,
+}
+
+// Lower cased and kana normalised copies of every card's text, built once per search page
+pub struct SearchIndex {
+    entries: Vec<IndexEntry>,
+}
+
+fn is_match(text: &str, query: &str, mode: MatchMode) -> bool {
+    match mode {
+        MatchMode::Substring => text.contains(query),
+        MatchMode::Prefix => {
+            text.starts_with(query) || text.split_whitespace().any(|word| word.starts_with(query))
+        }
+    }
+}
+
+impl SearchIndex {
+    pub fn new(study_sets: Vec<StudySet>) -> SearchIndex {
+        let mut entries = Vec::new();
+        for study_set in study_sets {
+            for card in study_set.get_all_cards() {
+                let mut texts = Vec::new();
+                for (field, text) in [
+                    (CardField::Word, card.get_word()),
+                    (CardField::Answer, card.get_ans()),
+                    (CardField::Remarks, card.get_remarks()),
+                ] {
+                    texts.push(IndexedText {
+                        field,
+                        plain: text.trim().to_lowercase(),
+                        kana: kana::to_comparable_kana(&text),
+                    });
+                }
+                entries.push(IndexEntry {
+                    set_name: study_set.get_set_name(),
+                    card,
+                    texts,
+                });
+            }
+        }
+        SearchIndex { entries }
+    }
+
+    // With a card tag, only cards with that tag are searched, and all of them are found
+    // when there is no text to search for
+    pub fn search(
+        &self,
+        query: String,
+        mode: MatchMode,
+        ignore_kana: bool,
+        card_tag: String,
+    ) -> Vec<SearchResult> {
+        let plain_query = query.trim().to_lowercase();
+        let card_tag = card_tag.trim();
+        if plain_query.is_empty() && card_tag.is_empty() {
+            return Vec::new();
+        }
+        let kana_query = kana::to_comparable_kana(&query);
+        let mut res = Vec::new();
+        for entry in &self.entries {
+            if !card_tag.is_empty() && !tags::has_tag(&entry.card.get_tags(), card_tag) {
+                continue;
+            }
+            let mut matched_fields = Vec::new();
+            for text in &entry.texts {
+                if plain_query.is_empty() {
+                    break;
+                }
+                if is_match(&text.plain, &plain_query, mode)
+                    || ignore_kana && is_match(&text.kana, &kana_query, mode)
+                {
+                    matched_fields.push(text.field);
+                }
+            }
+            if !card_tag.is_empty() && (plain_query.is_empty() || !matched_fields.is_empty()) {
+                matched_fields.push(CardField::Tags);
+            }
+            if !matched_fields.is_empty() {
+                res.push(SearchResult {
+                    set_name: entry.set_name.clone(),
+                    card: entry.card.clone(),
+                    matched_fields,
+                });
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_index() -> SearchIndex {
+        let mut animals = StudySet::new(0, String::from("Animals"));
+        let mut cat = Card::new(
+            0,
+            String::from("ねこ"),
+            String::from("cat"),
+            String::from("a small cat"),
+        );
+        cat.set_tags(vec![String::from("N5/animals")]);
+        animals.add_card(cat);
+        animals.add_card(Card::new(
+            1,
+            String::from("いぬ"),
+            String::from("dog"),
+            String::from("concatenated"),
+        ));
+        let mut verbs = StudySet::new(1, String::from("Verbs"));
+        verbs.add_card(Card::new(
+            0,
+            String::from("たべる"),
+            String::from("to eat"),
+            String::new(),
+        ));
+        SearchIndex::new(vec![animals, verbs])
+    }
+
+    fn found(results: &[SearchResult]) -> Vec<(String, String, Vec<CardField>)> {
+        results
+            .iter()
+            .map(|result| {
+                (
+                    result.get_set_name(),
+                    result.get_card().get_word(),
+                    result.get_matched_fields(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_text_anywhere_in_every_field() {
+        let results = make_index().search(
+            String::from(" CAT "),
+            MatchMode::Substring,
+            false,
+            String::new(),
+        );
+        assert_eq!(
+            found(&results),
+            vec![
+                (
+                    String::from("Animals"),
+                    String::from("ねこ"),
+                    vec![CardField::Answer, CardField::Remarks]
+                ),
+                (
+                    String::from("Animals"),
+                    String::from("いぬ"),
+                    vec![CardField::Remarks]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn prefix_matches_start_of_field_or_word() {
+        let index = make_index();
+        let results = index.search(String::from("eat"), MatchMode::Prefix, false, String::new());
+        assert_eq!(
+            found(&results),
+            vec![(
+                String::from("Verbs"),
+                String::from("たべる"),
+                vec![CardField::Answer]
+            )]
+        );
+        let results = index.search(String::from("cat"), MatchMode::Prefix, false, String::new());
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].get_card().get_word(), "ねこ");
+    }
+
+    #[test]
+    fn matches_across_kana_scripts_only_when_asked() {
+        let index = make_index();
+        let results = index.search(
+            String::from("ネコ"),
+            MatchMode::Substring,
+            true,
+            String::new(),
+        );
+        assert_eq!(
+            found(&results),
+            vec![(
+                String::from("Animals"),
+                String::from("ねこ"),
+                vec![CardField::Word]
+            )]
+        );
+        let results = index.search(
+            String::from("ネコ"),
+            MatchMode::Substring,
+            false,
+            String::new(),
+        );
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn card_tag_narrows_search_and_lists_tagged_cards() {
+        let index = make_index();
+        let results = index.search(
+            String::new(),
+            MatchMode::Substring,
+            false,
+            String::from("n5"),
+        );
+        assert_eq!(
+            found(&results),
+            vec![(
+                String::from("Animals"),
+                String::from("ねこ"),
+                vec![CardField::Tags]
+            )]
+        );
+        let results = index.search(
+            String::from("cat"),
+            MatchMode::Substring,
+            false,
+            String::from("N5/verbs"),
+        );
+        assert!(results.is_empty());
+        assert!(index
+            .search(
+                String::from("  "),
+                MatchMode::Substring,
+                false,
+                String::new()
+            )
+            .is_empty());
+    }
+}
@@ -0,0 +1,204 @@
+use druid::KeyEvent;
+use serde::{Deserialize, Serialize};
+
+use crate::query::SavedQuery;
+use crate::sort::ListSort;
+use crate::tags::{self, TagColor};
+use crate::timer::TimeLimits;
+
+// Things that can be done from the keyboard on the learn and test pages
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyAction {
+    Confirm,
+    Next,
+    Prev,
+    ToggleAnswer,
+    Back,
+}
+
+impl KeyAction {
+    pub fn get_label(&self) -> String {
+        match self {
+            KeyAction::Confirm => String::from("Confirm"),
+            KeyAction::Next => String::from("Next"),
+            KeyAction::Prev => String::from("Prev"),
+            KeyAction::ToggleAnswer => String::from("Show/Hide Answer"),
+            KeyAction::Back => String::from("Back to List"),
+        }
+    }
+}
+
+// Each binding is a key name with optional modifiers, e.g. "Enter", "Shift+Tab" or "Ctrl+H"
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    confirm: Vec<String>,
+    next: Vec<String>,
+    prev: Vec<String>,
+    toggle_answer: Vec<String>,
+    back: Vec<String>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        let to_strings = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect();
+        KeyBindings {
+            confirm: to_strings(&["Enter"]),
+            next: to_strings(&["Tab", "ArrowDown", "PageDown"]),
+            prev: to_strings(&["Shift+Tab", "ArrowUp", "PageUp"]),
+            toggle_answer: to_strings(&["Ctrl+H", "F2"]),
+            back: to_strings(&["Escape"]),
+        }
+    }
+}
+
+fn is_binding_pressed(binding: &str, event: &KeyEvent) -> bool {
+    let parts: Vec<&str> = binding.split('+').map(|part| part.trim()).collect();
+    let (key, modifiers) = match parts.split_last() {
+        Some((key, modifiers)) => (key.to_string(), modifiers),
+        None => return false,
+    };
+    let has_modifier = |name: &str| {
+        modifiers
+            .iter()
+            .any(|modifier| modifier.eq_ignore_ascii_case(name))
+    };
+    let pressed_key = event.key.to_string();
+    // typed characters already carry shift, e.g. "?" rather than "Shift+/"
+    let is_character = pressed_key.chars().count() == 1;
+    pressed_key.eq_ignore_ascii_case(&key)
+        && has_modifier("Ctrl") == event.mods.ctrl()
+        && has_modifier("Alt") == event.mods.alt()
+        && has_modifier("Meta") == event.mods.meta()
+        && (is_character || has_modifier("Shift") == event.mods.shift())
+}
+
+impl KeyBindings {
+    fn get_bindings(&self, action: KeyAction) -> &Vec<String> {
+        match action {
+            KeyAction::Confirm => &self.confirm,
+            KeyAction::Next => &self.next,
+            KeyAction::Prev => &self.prev,
+            KeyAction::ToggleAnswer => &self.toggle_answer,
+            KeyAction::Back => &self.back,
+        }
+    }
+
+    pub fn get_action(&self, event: &KeyEvent) -> Option<KeyAction> {
+        let actions = [
+            KeyAction::Confirm,
+            KeyAction::Next,
+            KeyAction::Prev,
+            KeyAction::ToggleAnswer,
+            KeyAction::Back,
+        ];
+        actions.into_iter().find(|action| {
+            self.get_bindings(*action)
+                .iter()
+                .any(|binding| is_binding_pressed(binding, event))
+        })
+    }
+
+    // e.g. "Next: Tab / ArrowDown"
+    pub fn get_description(&self, action: KeyAction) -> String {
+        format!(
+            "{}: {}",
+            action.get_label(),
+            self.get_bindings(action).join(" / ")
+        )
+    }
+}
+
+// Preferences kept in the settings file, see Storage::read_settings_file
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    key_bindings: KeyBindings,
+    // last used for a timed test
+    time_limits: TimeLimits,
+    // study set list queries, in the order they were saved
+    saved_queries: Vec<SavedQuery>,
+    // colours chosen on the tags page
+    tag_colors: Vec<TagColor>,
+    // how the study sets list was last shown
+    list_sort: ListSort,
+    group_by_tag: bool,
+}
+
+impl Settings {
+    pub fn get_key_bindings(&self) -> KeyBindings {
+        self.key_bindings.clone()
+    }
+
+    pub fn get_time_limits(&self) -> TimeLimits {
+        self.time_limits
+    }
+
+    pub fn set_time_limits(&mut self, time_limits: TimeLimits) {
+        self.time_limits = time_limits;
+    }
+
+    pub fn get_list_sort(&self) -> ListSort {
+        self.list_sort
+    }
+
+    pub fn set_list_sort(&mut self, list_sort: ListSort) {
+        self.list_sort = list_sort;
+    }
+
+    pub fn is_grouped_by_tag(&self) -> bool {
+        self.group_by_tag
+    }
+
+    pub fn set_group_by_tag(&mut self, group_by_tag: bool) {
+        self.group_by_tag = group_by_tag;
+    }
+
+    pub fn get_saved_queries(&self) -> Vec<SavedQuery> {
+        self.saved_queries.clone()
+    }
+
+    // Replaces any query saved under the same name
+    pub fn save_query(&mut self, saved_query: SavedQuery) {
+        self.delete_query(saved_query.get_name());
+        self.saved_queries.push(saved_query);
+    }
+
+    pub fn delete_query(&mut self, name: String) {
+        self.saved_queries
+            .retain(|saved_query| saved_query.get_name() != name);
+    }
+
+    pub fn get_tag_colors(&self) -> Vec<TagColor> {
+        self.tag_colors.clone()
+    }
+
+    // No colour leaves the tag in the colour of its parent
+    pub fn set_tag_color(&mut self, tag: String, color: Option<String>) {
+        self.tag_colors
+            .retain(|tag_color| tag_color.get_tag() != tag);
+        if let Some(color) = color {
+            self.tag_colors.push(TagColor::new(tag, color));
+        }
+    }
+
+    // Colours follow a renamed tag, unless the tag it is merged into has its own
+    pub fn rename_tag(&mut self, from: &str, to: &str) {
+        let (moved, mut kept): (Vec<TagColor>, Vec<TagColor>) = self
+            .tag_colors
+            .drain(..)
+            .partition(|tag_color| tags::is_under(&tag_color.get_tag(), from));
+        for tag_color in moved {
+            let tag = tags::rename_tag(&tag_color.get_tag(), from, to);
+            if !kept.iter().any(|other| other.get_tag() == tag) {
+                kept.push(TagColor::new(tag, tag_color.get_color()));
+            }
+        }
+        self.tag_colors = kept;
+    }
+
+    pub fn delete_tag(&mut self, tag: &str) {
+        self.tag_colors
+            .retain(|tag_color| !tags::is_under(&tag_color.get_tag(), tag));
+    }
+}
@@ -0,0 +1,120 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::history::SetStats;
+use crate::storage::ListItem;
+
+// What the study sets list is sorted by
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub enum SortKey {
+    Name,
+    Cards,
+    LastStudied,
+    Accuracy,
+    // sets made before creation dates were kept stay in the order they were added
+    #[default]
+    Created,
+}
+
+impl SortKey {
+    pub fn get_all() -> Vec<SortKey> {
+        vec![
+            SortKey::Name,
+            SortKey::Cards,
+            SortKey::LastStudied,
+            SortKey::Accuracy,
+            SortKey::Created,
+        ]
+    }
+
+    pub fn get_label(&self) -> String {
+        match self {
+            SortKey::Name => String::from("Name"),
+            SortKey::Cards => String::from("Cards"),
+            SortKey::LastStudied => String::from("Last Studied"),
+            SortKey::Accuracy => String::from("Accuracy"),
+            SortKey::Created => String::from("Date Created"),
+        }
+    }
+}
+
+// Ascending is A to Z, fewest cards, longest ago studied, least accurate and oldest first;
+// sets never studied come before the rest
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ListSort {
+    key: SortKey,
+    descending: bool,
+}
+
+impl ListSort {
+    pub fn new(key: SortKey, descending: bool) -> ListSort {
+        ListSort { key, descending }
+    }
+
+    pub fn get_key(&self) -> SortKey {
+        self.key
+    }
+
+    pub fn is_descending(&self) -> bool {
+        self.descending
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Chunk {
+    Number(u64),
+    Text(String),
+}
+
+fn split_chunks(text: &str) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        let is_digit = c.is_ascii_digit();
+        let mut chunk = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_ascii_digit() != is_digit {
+                break;
+            }
+            chunk.push(c);
+            chars.next();
+        }
+        chunks.push(match chunk.parse() {
+            Ok(number) if is_digit => Chunk::Number(number),
+            _ => Chunk::Text(chunk.to_lowercase()),
+        });
+    }
+    chunks
+}
+
+// Compares numbers in names by value, so "Lesson 9" comes before "Lesson 10"
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    split_chunks(a).cmp(&split_chunks(b)).then_with(|| a.cmp(b))
+}
+
+// `set_stats` holds the stats of each set by name, sets missing from it are never studied
+pub fn sort_list_items(
+    items: &mut [ListItem],
+    sort: ListSort,
+    set_stats: &HashMap<String, SetStats>,
+) {
+    let get_stats = |item: &ListItem| set_stats.get(&item.get_name()).copied().unwrap_or_default();
+    match sort.key {
+        SortKey::Name => items.sort_by(|a, b| natural_cmp(&a.get_name(), &b.get_name())),
+        SortKey::Cards => items.sort_by_key(|item| item.get_num_of_cards()),
+        SortKey::LastStudied => items.sort_by_key(|item| get_stats(item).get_last_studied()),
+        SortKey::Accuracy => items.sort_by_key(|item| {
+            // whole percent is enough to order by
+            get_stats(item)
+                .get_accuracy()
+                .map(|accuracy| (accuracy * 100.0).round() as u64)
+        }),
+        SortKey::Created => items.sort_by_key(|item| item.get_created_at()),
+    }
+    if sort.descending {
+        items.reverse();
+    }
+}
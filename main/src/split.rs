@@ -0,0 +1,418 @@
+// Splitting a set into new sets, by card range, e.g. "1-20, 21-40", or by card tag, e.g.
+// "kanji, vocab". Each part can be given the name of its set after "=", e.g.
+// "1-20 = Kanji Unit 17A"; otherwise range parts are named "<set name> A", "<set name> B", ...
+// and tag parts "<set name> - <tag>".
+// Cards in no part stay in the set being split.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use crate::duplicates;
+use crate::storage::{Catalogue, Storage, StudySet};
+use crate::tags;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SplitPart {
+    name: String,
+    // ids of the cards going into the new set, in order
+    card_ids: Vec<usize>,
+}
+
+impl SplitPart {
+    pub fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn get_card_ids(&self) -> Vec<usize> {
+        self.card_ids.clone()
+    }
+}
+
+// "1-20 = Kanji Unit 17A" gives ("1-20", Some("Kanji Unit 17A"))
+fn split_name(part: &str) -> (String, Option<String>) {
+    match part.split_once('=') {
+        Some((part, name)) if !name.trim().is_empty() => {
+            (part.trim().to_string(), Some(name.trim().to_string()))
+        }
+        Some((part, _)) => (part.trim().to_string(), None),
+        None => (part.trim().to_string(), None),
+    }
+}
+
+// "A" to "Z", then "27", "28", ...
+fn get_part_letter(index: usize) -> String {
+    if index < 26 {
+        ((b'A' + index as u8) as char).to_string()
+    } else {
+        (index + 1).to_string()
+    }
+}
+
+fn parse_card_number(text: &str, num_of_cards: usize) -> Result<usize, String> {
+    match text.trim().parse::<usize>() {
+        Ok(number) if number >= 1 && number <= num_of_cards => Ok(number),
+        Ok(_) => Err(format!(
+            "Card {} is not in the set, it has {} cards",
+            text.trim(),
+            num_of_cards
+        )),
+        Err(_) => Err(format!("Invalid card number [{}]", text.trim())),
+    }
+}
+
+// Card numbers start from 1 as on the view page, a range of "5" is just card 5
+pub fn parse_range_parts(text: &str, study_set: &StudySet) -> Result<Vec<SplitPart>, String> {
+    let num_of_cards = study_set.get_num_of_cards();
+    let mut parts: Vec<SplitPart> = Vec::new();
+    let mut used = vec![false; num_of_cards];
+    for (index, part) in text
+        .split(',')
+        .filter(|part| !part.trim().is_empty())
+        .enumerate()
+    {
+        let (range, name) = split_name(part);
+        let (first, last) = match range.split_once('-') {
+            Some((first, last)) => (
+                parse_card_number(first, num_of_cards)?,
+                parse_card_number(last, num_of_cards)?,
+            ),
+            None => {
+                let number = parse_card_number(&range, num_of_cards)?;
+                (number, number)
+            }
+        };
+        if first > last {
+            return Err(format!("Range [{}] ends before it starts", range));
+        }
+        let card_ids: Vec<usize> = (first - 1..last).collect();
+        if card_ids.iter().any(|card_id| used[*card_id]) {
+            return Err(format!("Range [{}] overlaps another range", range));
+        }
+        for card_id in &card_ids {
+            used[*card_id] = true;
+        }
+        let name = name.unwrap_or(format!(
+            "{} {}",
+            study_set.get_set_name(),
+            get_part_letter(index)
+        ));
+        parts.push(SplitPart { name, card_ids });
+    }
+    if parts.is_empty() {
+        return Err(String::from("Enter card ranges, e.g. 1-20, 21-40"));
+    }
+    Ok(parts)
+}
+
+// A card with more than one of the tags goes into the part of the first one listed
+pub fn parse_tag_parts(text: &str, study_set: &StudySet) -> Result<Vec<SplitPart>, String> {
+    let mut parts: Vec<SplitPart> = Vec::new();
+    let mut tags_to_split: Vec<String> = Vec::new();
+    for part in text.split(',').filter(|part| !part.trim().is_empty()) {
+        let (tag, name) = split_name(part);
+        let tag = tags::normalize_tag(&tag);
+        if tags_to_split.contains(&tag) {
+            return Err(format!("Tag [{}] is listed twice", tag));
+        }
+        let name = name.unwrap_or(format!(
+            "{} - {}",
+            study_set.get_set_name(),
+            tags::get_leaf(&tag)
+        ));
+        tags_to_split.push(tag);
+        parts.push(SplitPart {
+            name,
+            card_ids: Vec::new(),
+        });
+    }
+    if parts.is_empty() {
+        return Err(String::from("Enter card tags, e.g. kanji, vocab"));
+    }
+    for card in study_set.get_all_cards() {
+        if let Some(index) = tags_to_split
+            .iter()
+            .position(|tag| tags::has_tag(&card.get_tags(), tag))
+        {
+            parts[index].card_ids.push(card.get_id());
+        }
+    }
+    if let Some(index) = parts.iter().position(|part| part.card_ids.is_empty()) {
+        return Err(format!("No card has the tag [{}]", tags_to_split[index]));
+    }
+    Ok(parts)
+}
+
+// Appends the cards of the named sets to the set, adds their tags to its own and deletes
+// them once it is written. With `skip_duplicates`, cards whose word and answer match a card
+// already in the set are left out. Returns the number of cards added
+pub fn merge_sets(
+    catalogue: &mut Catalogue,
+    set_id: usize,
+    set_names: Vec<String>,
+    skip_duplicates: bool,
+) -> Result<usize, String> {
+    let item = match catalogue.get_item_by_id(set_id).pop() {
+        Some(item) => item,
+        None => return Err(String::from("Set not found")),
+    };
+    if set_names.is_empty() {
+        return Err(String::from("Enter the sets to merge in"));
+    }
+    let mut source_names: Vec<String> = Vec::new();
+    for set_name in &set_names {
+        match catalogue.get_id_by_name(set_name.clone()) {
+            Some(source_id) if source_id == set_id => {
+                return Err(format!("[{}] cannot be merged into itself", set_name))
+            }
+            Some(_) if !source_names.contains(set_name) => source_names.push(set_name.clone()),
+            Some(_) => {}
+            None => return Err(format!("Set [{}] not found", set_name)),
+        }
+    }
+    let prev_set = Storage::read_set_file(item.get_name())?;
+    let mut study_set = prev_set.clone();
+    let mut seen: HashSet<(String, String)> = study_set
+        .get_all_cards()
+        .iter()
+        .map(duplicates::get_card_key)
+        .collect();
+    let mut media_copies: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut num_added = 0;
+    for source_name in &source_names {
+        let source_set = Storage::read_set_file(source_name.clone())?;
+        for card in source_set.get_all_cards() {
+            if skip_duplicates && !seen.insert(duplicates::get_card_key(&card)) {
+                continue;
+            }
+            let mut card = Storage::plan_card_media(
+                card,
+                source_name.clone(),
+                item.get_name(),
+                &mut media_copies,
+            );
+            card.set_id(study_set.get_num_of_cards());
+            study_set.add_card(card);
+            num_added += 1;
+        }
+        let mut set_tags = study_set.get_all_tags();
+        set_tags.extend(source_set.get_all_tags());
+        study_set.set_tags(set_tags);
+    }
+    Storage::update_set_files(&[study_set.clone()])?;
+    if let Err(err) = Storage::copy_media_files(&media_copies) {
+        let _ = Storage::update_set_files(&[prev_set]);
+        return Err(err);
+    }
+    catalogue.update_item(&study_set);
+    // deleting renumbers the sets, so go by name
+    for source_name in source_names {
+        if let Some(source_id) = catalogue.get_id_by_name(source_name) {
+            catalogue.delete_item_by_id(source_id);
+        }
+    }
+    Ok(num_added)
+}
+
+// Moves the cards of each part into a new set in the same folder, with the tags, fields
+// and romaji input of the set. The set keeps the cards in no part and is deleted if it
+// has none left. Every set is written before anything is deleted, and nothing is changed
+// if one cannot be. Returns the number of sets made
+pub fn split_set(
+    catalogue: &mut Catalogue,
+    set_id: usize,
+    parts: Vec<SplitPart>,
+) -> Result<usize, String> {
+    let item = match catalogue.get_item_by_id(set_id).pop() {
+        Some(item) => item,
+        None => return Err(String::from("Set not found")),
+    };
+    let mut names = catalogue.get_all_names();
+    for part in &parts {
+        crate::check_set_name(&part.get_name(), &names)?;
+        names.push(part.get_name());
+    }
+    let prev_set = Storage::read_set_file(item.get_name())?;
+    let mut study_set = prev_set.clone();
+    let mut new_sets: Vec<StudySet> = Vec::new();
+    let mut media_copies: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut moved_ids: Vec<usize> = Vec::new();
+    for part in &parts {
+        let mut new_set = StudySet::new(
+            catalogue.get_num_of_items() + new_sets.len(),
+            part.get_name(),
+        );
+        new_set.set_tags(study_set.get_all_tags());
+        new_set.set_schema(study_set.get_schema());
+        new_set.set_kana_input(study_set.get_kana_input());
+        for card_id in part.get_card_ids() {
+            let mut card = Storage::plan_card_media(
+                study_set.get_card(card_id),
+                item.get_name(),
+                part.get_name(),
+                &mut media_copies,
+            );
+            card.set_id(new_set.get_num_of_cards());
+            new_set.add_card(card);
+            moved_ids.push(card_id);
+        }
+        new_sets.push(new_set);
+    }
+    moved_ids.sort();
+    moved_ids.dedup();
+    for card_id in moved_ids.iter().rev() {
+        study_set.delete_card(*card_id);
+    }
+    // a set left without cards is deleted instead of written
+    let changed_sets = if study_set.get_num_of_cards() == 0 {
+        Vec::new()
+    } else {
+        vec![study_set.clone()]
+    };
+    Storage::create_set_files(&new_sets, item.get_folder(), &changed_sets)?;
+    if let Err(err) = Storage::copy_media_files(&media_copies) {
+        for new_set in &new_sets {
+            Storage::delete_set_file(new_set.get_set_name());
+        }
+        let _ = Storage::update_set_files(&[prev_set]);
+        return Err(err);
+    }
+    for new_set in &new_sets {
+        catalogue.add_item(catalogue.get_num_of_items(), new_set, item.get_folder());
+    }
+    if study_set.get_num_of_cards() == 0 {
+        catalogue.delete_item_by_id(set_id);
+    } else {
+        Storage::remove_unused_media(&study_set);
+        catalogue.update_item(&study_set);
+    }
+    Ok(parts.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Card;
+
+    fn make_set(num_of_cards: usize) -> StudySet {
+        let mut study_set = StudySet::new(0, String::from("Kanji"));
+        for card_id in 0..num_of_cards {
+            study_set.add_card(Card::new(
+                card_id,
+                format!("word {}", card_id + 1),
+                String::new(),
+                String::new(),
+            ));
+        }
+        study_set
+    }
+
+    fn get_parts(parts: &[SplitPart]) -> Vec<(String, Vec<usize>)> {
+        parts
+            .iter()
+            .map(|part| (part.get_name(), part.get_card_ids()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_ranges_with_names() {
+        let parts = parse_range_parts("1-3, 5 = Kanji Extra,, 4-4", &make_set(6)).unwrap();
+        assert_eq!(
+            get_parts(&parts),
+            vec![
+                (String::from("Kanji A"), vec![0, 1, 2]),
+                (String::from("Kanji Extra"), vec![4]),
+                (String::from("Kanji C"), vec![3]),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_overlapping_and_out_of_bounds_ranges() {
+        let study_set = make_set(5);
+        for (text, err) in [
+            ("1-3, 3-5", "Range [3-5] overlaps another range"),
+            ("1-5, 2", "Range [2] overlaps another range"),
+            ("4-6", "Card 6 is not in the set, it has 5 cards"),
+            ("0-2", "Card 0 is not in the set, it has 5 cards"),
+            ("4-2", "Range [4-2] ends before it starts"),
+            ("a-2", "Invalid card number [a]"),
+            (" , ", "Enter card ranges, e.g. 1-20, 21-40"),
+        ] {
+            assert_eq!(
+                parse_range_parts(text, &study_set),
+                Err(err.to_string()),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn parses_tags_in_order_listed() {
+        let mut study_set = make_set(3);
+        let mut cards = study_set.get_all_cards();
+        cards[0].set_tags(vec![String::from("vocab"), String::from("kanji/n5")]);
+        cards[1].set_tags(vec![String::from("Kanji")]);
+        cards[2].set_tags(vec![String::from("vocab")]);
+        study_set.set_cards(cards);
+        // a card with both tags goes with the first one listed
+        let parts = parse_tag_parts("kanji, vocab = Words", &study_set).unwrap();
+        assert_eq!(
+            get_parts(&parts),
+            vec![
+                (String::from("Kanji - kanji"), vec![0, 1]),
+                (String::from("Words"), vec![2]),
+            ]
+        );
+        let parts = parse_tag_parts("vocab, kanji", &study_set).unwrap();
+        assert_eq!(
+            get_parts(&parts),
+            vec![
+                (String::from("Kanji - vocab"), vec![0, 2]),
+                (String::from("Kanji - kanji"), vec![1]),
+            ]
+        );
+        assert_eq!(
+            parse_tag_parts("kanji, kanji /", &study_set),
+            Err(String::from("Tag [kanji] is listed twice"))
+        );
+        assert_eq!(
+            parse_tag_parts("grammar", &study_set),
+            Err(String::from("No card has the tag [grammar]"))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_part_names_before_writing() {
+        let item = serde_json::from_value(serde_json::json!({
+            "item_id": 0,
+            "item_name": "Kanji",
+            "item_tags": [],
+            "num_of_cards": 4,
+        }))
+        .unwrap();
+        let mut catalogue = Catalogue::new(vec![item]);
+        for (names, err) in [
+            (vec!["Kanji A", " "], "Set Name Cannot Be Empty"),
+            (vec!["Kanji A", "Kanji A"], "Set [Kanji A] already exists!"),
+            (vec!["Kanji"], "Set [Kanji] already exists!"),
+            (vec!["Kanji A", "Kanji/B"], "Set Name Cannot Contain [/]"),
+            (vec!["Kanji: 1"], "Set Name Cannot Contain [:]"),
+        ] {
+            let parts = names
+                .iter()
+                .map(|name| SplitPart {
+                    name: name.to_string(),
+                    card_ids: vec![0],
+                })
+                .collect();
+            assert_eq!(
+                split_set(&mut catalogue, 0, parts),
+                Err(err.to_string()),
+                "{:?}",
+                names
+            );
+        }
+        assert_eq!(catalogue.get_all_names(), vec!["Kanji"]);
+    }
+}
@@ -1,26 +1,139 @@
+use crate::history::StudyRecord;
+use crate::kana::KanaInput;
+use crate::schema::FieldSchema;
+use crate::settings::Settings;
 use druid::Data;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::fs::File;
 use std::fs::OpenOptions;
-use std::io::Write;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
 
 const DATA_DIR_PATH: &str = "./data";
 const INVENTORY_DIR_PATH: &str = "./inventory";
 const INVENTORY_FILE_PATH: &str = "./inventory/inventory.json";
+const EXPORT_DIR_PATH: &str = "./exports";
+const SETTINGS_FILE_PATH: &str = "./settings.json";
+const SESSION_DIR_PATH: &str = "./sessions";
+const HISTORY_DIR_PATH: &str = "./history";
+
+// between the folders of a set's folder, see folders.rs
+pub const FOLDER_SEPARATOR: char = '/';
+
+// The last modified time of each set file as the app itself left it, by set name, or None if
+// the app removed it, so the watcher can tell its own writes from outside ones
+static OWN_WRITES: Mutex<BTreeMap<String, Option<SystemTime>>> = Mutex::new(BTreeMap::new());
+
+// The folder of each set by name, as kept in the inventory, so set files are found without
+// searching the data folder
+static SET_FOLDERS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+// The last copy of each set that was read or written, by name, used while its file cannot be
+// parsed, e.g. when another program is half way through saving it
+static GOOD_SETS: Mutex<BTreeMap<String, StudySet>> = Mutex::new(BTreeMap::new());
+
+// seconds since the unix epoch
+pub fn get_unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+// Kinds of files a card can have in its set's media folder
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MediaKind {
+    Audio,
+    Image,
+}
+
+impl MediaKind {
+    pub fn get_label(&self) -> String {
+        match self {
+            MediaKind::Audio => String::from("Audio"),
+            MediaKind::Image => String::from("Image"),
+        }
+    }
+}
+
+// Marks a card can carry on its own, whatever set it is in
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CardFlag {
+    Starred,
+    // keeps being forgotten
+    Leech,
+    // left out of study when skipping suspended cards
+    Suspended,
+    NeedsReview,
+}
+
+impl CardFlag {
+    pub fn get_all() -> Vec<CardFlag> {
+        vec![
+            CardFlag::Starred,
+            CardFlag::Leech,
+            CardFlag::Suspended,
+            CardFlag::NeedsReview,
+        ]
+    }
+
+    pub fn get_label(&self) -> String {
+        match self {
+            CardFlag::Starred => String::from("Starred"),
+            CardFlag::Leech => String::from("Leech"),
+            CardFlag::Suspended => String::from("Suspended"),
+            CardFlag::NeedsReview => String::from("Needs Review"),
+        }
+    }
+}
+
+// How a card is studied: a word with its answer, or a cloze sentence with blanks to fill in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CardType {
+    #[default]
+    Basic,
+    Cloze,
+}
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Card {
     id: usize,
+    #[serde(default)]
+    card_type: CardType,
     word: String,
     ans: String,
     remarks: String,
+    // file name in the set's media folder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    audio: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    image: Option<String>,
+    // values of the set's fields after the first three
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    extra_fields: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    flags: Vec<CardFlag>,
 }
 
 impl Data for Card {
     fn same(&self, other: &Self) -> bool {
-        if self.word == other.word && self.ans == other.ans && self.remarks == other.remarks {
+        if self.card_type == other.card_type
+            && self.word == other.word
+            && self.ans == other.ans
+            && self.remarks == other.remarks
+            && self.audio == other.audio
+            && self.image == other.image
+            && self.extra_fields == other.extra_fields
+            && self.tags == other.tags
+            && self.flags == other.flags
+        {
             return true;
         }
         false
@@ -36,9 +149,15 @@ impl Card {
     ) -> Card {
         Card {
             id: new_card_id,
+            card_type: CardType::Basic,
             word: new_card_word,
             ans: new_card_ans,
             remarks: new_card_remark,
+            audio: None,
+            image: None,
+            extra_fields: Vec::new(),
+            tags: Vec::new(),
+            flags: Vec::new(),
         }
     }
 
@@ -47,7 +166,16 @@ impl Card {
     }
 
     pub fn get_id(&self) -> usize {
-        self.id.clone()
+        self.id
+    }
+
+    pub fn get_card_type(&self) -> CardType {
+        self.card_type
+    }
+
+    // see cloze::get_card_type
+    pub fn set_card_type(&mut self, card_type: CardType) {
+        self.card_type = card_type;
     }
 
     pub fn get_word(&self) -> String {
@@ -61,6 +189,120 @@ impl Card {
     pub fn get_remarks(&self) -> String {
         self.remarks.clone()
     }
+
+    // The value of the set's field at the given position, see FieldSchema
+    pub fn get_field(&self, field: usize) -> String {
+        match field {
+            0 => self.get_word(),
+            1 => self.get_ans(),
+            2 => self.get_remarks(),
+            _ => self
+                .extra_fields
+                .get(field - 3)
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    // Sets the value of the set's field at the given position, see get_field
+    pub fn set_field(&mut self, field: usize, text: String) {
+        match field {
+            0 => self.word = text,
+            1 => self.ans = text,
+            2 => self.remarks = text,
+            _ => {
+                if self.extra_fields.len() < field - 2 {
+                    self.extra_fields.resize(field - 2, String::new());
+                }
+                self.extra_fields[field - 3] = text;
+            }
+        }
+    }
+
+    pub fn get_extra_fields(&self) -> Vec<String> {
+        self.extra_fields.clone()
+    }
+
+    pub fn set_extra_fields(&mut self, extra_fields: Vec<String>) {
+        self.extra_fields = extra_fields;
+    }
+
+    pub fn get_audio(&self) -> Option<String> {
+        self.audio.clone()
+    }
+
+    pub fn set_audio(&mut self, audio: Option<String>) {
+        self.audio = audio;
+    }
+
+    pub fn get_image(&self) -> Option<String> {
+        self.image.clone()
+    }
+
+    pub fn set_image(&mut self, image: Option<String>) {
+        self.image = image;
+    }
+
+    pub fn get_tags(&self) -> Vec<String> {
+        self.tags.clone()
+    }
+
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        self.tags = tags;
+    }
+
+    pub fn add_tag(&mut self, tag: String) {
+        if !self.tags.contains(&tag) {
+            self.tags.push(tag);
+        }
+    }
+
+    pub fn delete_tag(&mut self, tag: String) {
+        self.tags.retain(|card_tag| *card_tag != tag);
+    }
+
+    pub fn get_flags(&self) -> Vec<CardFlag> {
+        self.flags.clone()
+    }
+
+    pub fn set_flags(&mut self, flags: Vec<CardFlag>) {
+        self.flags = flags;
+    }
+
+    pub fn has_flag(&self, flag: CardFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    pub fn toggle_flag(&mut self, flag: CardFlag) {
+        if self.has_flag(flag) {
+            self.flags.retain(|card_flag| *card_flag != flag);
+        } else {
+            self.flags.push(flag);
+        }
+    }
+
+    pub fn get_media(&self, kind: MediaKind) -> Option<String> {
+        match kind {
+            MediaKind::Audio => self.get_audio(),
+            MediaKind::Image => self.get_image(),
+        }
+    }
+
+    pub fn set_media(&mut self, kind: MediaKind, file_name: Option<String>) {
+        match kind {
+            MediaKind::Audio => self.set_audio(file_name),
+            MediaKind::Image => self.set_image(file_name),
+        }
+    }
+
+    // names of the files in the set's media folder this card uses
+    pub fn get_media_files(&self) -> Vec<String> {
+        self.audio
+            .iter()
+            .chain(self.image.iter())
+            .cloned()
+            .collect()
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -69,6 +311,13 @@ pub struct StudySet {
     name: String,
     tags: Vec<String>,
     cards: Vec<Card>,
+    #[serde(default)]
+    kana_input: KanaInput,
+    #[serde(default, skip_serializing_if = "FieldSchema::is_default")]
+    schema: FieldSchema,
+    // seconds since the unix epoch, 0 for sets made before this was kept
+    #[serde(default)]
+    created_at: u64,
 }
 
 impl Data for StudySet {
@@ -96,9 +345,16 @@ impl StudySet {
             name: new_set_name,
             tags: vec![],
             cards: vec![],
+            kana_input: KanaInput::Off,
+            schema: FieldSchema::default(),
+            created_at: get_unix_time(),
         }
     }
 
+    pub fn get_created_at(&self) -> u64 {
+        self.created_at
+    }
+
     pub fn get_id(&self) -> usize {
         self.id
     }
@@ -107,29 +363,60 @@ impl StudySet {
     }
 
     pub fn clean_up_set(&mut self) {
-        let mut count = 0;
         let mut new_cards = Vec::new();
-        for card in &self.cards {
+        for (count, card) in self.cards.iter().enumerate() {
             let mut temp = card.clone();
             temp.set_id(count);
             new_cards.push(temp);
-            count += 1;
         }
         self.cards = new_cards;
     }
 
+    pub fn get_schema(&self) -> FieldSchema {
+        self.schema.clone()
+    }
+
+    pub fn set_schema(&mut self, schema: FieldSchema) {
+        self.schema = schema;
+    }
+
+    pub fn get_kana_input(&self) -> KanaInput {
+        self.kana_input
+    }
+
+    pub fn set_kana_input(&mut self, kana_input: KanaInput) {
+        self.kana_input = kana_input;
+    }
+
     pub fn rename_set(&mut self, new_name: String) {
         self.name = new_name;
     }
 
+    // see tags::normalize_tag
     pub fn add_tag(&mut self, tag: String) {
-        self.tags.push(tag);
+        if !tag.is_empty() && !self.tags.contains(&tag) {
+            self.tags.push(tag);
+        }
+    }
+
+    // Keeps the first of any repeated tags
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        self.tags.clear();
+        for tag in tags {
+            self.add_tag(tag);
+        }
     }
 
     pub fn add_card(&mut self, card: Card) {
         self.cards.push(card);
     }
 
+    // Replaces every card, renumbering them
+    pub fn set_cards(&mut self, cards: Vec<Card>) {
+        self.cards = cards;
+        self.clean_up_set();
+    }
+
     pub fn replace_card(&mut self, index: usize, card: Card) {
         self.cards[index] = card;
     }
@@ -139,6 +426,16 @@ impl StudySet {
         self.clean_up_set();
     }
 
+    // Takes the card out and puts it back at the given position, renumbering the cards
+    pub fn move_card(&mut self, from: usize, to: usize) {
+        if from >= self.cards.len() || to >= self.cards.len() {
+            return;
+        }
+        let card = self.cards.remove(from);
+        self.cards.insert(to, card);
+        self.clean_up_set();
+    }
+
     pub fn get_set_name(&self) -> String {
         self.name.clone()
     }
@@ -159,35 +456,13 @@ impl StudySet {
         self.tags.clone()
     }
 
-    pub fn has_tag(&self, tag: String) -> bool {
-        self.tags.contains(&tag)
-    }
-
-    pub fn has_any_tags(&self, tags: HashSet<String>) -> bool {
-        for tag in tags {
-            if self.has_tag(tag) {
-                return true;
-            }
-        }
-        false
-    }
-
-    pub fn has_all_tags(&self, tags: HashSet<String>) -> bool {
-        for tag in tags {
-            if !self.has_tag(tag) {
-                return false;
-            }
-        }
-        true
-    }
-
     pub fn get_tag_index(&self, tag: String) -> i8 {
         for i in 0..self.tags.len() {
             if self.tags[i] == tag {
                 return i as i8;
             }
         }
-        return -1;
+        -1
     }
 
     pub fn delete_tag(&mut self, tag: String) {
@@ -198,6 +473,96 @@ impl StudySet {
     }
 }
 
+// Pages a set can be studied on
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum StudyMode {
+    Learn,
+    Test,
+    TestNoRemarks,
+    Listen,
+    Flashcards,
+}
+
+impl StudyMode {
+    pub fn get_all() -> Vec<StudyMode> {
+        vec![
+            StudyMode::Learn,
+            StudyMode::Test,
+            StudyMode::TestNoRemarks,
+            StudyMode::Listen,
+            StudyMode::Flashcards,
+        ]
+    }
+
+    pub fn get_label(&self) -> String {
+        match self {
+            StudyMode::Learn => String::from("Learn"),
+            StudyMode::Test => String::from("Test"),
+            StudyMode::TestNoRemarks => String::from("Test No Remarks"),
+            StudyMode::Listen => String::from("Listen"),
+            StudyMode::Flashcards => String::from("Flashcards"),
+        }
+    }
+}
+
+// An unfinished round of study on a set, kept so it can be resumed after a restart
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Session {
+    mode: StudyMode,
+    // index into `order` of the card being shown
+    position: usize,
+    // ids of the cards, in the order they are shown
+    order: Vec<usize>,
+    answers: Vec<String>,
+    results: Vec<String>,
+}
+
+impl Session {
+    pub fn new(
+        mode: StudyMode,
+        position: usize,
+        order: Vec<usize>,
+        answers: Vec<String>,
+        results: Vec<String>,
+    ) -> Session {
+        Session {
+            mode,
+            position,
+            order,
+            answers,
+            results,
+        }
+    }
+
+    pub fn get_mode(&self) -> StudyMode {
+        self.mode
+    }
+
+    pub fn get_position(&self) -> usize {
+        self.position
+    }
+
+    pub fn get_answers(&self) -> Vec<String> {
+        self.answers.clone()
+    }
+
+    pub fn get_results(&self) -> Vec<String> {
+        self.results.clone()
+    }
+
+    pub fn get_order(&self) -> Vec<usize> {
+        self.order.clone()
+    }
+
+    // false once cards have been added or removed since the session was saved
+    pub fn fits_set(&self, num_of_cards: usize) -> bool {
+        self.order.iter().all(|id| *id < num_of_cards)
+            && self.answers.len() == num_of_cards
+            && self.results.len() == num_of_cards
+            && self.position < self.order.len()
+    }
+}
+
 pub struct Storage;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -206,20 +571,34 @@ pub struct ListItem {
     item_name: String,
     item_tags: Vec<String>,
     num_of_cards: usize,
+    #[serde(default)]
+    created_at: u64,
+    // see folders.rs
+    #[serde(default)]
+    folder: String,
 }
 
 impl ListItem {
-    fn new(id: usize, name: String, tags: Vec<String>, num: usize) -> ListItem {
+    fn new(
+        id: usize,
+        name: String,
+        tags: Vec<String>,
+        num: usize,
+        created_at: u64,
+        folder: String,
+    ) -> ListItem {
         ListItem {
             item_id: id,
             item_name: name,
             item_tags: tags,
             num_of_cards: num,
+            created_at,
+            folder,
         }
     }
 
     pub fn get_id(&self) -> usize {
-        self.item_id.clone()
+        self.item_id
     }
 
     pub fn set_id(&mut self, id: usize) {
@@ -242,26 +621,12 @@ impl ListItem {
         self.num_of_cards
     }
 
-    pub fn has_tag(&self, tag: String) -> bool {
-        self.item_tags.contains(&tag)
+    pub fn get_created_at(&self) -> u64 {
+        self.created_at
     }
 
-    pub fn has_any_tags(&self, tags: HashSet<String>) -> bool {
-        for tag in tags {
-            if self.has_tag(tag) {
-                return true;
-            }
-        }
-        false
-    }
-
-    pub fn has_all_tags(&self, tags: HashSet<String>) -> bool {
-        for tag in tags {
-            if !self.has_tag(tag) {
-                return false;
-            }
-        }
-        true
+    pub fn get_folder(&self) -> String {
+        self.folder.clone()
     }
 }
 
@@ -291,36 +656,6 @@ impl Catalogue {
         res
     }
 
-    pub fn get_all_tags(&self) -> Vec<String> {
-        let mut res: Vec<String> = Vec::new();
-        for item in &self.inventory {
-            for tag in item.get_all_tags() {
-                if !res.contains(&tag) {
-                    res.push(tag);
-                }
-            }
-        }
-        res
-    }
-
-    pub fn get_items_by_tags(&self, tags: HashSet<String>, is_match_any: bool) -> Vec<ListItem> {
-        let mut res = Vec::new();
-        if is_match_any {
-            for item in &self.inventory {
-                if item.has_any_tags(tags.clone()) {
-                    res.push(item.clone());
-                }
-            }
-        } else {
-            for item in &self.inventory {
-                if item.has_all_tags(tags.clone()) {
-                    res.push(item.clone());
-                }
-            }
-        }
-        res
-    }
-
     pub fn get_all_untagged_study_sets(&self) -> Vec<ListItem> {
         let mut res = Vec::new();
         for item in &self.inventory {
@@ -341,11 +676,14 @@ impl Catalogue {
                     );
                 }
                 Storage::update_set_file(updated_set.clone());
+                Storage::remove_unused_media(&updated_set);
                 let item = ListItem::new(
                     i,
                     updated_set.get_set_name(),
                     updated_set.get_all_tags(),
                     updated_set.get_num_of_cards(),
+                    updated_set.get_created_at(),
+                    self.inventory[i].get_folder(),
                 );
                 self.inventory[i] = item;
                 break;
@@ -353,15 +691,142 @@ impl Catalogue {
         }
     }
 
-    pub fn add_study_set(&mut self, study_set: StudySet, item_id: usize) {
+    pub fn add_study_set(&mut self, study_set: StudySet, item_id: usize, folder: String) {
+        self.add_item(item_id, &study_set, folder.clone());
+        Storage::create_set_file(study_set, folder);
+    }
+
+    // Lists a set whose file is already written, see Storage::create_set_files
+    pub fn add_item(&mut self, item_id: usize, study_set: &StudySet, folder: String) {
         let item = ListItem::new(
             item_id,
             study_set.get_set_name(),
             study_set.get_all_tags(),
             study_set.get_num_of_cards(),
+            study_set.get_created_at(),
+            folder,
         );
         self.inventory.push(item);
-        Storage::create_set_file(study_set);
+    }
+
+    // Takes the tags and number of cards of a set whose file is already written, e.g. with
+    // Storage::update_set_files
+    pub fn update_item(&mut self, study_set: &StudySet) {
+        if let Some(item) = self
+            .inventory
+            .iter_mut()
+            .find(|item| item.get_name() == study_set.get_set_name())
+        {
+            item.item_tags = study_set.get_all_tags();
+            item.num_of_cards = study_set.get_num_of_cards();
+        }
+    }
+
+    // Copies cards to the end of another set, removing them from their own set unless
+    // `keep_originals`. Both set files are replaced together, then the media files are copied
+    // along; if they cannot be, the sets are put back. Returns the number of cards copied or moved
+    pub fn transfer_cards(
+        &mut self,
+        from_id: usize,
+        to_id: usize,
+        mut card_ids: Vec<usize>,
+        keep_originals: bool,
+    ) -> Result<usize, String> {
+        if from_id == to_id {
+            return Err(String::from("Pick a different set to move or copy to"));
+        }
+        let (from_item, to_item) = match (
+            self.get_item_by_id(from_id).pop(),
+            self.get_item_by_id(to_id).pop(),
+        ) {
+            (Some(from_item), Some(to_item)) => (from_item, to_item),
+            _ => return Err(String::from("Set not found")),
+        };
+        let from_set = Storage::read_set_file(from_item.get_name())?;
+        let to_set = Storage::read_set_file(to_item.get_name())?;
+        card_ids.sort();
+        card_ids.dedup();
+        card_ids.retain(|card_id| *card_id < from_set.get_num_of_cards());
+        let mut media_copies: Vec<(PathBuf, PathBuf)> = Vec::new();
+        let (changed_sets, prev_sets) = Catalogue::plan_transfer(
+            from_set,
+            to_set,
+            &card_ids,
+            keep_originals,
+            &mut media_copies,
+        );
+        Storage::update_set_files(&changed_sets)?;
+        if let Err(err) = Storage::copy_media_files(&media_copies) {
+            let _ = Storage::update_set_files(&prev_sets);
+            return Err(err);
+        }
+        for study_set in changed_sets {
+            Storage::remove_unused_media(&study_set);
+            self.update_item(&study_set);
+        }
+        Ok(card_ids.len())
+    }
+
+    // The sets to write for a transfer of the sorted `card_ids`, and the sets as they were to put
+    // back if the media files cannot be copied
+    fn plan_transfer(
+        mut from_set: StudySet,
+        mut to_set: StudySet,
+        card_ids: &[usize],
+        keep_originals: bool,
+        media_copies: &mut Vec<(PathBuf, PathBuf)>,
+    ) -> (Vec<StudySet>, Vec<StudySet>) {
+        let mut prev_sets = vec![to_set.clone()];
+        for card_id in card_ids {
+            let mut card = Storage::plan_card_media(
+                from_set.get_card(*card_id),
+                from_set.get_set_name(),
+                to_set.get_set_name(),
+                media_copies,
+            );
+            card.set_id(to_set.get_num_of_cards());
+            to_set.add_card(card);
+        }
+        let mut changed_sets = vec![to_set];
+        if !keep_originals {
+            prev_sets.push(from_set.clone());
+            for card_id in card_ids.iter().rev() {
+                from_set.delete_card(*card_id);
+            }
+            changed_sets.push(from_set);
+        }
+        (changed_sets, prev_sets)
+    }
+
+    // Sets directly inside the folder
+    pub fn get_items_in_folder(&self, folder: &str) -> Vec<ListItem> {
+        self.inventory
+            .iter()
+            .filter(|item| item.folder == folder)
+            .cloned()
+            .collect()
+    }
+
+    pub fn move_set(&mut self, set_id: usize, folder: String) -> Result<(), String> {
+        for item in self.inventory.iter_mut() {
+            if item.get_id() == set_id {
+                Storage::move_set_file(item.get_name(), folder.clone())?;
+                item.folder = folder;
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // For sets whose folder was moved with Storage::move_folder
+    pub fn set_item_folder(&mut self, set_id: usize, folder: String) {
+        for item in self.inventory.iter_mut() {
+            if item.get_id() == set_id {
+                Storage::remember_folder(item.get_name(), Some(folder.clone()));
+                item.folder = folder;
+                break;
+            }
+        }
     }
 
     pub fn get_item_by_id(&self, id: usize) -> Vec<ListItem> {
@@ -375,6 +840,15 @@ impl Catalogue {
         res
     }
 
+    pub fn get_id_by_name(&self, name: String) -> Option<usize> {
+        for item in &self.inventory {
+            if item.get_name() == name {
+                return Some(item.get_id());
+            }
+        }
+        None
+    }
+
     pub fn delete_item_by_id(&mut self, id: usize) {
         let mut res = Vec::new();
         let mut count = 0;
@@ -385,6 +859,8 @@ impl Catalogue {
                     item.get_name(),
                     item.get_all_tags(),
                     item.get_num_of_cards(),
+                    item.get_created_at(),
+                    item.get_folder(),
                 );
                 new_item.set_id(count);
                 res.push(new_item.clone());
@@ -398,10 +874,13 @@ impl Catalogue {
 }
 
 impl Storage {
-    pub fn create_set_file(set: StudySet) {
+    pub fn create_set_file(set: StudySet, folder: String) {
         let file_name = set.get_set_name();
         let set_data = serde_json::to_string_pretty(&set).expect("Error parsing data to json");
-        let set_data_path = format!("{}/{}.json", DATA_DIR_PATH, file_name);
+        let set_dir = Storage::get_folder_dir(&folder);
+        let err_msg_dir = format!("Failed to create folder [{}]", folder);
+        fs::create_dir_all(&set_dir).expect(&err_msg_dir);
+        let set_data_path = set_dir.join(format!("{}.json", file_name));
         let err_msg_create = format!("Failed to create set data file {}.json", file_name);
         File::create(set_data_path.clone()).expect(&err_msg_create);
         let err_msg_open = format!("Failed to open set data file {}.json", file_name);
@@ -411,22 +890,61 @@ impl Storage {
             .open(set_data_path)
             .expect(&err_msg_open);
         let _ = file.write_all(set_data.as_bytes());
+        Storage::remember_folder(file_name.clone(), Some(folder));
+        Storage::remember_good_set(&set);
+        Storage::record_own_write(file_name);
     }
 
+    // The set stays in its folder
     pub fn rename_set_file(prev_set_name: String, new_set_name: String) {
-        let prev_set_file_name = format!("{}/{}.json", DATA_DIR_PATH, prev_set_name);
-        let new_set_file_name = format!("{}/{}.json", DATA_DIR_PATH, new_set_name);
+        let set_dir = Storage::get_set_dir(prev_set_name.clone());
+        let prev_set_file_name = set_dir.join(format!("{}.json", prev_set_name));
+        let new_set_file_name = set_dir.join(format!("{}.json", new_set_name));
         let err_msg = format!(
             "Failed to change name from [{}] to [{}]",
-            prev_set_file_name, new_set_file_name
+            prev_set_file_name.display(),
+            new_set_file_name.display()
         );
         fs::rename(prev_set_file_name, new_set_file_name).expect(&err_msg);
+        let prev_media_dir = set_dir.join(format!("{}.media", prev_set_name));
+        if prev_media_dir.is_dir() {
+            let err_msg = format!("Failed to rename media folder of [{}]", prev_set_name);
+            fs::rename(
+                prev_media_dir,
+                set_dir.join(format!("{}.media", new_set_name)),
+            )
+            .expect(&err_msg);
+        }
+        let prev_session_path = Storage::get_session_path(prev_set_name.clone());
+        if prev_session_path.is_file() {
+            let _ = fs::rename(
+                prev_session_path,
+                Storage::get_session_path(new_set_name.clone()),
+            );
+        }
+        let prev_history_path = Storage::get_history_path(prev_set_name.clone());
+        if prev_history_path.is_file() {
+            let _ = fs::rename(
+                prev_history_path,
+                Storage::get_history_path(new_set_name.clone()),
+            );
+        }
+        if let Ok(mut set_folders) = SET_FOLDERS.lock() {
+            if let Some(folder) = set_folders.remove(&prev_set_name) {
+                set_folders.insert(new_set_name.clone(), folder);
+            }
+        }
+        if let Ok(mut good_sets) = GOOD_SETS.lock() {
+            good_sets.remove(&prev_set_name);
+        }
+        Storage::record_own_write(prev_set_name);
+        Storage::record_own_write(new_set_name);
     }
 
     pub fn update_set_file(set: StudySet) {
         let file_name = set.get_set_name();
         let set_data = serde_json::to_string_pretty(&set).expect("Error parsing data to json");
-        let set_data_path = format!("{}/{}.json", DATA_DIR_PATH, file_name);
+        let set_data_path = Storage::get_set_path(file_name.clone());
         let err_msg_open = format!("Failed to open set data file {}.json", file_name);
         let err_msg_write = format!("Failed to write to set data file {}.json", file_name);
         let mut file = OpenOptions::new()
@@ -435,65 +953,580 @@ impl Storage {
             .open(set_data_path)
             .expect(&err_msg_open);
         file.write_all(set_data.as_bytes()).expect(&err_msg_write);
+        Storage::remember_good_set(&set);
+        Storage::record_own_write(file_name);
+    }
+
+    // Writes every set to a temporary file first, so either all the set files are replaced
+    // or, if one cannot be written or put in place, none of them are
+    pub fn update_set_files(sets: &[StudySet]) -> Result<(), String> {
+        let mut written: Vec<(PathBuf, PathBuf)> = Vec::new();
+        for set in sets {
+            let set_data = serde_json::to_string_pretty(set).expect("Error parsing data to json");
+            let set_data_path = Storage::get_set_path(set.get_set_name());
+            let temp_path = set_data_path.with_extension("json.tmp");
+            if fs::write(&temp_path, set_data).is_err() {
+                let _ = fs::remove_file(&temp_path);
+                for (temp_path, _) in written {
+                    let _ = fs::remove_file(temp_path);
+                }
+                return Err(format!(
+                    "Failed to write to set data file {}.json",
+                    set.get_set_name()
+                ));
+            }
+            written.push((temp_path, set_data_path));
+        }
+        Storage::replace_with_temp_files(&written)?;
+        for set in sets {
+            Storage::remember_good_set(set);
+            Storage::record_own_write(set.get_set_name());
+        }
+        Ok(())
+    }
+
+    // Moves each written temp file over its set file. The current files are kept until every set
+    // file is replaced, to put them back if one cannot be
+    fn replace_with_temp_files(written: &[(PathBuf, PathBuf)]) -> Result<(), String> {
+        let mut replaced: Vec<(PathBuf, Option<PathBuf>)> = Vec::new();
+        for (i, (temp_path, set_data_path)) in written.iter().enumerate() {
+            match Storage::replace_with_temp_file(temp_path, set_data_path) {
+                Ok(backup_path) => replaced.push((set_data_path.clone(), backup_path)),
+                Err(_) => {
+                    for (set_data_path, backup_path) in replaced {
+                        let _ = match backup_path {
+                            Some(backup_path) => fs::rename(backup_path, set_data_path),
+                            None => fs::remove_file(set_data_path),
+                        };
+                    }
+                    for (temp_path, _) in &written[i..] {
+                        let _ = fs::remove_file(temp_path);
+                    }
+                    return Err(format!(
+                        "Failed to replace set data file {}",
+                        set_data_path.display()
+                    ));
+                }
+            }
+        }
+        for (_, backup_path) in replaced {
+            if let Some(backup_path) = backup_path {
+                let _ = fs::remove_file(backup_path);
+            }
+        }
+        Ok(())
+    }
+
+    // Writes new sets into the folder together with changed ones, see update_set_files
+    pub fn create_set_files(
+        new_sets: &[StudySet],
+        folder: String,
+        changed_sets: &[StudySet],
+    ) -> Result<(), String> {
+        for set in new_sets {
+            Storage::remember_folder(set.get_set_name(), Some(folder.clone()));
+        }
+        let sets: Vec<StudySet> = new_sets.iter().chain(changed_sets).cloned().collect();
+        let res = Storage::update_set_files(&sets);
+        if res.is_err() {
+            for set in new_sets {
+                Storage::remember_folder(set.get_set_name(), None);
+            }
+        }
+        res
+    }
+
+    // Moves the set file aside and the temporary file in its place, returning where the set
+    // file was moved to, if there was one
+    fn replace_with_temp_file(
+        temp_path: &Path,
+        set_data_path: &Path,
+    ) -> io::Result<Option<PathBuf>> {
+        let backup_path = if set_data_path.is_file() {
+            let backup_path = set_data_path.with_extension("json.bak");
+            fs::rename(set_data_path, &backup_path)?;
+            Some(backup_path)
+        } else {
+            None
+        };
+        if let Err(err) = fs::rename(temp_path, set_data_path) {
+            if let Some(backup_path) = backup_path {
+                let _ = fs::rename(backup_path, set_data_path);
+            }
+            return Err(err);
+        }
+        Ok(backup_path)
     }
 
     pub fn delete_set_file(set_name: String) {
-        let set_data_path = format!("{}/{}.json", DATA_DIR_PATH, set_name);
+        let set_data_path = Storage::get_set_path(set_name.clone());
+        let media_dir = Storage::get_media_dir(set_name.clone());
         let err_msg_delete = format!("Failed to delete set data file {}.json", set_name);
         fs::remove_file(set_data_path).expect(&err_msg_delete);
+        if media_dir.is_dir() {
+            let _ = fs::remove_dir_all(media_dir);
+        }
+        Storage::delete_session_file(set_name.clone());
+        let _ = fs::remove_file(Storage::get_history_path(set_name.clone()));
+        Storage::remember_folder(set_name.clone(), None);
+        if let Ok(mut good_sets) = GOOD_SETS.lock() {
+            good_sets.remove(&set_name);
+        }
+        Storage::record_own_write(set_name);
+    }
+
+    // Sessions live outside the data folder, so they are not taken for set files
+    fn get_session_path(set_name: String) -> PathBuf {
+        Path::new(SESSION_DIR_PATH).join(format!("{}.json", set_name))
+    }
+
+    pub fn read_session_file(set_name: String) -> Option<Session> {
+        let data = fs::read_to_string(Storage::get_session_path(set_name)).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    pub fn update_session_file(set_name: String, session: &Session) -> io::Result<()> {
+        let data = serde_json::to_string_pretty(session)?;
+        fs::create_dir_all(SESSION_DIR_PATH)?;
+        fs::write(Storage::get_session_path(set_name), data)
+    }
+
+    // Every finished round on a set, oldest first, in "./history/<set name>.json"
+    fn get_history_path(set_name: String) -> PathBuf {
+        Path::new(HISTORY_DIR_PATH).join(format!("{}.json", set_name))
+    }
+
+    pub fn read_history_file(set_name: String) -> Vec<StudyRecord> {
+        match fs::read_to_string(Storage::get_history_path(set_name)) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn add_history_record(set_name: String, record: StudyRecord) -> io::Result<()> {
+        let mut history = Storage::read_history_file(set_name.clone());
+        history.push(record);
+        let data = serde_json::to_string_pretty(&history)?;
+        fs::create_dir_all(HISTORY_DIR_PATH)?;
+        fs::write(Storage::get_history_path(set_name), data)
+    }
+
+    pub fn delete_session_file(set_name: String) {
+        let session_path = Storage::get_session_path(set_name);
+        if session_path.is_file() {
+            let _ = fs::remove_file(session_path);
+        }
+    }
+
+    // Files attached to a set's cards are kept in "<set name>.media" next to the set file
+    pub fn get_media_dir(set_name: String) -> PathBuf {
+        Storage::get_set_dir(set_name.clone()).join(format!("{}.media", set_name))
+    }
+
+    fn get_folder_dir(folder: &str) -> PathBuf {
+        let mut dir = PathBuf::from(DATA_DIR_PATH);
+        for part in folder.split(FOLDER_SEPARATOR) {
+            if !part.is_empty() {
+                dir.push(part);
+            }
+        }
+        dir
+    }
+
+    // Every folder inside the data folder, parents before their subfolders, starting with ""
+    pub fn get_all_folders() -> Vec<String> {
+        let mut res = vec![String::new()];
+        let mut i = 0;
+        while i < res.len() {
+            let mut subfolders = Vec::new();
+            if let Ok(dir_entries) = fs::read_dir(Storage::get_folder_dir(&res[i])) {
+                for entry in dir_entries.flatten() {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    // media folders belong to the set next to them
+                    if entry.path().is_dir() && !name.ends_with(".media") {
+                        subfolders.push(if res[i].is_empty() {
+                            name
+                        } else {
+                            format!("{}{}{}", res[i], FOLDER_SEPARATOR, name)
+                        });
+                    }
+                }
+            }
+            subfolders.sort();
+            res.splice(i + 1..i + 1, subfolders);
+            i += 1;
+        }
+        res
+    }
+
+    // Every set file with the folder it is in
+    fn get_set_files() -> Vec<(PathBuf, String)> {
+        let mut res = Vec::new();
+        for folder in Storage::get_all_folders() {
+            if let Ok(dir_entries) = fs::read_dir(Storage::get_folder_dir(&folder)) {
+                for entry in dir_entries.flatten() {
+                    let path = entry.path();
+                    if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+                        res.push((path, folder.clone()));
+                    }
+                }
+            }
+        }
+        res
+    }
+
+    // Where the set's file is, or would be if it were added to the top level folder
+    fn get_set_path(set_name: String) -> PathBuf {
+        let folder = match SET_FOLDERS.lock() {
+            Ok(set_folders) => set_folders.get(&set_name).cloned().unwrap_or_default(),
+            Err(_) => String::new(),
+        };
+        Storage::get_folder_dir(&folder).join(format!("{}.json", set_name))
+    }
+
+    // Keeps the set's folder, or forgets the set if it has none
+    fn remember_folder(set_name: String, folder: Option<String>) {
+        if let Ok(mut set_folders) = SET_FOLDERS.lock() {
+            match folder {
+                Some(folder) => set_folders.insert(set_name, folder),
+                None => set_folders.remove(&set_name),
+            };
+        }
+    }
+
+    fn remember_folders(set_folders: BTreeMap<String, String>) {
+        if let Ok(mut remembered) = SET_FOLDERS.lock() {
+            *remembered = set_folders;
+        }
+    }
+
+    fn remember_inventory(inventory: &Catalogue) {
+        Storage::remember_folders(
+            inventory
+                .inventory
+                .iter()
+                .map(|item| (item.get_name(), item.get_folder()))
+                .collect(),
+        );
+    }
+
+    fn get_set_dir(set_name: String) -> PathBuf {
+        match Storage::get_set_path(set_name).parent() {
+            Some(dir) => dir.to_path_buf(),
+            None => PathBuf::from(DATA_DIR_PATH),
+        }
+    }
+
+    // Moves the set file and its media into another folder, creating it if needed
+    pub fn move_set_file(set_name: String, folder: String) -> Result<(), String> {
+        let set_dir = Storage::get_set_dir(set_name.clone());
+        let target_dir = Storage::get_folder_dir(&folder);
+        if set_dir == target_dir {
+            return Ok(());
+        }
+        fs::create_dir_all(&target_dir)
+            .map_err(|_| format!("Failed to create folder [{}]", folder))?;
+        let file_name = format!("{}.json", set_name);
+        fs::rename(set_dir.join(&file_name), target_dir.join(&file_name))
+            .map_err(|_| format!("Failed to move [{}] to [{}]", set_name, folder))?;
+        Storage::remember_folder(set_name.clone(), Some(folder.clone()));
+        let media_name = format!("{}.media", set_name);
+        if set_dir.join(&media_name).is_dir() {
+            fs::rename(set_dir.join(&media_name), target_dir.join(&media_name))
+                .map_err(|_| format!("Failed to move media folder of [{}]", set_name))?;
+        }
+        Storage::record_own_write(set_name);
+        Ok(())
+    }
+
+    pub fn create_folder(folder: &str) -> Result<(), String> {
+        let dir = Storage::get_folder_dir(folder);
+        if dir.is_dir() {
+            return Err(format!("Folder [{}] already exists", folder));
+        }
+        fs::create_dir_all(dir).map_err(|_| format!("Failed to create folder [{}]", folder))
+    }
+
+    // Only empty folders are deleted, so no set is ever lost with its folder
+    pub fn delete_folder(folder: &str) -> Result<(), String> {
+        fs::remove_dir(Storage::get_folder_dir(folder))
+            .map_err(|_| format!("Folder [{}] is not empty", folder))
+    }
+
+    // Moves the folder with the set files in it, see Catalogue::set_item_folder for the inventory
+    pub fn move_folder(from: &str, to: &str) -> Result<(), String> {
+        let target_dir = Storage::get_folder_dir(to);
+        if target_dir.exists() {
+            return Err(format!("Folder [{}] already exists", to));
+        }
+        if let Some(parent) = target_dir.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|_| format!("Failed to move [{}] to [{}]", from, to))?;
+        }
+        fs::rename(Storage::get_folder_dir(from), target_dir)
+            .map_err(|_| format!("Failed to move [{}] to [{}]", from, to))
+    }
+
+    pub fn get_media_path(set_name: String, file_name: String) -> PathBuf {
+        Storage::get_media_dir(set_name).join(file_name)
+    }
+
+    // Copies a file into the set's media folder, returning the name it is stored under
+    pub fn add_media_file(set_name: String, source_path: &Path) -> Result<String, String> {
+        if !source_path.is_file() {
+            return Err(format!("File [{}] not found", source_path.display()));
+        }
+        let media_dir = Storage::get_media_dir(set_name);
+        fs::create_dir_all(&media_dir)
+            .map_err(|_| String::from("Failed to create media folder"))?;
+        let file_name = Storage::get_free_media_name(&media_dir, source_path, &[]);
+        fs::copy(source_path, media_dir.join(&file_name))
+            .map_err(|_| format!("Failed to copy [{}]", source_path.display()))?;
+        Ok(file_name)
+    }
+
+    // A path in the set's media folder for a new recording, not used by any other file
+    pub fn get_new_recording_path(set_name: String) -> Result<PathBuf, String> {
+        let media_dir = Storage::get_media_dir(set_name);
+        fs::create_dir_all(&media_dir)
+            .map_err(|_| String::from("Failed to create media folder"))?;
+        let file_name = Storage::get_free_media_name(&media_dir, Path::new("recording.wav"), &[]);
+        Ok(media_dir.join(file_name))
+    }
+
+    // The name of the source file, numbered if the media folder already has a file by that
+    // name or one is about to be copied there
+    fn get_free_media_name(
+        media_dir: &Path,
+        source_path: &Path,
+        media_copies: &[(PathBuf, PathBuf)],
+    ) -> String {
+        let stem = source_path
+            .file_stem()
+            .map_or(String::from("media"), |stem| {
+                stem.to_string_lossy().to_string()
+            });
+        let extension = source_path
+            .extension()
+            .map_or(String::new(), |ext| format!(".{}", ext.to_string_lossy()));
+        // never overwrite a file another card may be using
+        let is_taken = |file_name: &str| {
+            let path = media_dir.join(file_name);
+            path.exists() || media_copies.iter().any(|(_, target)| *target == path)
+        };
+        let mut file_name = format!("{}{}", stem, extension);
+        let mut count = 1;
+        while is_taken(&file_name) {
+            file_name = format!("{} ({}){}", stem, count, extension);
+            count += 1;
+        }
+        file_name
+    }
+
+    // The card with its media files named as they will be in the other set's media folder.
+    // The files to copy there are added to `media_copies`, see copy_media_files
+    pub fn plan_card_media(
+        mut card: Card,
+        from: String,
+        to: String,
+        media_copies: &mut Vec<(PathBuf, PathBuf)>,
+    ) -> Card {
+        let media_dir = Storage::get_media_dir(to);
+        for kind in [MediaKind::Audio, MediaKind::Image] {
+            if let Some(file_name) = card.get_media(kind) {
+                let media_path = Storage::get_media_path(from.clone(), file_name);
+                if media_path.is_file() {
+                    let file_name =
+                        Storage::get_free_media_name(&media_dir, &media_path, media_copies);
+                    media_copies.push((media_path, media_dir.join(&file_name)));
+                    card.set_media(kind, Some(file_name));
+                }
+            }
+        }
+        card
+    }
+
+    // Copies each file to its target, removing the ones copied if any of them cannot be
+    pub fn copy_media_files(media_copies: &[(PathBuf, PathBuf)]) -> Result<(), String> {
+        for (i, (source_path, target_path)) in media_copies.iter().enumerate() {
+            let is_copied = match target_path.parent() {
+                Some(media_dir) => fs::create_dir_all(media_dir).is_ok(),
+                None => true,
+            } && fs::copy(source_path, target_path).is_ok();
+            if !is_copied {
+                for (_, target_path) in &media_copies[..i] {
+                    let _ = fs::remove_file(target_path);
+                    // only succeeds once the folder is empty
+                    if let Some(media_dir) = target_path.parent() {
+                        let _ = fs::remove_dir(media_dir);
+                    }
+                }
+                return Err(format!("Failed to copy [{}]", source_path.display()));
+            }
+        }
+        Ok(())
+    }
+
+    // Deletes media files no card of the set refers to anymore
+    pub fn remove_unused_media(set: &StudySet) {
+        let media_dir = Storage::get_media_dir(set.get_set_name());
+        let dir_entries = match fs::read_dir(&media_dir) {
+            Ok(dir_entries) => dir_entries,
+            Err(_) => return,
+        };
+        let mut used_files = HashSet::new();
+        for card in set.get_all_cards() {
+            used_files.extend(card.get_media_files());
+        }
+        for entry in dir_entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if !used_files.contains(&file_name) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+        // only succeeds once the folder is empty
+        let _ = fs::remove_dir(media_dir);
+    }
+
+    // Writes the set file and the media its cards use to "./exports/<set name>"
+    pub fn export_set(set_name: String) -> Result<PathBuf, String> {
+        let set = Storage::read_set_file(set_name.clone())?;
+        let export_dir = Path::new(EXPORT_DIR_PATH).join(&set_name);
+        let export_media_dir = export_dir.join(format!("{}.media", set_name));
+        fs::create_dir_all(&export_dir)
+            .map_err(|_| String::from("Failed to create export folder"))?;
+        let set_data = serde_json::to_string_pretty(&set).expect("Error parsing data to json");
+        fs::write(export_dir.join(format!("{}.json", set_name)), set_data)
+            .map_err(|_| format!("Failed to export set file {}.json", set_name))?;
+        for card in set.get_all_cards() {
+            for file_name in card.get_media_files() {
+                let media_path = Storage::get_media_path(set_name.clone(), file_name.clone());
+                if !media_path.is_file() {
+                    continue;
+                }
+                fs::create_dir_all(&export_media_dir)
+                    .map_err(|_| String::from("Failed to create export media folder"))?;
+                fs::copy(media_path, export_media_dir.join(&file_name))
+                    .map_err(|_| format!("Failed to export media file {}", file_name))?;
+            }
+        }
+        Ok(export_dir)
+    }
+
+    // Called once the set file is in place, so its time is the one the watcher will see
+    fn record_own_write(set_name: String) {
+        let modified = fs::metadata(Storage::get_set_path(set_name.clone()))
+            .and_then(|meta| meta.modified())
+            .ok();
+        if let Ok(mut own_writes) = OWN_WRITES.lock() {
+            own_writes.insert(set_name, modified);
+        }
+    }
+
+    // The last modified time of each set file written by the app, see OWN_WRITES
+    pub fn get_own_writes() -> BTreeMap<String, Option<SystemTime>> {
+        match OWN_WRITES.lock() {
+            Ok(own_writes) => own_writes.clone(),
+            Err(_) => BTreeMap::new(),
+        }
+    }
+
+    // Last modified time of every set file, keyed by set name
+    pub fn get_set_file_times() -> HashMap<String, SystemTime> {
+        let mut times = HashMap::new();
+        for (path, _) in Storage::get_set_files() {
+            let set_name = match path.file_stem() {
+                Some(stem) => stem.to_string_lossy().to_string(),
+                None => continue,
+            };
+            if let Ok(modified) = fs::metadata(&path).and_then(|meta| meta.modified()) {
+                times.insert(set_name, modified);
+            }
+        }
+        times
     }
 
     // Initialize files and folders to read/save data from/to
     pub fn set_up() {
-        if !fs::metadata(&DATA_DIR_PATH).is_ok() {
+        if fs::metadata(DATA_DIR_PATH).is_err() {
             fs::create_dir(DATA_DIR_PATH).expect("Failed to Create Data Folder");
         }
-        if !fs::metadata(&INVENTORY_DIR_PATH).is_ok() {
+        if fs::metadata(INVENTORY_DIR_PATH).is_err() {
             fs::create_dir(INVENTORY_DIR_PATH).expect("Failed to Create Inventory Folder");
         }
-        if !fs::metadata(&INVENTORY_FILE_PATH).is_ok() {
+        if fs::metadata(INVENTORY_FILE_PATH).is_err() {
             File::create(INVENTORY_FILE_PATH).expect("Failed to Create Inventory File");
         }
+        if fs::metadata(SETTINGS_FILE_PATH).is_err() {
+            Storage::update_settings(Settings::default());
+        }
+    }
+
+    // Falls back to the defaults if the settings file cannot be read
+    pub fn read_settings_file() -> Settings {
+        match fs::read_to_string(SETTINGS_FILE_PATH) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+            Err(_) => Settings::default(),
+        }
+    }
+
+    pub fn update_settings(settings: Settings) {
+        let data = serde_json::to_string_pretty(&settings).expect("Error parsing data to json");
+        let err_msg_write = format!("Failed to write to file [{}]", SETTINGS_FILE_PATH);
+        fs::write(SETTINGS_FILE_PATH, data).expect(&err_msg_write);
     }
 
     pub fn read_data() -> Vec<StudySet> {
-        if !fs::metadata(&DATA_DIR_PATH).is_ok() {
+        Storage::read_all_sets().0
+    }
+
+    // Every set, with the last good copy of those whose file cannot be read, and the names of
+    // the sets that could not be read
+    fn read_all_sets() -> (Vec<StudySet>, Vec<String>) {
+        if fs::metadata(DATA_DIR_PATH).is_err() {
             fs::create_dir(DATA_DIR_PATH).expect("Failed to Create Data Folder");
         }
         let mut sets: Vec<StudySet> = Vec::new();
-        match fs::read_dir(DATA_DIR_PATH) {
-            Ok(dir_entries) => {
-                for entry in dir_entries {
-                    if let Ok(entry) = entry {
-                        let set_data_file_path = entry.path();
-                        let set_data = fs::read_to_string(set_data_file_path)
-                            .expect("Failed to read set data file");
-                        let read_data_json = serde_json::from_str(&set_data);
-                        match read_data_json {
-                            Ok(data) => {
-                                sets.push(data);
-                            }
-                            Err(_) => {
-                                // println!("Error Reading Data");
-                            }
-                        }
-                    } else {
-                        // eprintln!("Error reading directory entry");
+        let mut unreadable: Vec<String> = Vec::new();
+        // set files in every folder, skipping media folders
+        for (set_data_file_path, _) in Storage::get_set_files() {
+            let set_name = match set_data_file_path.file_stem() {
+                Some(stem) => stem.to_string_lossy().to_string(),
+                None => continue,
+            };
+            match Storage::parse_set_file(&set_data_file_path) {
+                Ok(set) => {
+                    Storage::remember_good_set(&set);
+                    sets.push(set);
+                }
+                Err(_) => {
+                    if let Some(set) = Storage::get_good_set(&set_name) {
+                        sets.push(set);
                     }
+                    unreadable.push(set_name);
                 }
             }
-            Err(_err) => (),
         }
-        sets.sort_by(|a, b| a.id.cmp(&b.id));
-        sets
+        sets.sort_by_key(|set| set.id);
+        (sets, unreadable)
     }
 
-    pub fn inventory_check() {
-        let study_sets = Storage::read_data();
+    // Rebuilds the inventory from the set files, returning the names of those that could not be
+    // read. Sets read before are kept as they were last read
+    pub fn inventory_check() -> Vec<String> {
+        let (study_sets, unreadable) = Storage::read_all_sets();
+        let set_folders: BTreeMap<String, String> = Storage::get_set_files()
+            .into_iter()
+            .filter_map(|(path, folder)| {
+                let set_name = path.file_stem()?.to_string_lossy().to_string();
+                Some((set_name, folder))
+            })
+            .collect();
+        Storage::remember_folders(set_folders.clone());
         let mut items = Vec::new();
-        let mut count = 0;
-        for set in study_sets {
-            if set.get_id() != count {
+        for (count, set) in study_sets.into_iter().enumerate() {
+            // a file that cannot be read is left for whoever is writing it
+            if set.get_id() != count && !unreadable.contains(&set.get_set_name()) {
                 let mut cloned_set = set.clone();
                 cloned_set.set_id(count);
                 Storage::update_set_file(cloned_set);
@@ -503,28 +1536,59 @@ impl Storage {
                 set.get_set_name(),
                 set.get_all_tags(),
                 set.get_num_of_cards(),
+                set.get_created_at(),
+                set_folders
+                    .get(&set.get_set_name())
+                    .cloned()
+                    .unwrap_or_default(),
             );
             items.push(item);
-            count += 1;
         }
         let new_catologue = Catalogue::new(items);
         Storage::update_inventory(new_catologue);
+        unreadable
     }
 
-    pub fn read_set_file(file_name: String) -> StudySet {
-        let set_data_path = format!("{}/{}.json", DATA_DIR_PATH, file_name);
-        let set_data = fs::read_to_string(set_data_path).expect("Failed to read set data file");
-        let read_data: StudySet = serde_json::from_str(&set_data).expect("Error parsing set file");
-        read_data
+    // Falls back to the last good copy of the set if its file cannot be read
+    pub fn read_set_file(file_name: String) -> Result<StudySet, String> {
+        match Storage::parse_set_file(&Storage::get_set_path(file_name.clone())) {
+            Ok(set) => {
+                Storage::remember_good_set(&set);
+                Ok(set)
+            }
+            Err(err) => Storage::get_good_set(&file_name).ok_or(err),
+        }
+    }
+
+    fn parse_set_file(set_data_path: &Path) -> Result<StudySet, String> {
+        let set_data = fs::read_to_string(set_data_path)
+            .map_err(|_| format!("Failed to read set data file {}", set_data_path.display()))?;
+        serde_json::from_str(&set_data)
+            .map_err(|_| format!("Error parsing set file {}", set_data_path.display()))
+    }
+
+    fn remember_good_set(set: &StudySet) {
+        if let Ok(mut good_sets) = GOOD_SETS.lock() {
+            good_sets.insert(set.get_set_name(), set.clone());
+        }
+    }
+
+    fn get_good_set(set_name: &str) -> Option<StudySet> {
+        GOOD_SETS
+            .lock()
+            .ok()
+            .and_then(|good_sets| good_sets.get(set_name).cloned())
     }
 
     pub fn read_inventory_file() -> Catalogue {
         let data = fs::read_to_string(INVENTORY_FILE_PATH).expect("Failed to read inventory file");
         let catalogue: Catalogue = serde_json::from_str(&data).expect("Error parsing file");
+        Storage::remember_inventory(&catalogue);
         catalogue
     }
 
     pub fn update_inventory(inventory: Catalogue) {
+        Storage::remember_inventory(&inventory);
         let data = serde_json::to_string_pretty(&inventory).expect("Error parsing data to json");
         let err_msg_open = format!("Failed to open file [{}]", INVENTORY_FILE_PATH);
         let err_msg_write = format!("Failed to write to file [{}]", INVENTORY_FILE_PATH);
@@ -536,3 +1600,133 @@ impl Storage {
         file.write_all(data.as_bytes()).expect(&err_msg_write);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_session(order: Vec<usize>, position: usize, num_of_cards: usize) -> Session {
+        Session::new(
+            StudyMode::Test,
+            position,
+            order,
+            vec![String::new(); num_of_cards],
+            vec![String::new(); num_of_cards],
+        )
+    }
+
+    #[test]
+    fn session_fits_set_it_was_saved_for() {
+        assert!(make_session(vec![2, 0, 1], 1, 3).fits_set(3));
+    }
+
+    #[test]
+    fn session_does_not_fit_set_with_other_number_of_cards() {
+        let session = make_session(vec![0, 1, 2], 0, 3);
+        // a card was added
+        assert!(!session.fits_set(4));
+        // a card was deleted
+        assert!(!session.fits_set(2));
+    }
+
+    #[test]
+    fn session_does_not_fit_past_its_order() {
+        assert!(!make_session(vec![0, 1], 2, 2).fits_set(2));
+        assert!(!make_session(vec![0, 3], 0, 2).fits_set(2));
+    }
+
+    fn make_set(set_name: &str, words: &[&str]) -> StudySet {
+        let mut study_set = StudySet::new(0, String::from(set_name));
+        for (card_id, word) in words.iter().enumerate() {
+            study_set.add_card(Card::new(
+                card_id,
+                word.to_string(),
+                String::new(),
+                String::new(),
+            ));
+        }
+        study_set
+    }
+
+    fn get_words(study_set: &StudySet) -> Vec<String> {
+        (0..study_set.get_num_of_cards())
+            .map(|card_id| study_set.get_card(card_id).get_word())
+            .collect()
+    }
+
+    #[test]
+    fn moving_cards_takes_them_out_of_their_set() {
+        let (changed_sets, prev_sets) = Catalogue::plan_transfer(
+            make_set("Phone Calls", &["電話", "留守", "番号"]),
+            make_set("Appointments", &["予約"]),
+            &[0, 2],
+            false,
+            &mut Vec::new(),
+        );
+        assert_eq!(get_words(&changed_sets[0]), vec!["予約", "電話", "番号"]);
+        assert_eq!(changed_sets[0].get_card(2).get_id(), 2);
+        assert_eq!(get_words(&changed_sets[1]), vec!["留守"]);
+        // both sets are put back if the media cannot be copied
+        assert_eq!(get_words(&prev_sets[0]), vec!["予約"]);
+        assert_eq!(get_words(&prev_sets[1]), vec!["電話", "留守", "番号"]);
+    }
+
+    #[test]
+    fn copying_cards_leaves_their_set_alone() {
+        let (changed_sets, prev_sets) = Catalogue::plan_transfer(
+            make_set("Phone Calls", &["電話", "留守"]),
+            make_set("Appointments", &["予約"]),
+            &[1],
+            true,
+            &mut Vec::new(),
+        );
+        assert_eq!(changed_sets.len(), 1);
+        assert_eq!(get_words(&changed_sets[0]), vec!["予約", "留守"]);
+        assert_eq!(prev_sets.len(), 1);
+    }
+
+    // An empty folder of its own for each test, as the tests run at the same time
+    fn make_test_dir(test_name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("quiz_late_{}_{}", test_name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn replaces_every_set_file() {
+        let dir = make_test_dir("replaces_every_set_file");
+        fs::write(dir.join("a.json"), "old a").unwrap();
+        fs::write(dir.join("a.json.tmp"), "new a").unwrap();
+        fs::write(dir.join("b.json.tmp"), "new b").unwrap();
+        let written = vec![
+            (dir.join("a.json.tmp"), dir.join("a.json")),
+            (dir.join("b.json.tmp"), dir.join("b.json")),
+        ];
+        assert!(Storage::replace_with_temp_files(&written).is_ok());
+        assert_eq!(fs::read_to_string(dir.join("a.json")).unwrap(), "new a");
+        assert_eq!(fs::read_to_string(dir.join("b.json")).unwrap(), "new b");
+        assert!(!dir.join("a.json.bak").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn puts_set_files_back_when_one_cannot_be_replaced() {
+        let dir = make_test_dir("puts_set_files_back");
+        fs::write(dir.join("a.json"), "old a").unwrap();
+        fs::write(dir.join("a.json.tmp"), "new a").unwrap();
+        fs::write(dir.join("b.json"), "old b").unwrap();
+        // b.json.tmp is missing, so moving it over b.json fails
+        let written = vec![
+            (dir.join("a.json.tmp"), dir.join("a.json")),
+            (dir.join("b.json.tmp"), dir.join("b.json")),
+        ];
+        assert!(Storage::replace_with_temp_files(&written).is_err());
+        assert_eq!(fs::read_to_string(dir.join("a.json")).unwrap(), "old a");
+        assert_eq!(fs::read_to_string(dir.join("b.json")).unwrap(), "old b");
+        assert!(!dir.join("a.json.bak").exists());
+        assert!(!dir.join("b.json.bak").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,222 @@
+// Tags can be nested with "/", e.g. "LAJ/1/kanji" sits under "LAJ/1", which sits under "LAJ".
+// Filtering by a tag also picks up everything under it.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::{Catalogue, ListItem, Storage};
+
+pub const TAG_SEPARATOR: char = '/';
+
+// Colours a tag can be shown in, cycled through on the tags page
+pub const TAG_COLOR_NAMES: [&str; 8] = [
+    "Lime", "Aqua", "Fuchsia", "Yellow", "Orange", "Red", "Silver", "Purple",
+];
+
+// " LAJ / 1//kanji " becomes "LAJ/1/kanji"
+pub fn normalize_tag(tag: &str) -> String {
+    tag.split(TAG_SEPARATOR)
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join(&TAG_SEPARATOR.to_string())
+}
+
+// Tags typed into one box, separated by commas
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.split(',').map(normalize_tag) {
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+// Whether the tag is the ancestor itself or nested anywhere under it
+pub fn is_under(tag: &str, ancestor: &str) -> bool {
+    tag == ancestor
+        || tag
+            .strip_prefix(ancestor)
+            .is_some_and(|rest| rest.starts_with(TAG_SEPARATOR))
+}
+
+// Whether a set or card with the tag is picked by filtering or querying by `filter`, which
+// ignores case, so "laj" picks "LAJ/1/kanji"
+pub fn matches(tag: &str, filter: &str) -> bool {
+    is_under(&tag.to_lowercase(), &filter.to_lowercase())
+}
+
+// "LAJ/1/kanji" gives "LAJ" and "LAJ/1"
+pub fn get_ancestors(tag: &str) -> Vec<String> {
+    tag.match_indices(TAG_SEPARATOR)
+        .map(|(i, _)| tag[..i].to_string())
+        .collect()
+}
+
+pub fn get_depth(tag: &str) -> usize {
+    tag.matches(TAG_SEPARATOR).count()
+}
+
+// "LAJ/1/kanji" gives "kanji"
+pub fn get_leaf(tag: &str) -> String {
+    tag.rsplit(TAG_SEPARATOR).next().unwrap_or(tag).to_string()
+}
+
+// The tags with every ancestor added, each parent listed just before its children
+pub fn with_ancestors(tags: Vec<String>) -> Vec<String> {
+    let mut res: Vec<String> = Vec::new();
+    for tag in tags {
+        for parent in get_ancestors(&tag).into_iter().chain([tag]) {
+            if !res.contains(&parent) {
+                res.push(parent);
+            }
+        }
+    }
+    res.sort_by(|a, b| a.split(TAG_SEPARATOR).cmp(b.split(TAG_SEPARATOR)));
+    res
+}
+
+// Renaming "LAJ/1" to "Genki" turns "LAJ/1/kanji" into "Genki/kanji"; tags not under it are kept
+pub fn rename_tag(tag: &str, from: &str, to: &str) -> String {
+    if is_under(tag, from) {
+        format!("{}{}", to, &tag[from.len()..])
+    } else {
+        tag.to_string()
+    }
+}
+
+// Whether any of the tags matches the filter, e.g. tags of a set or of a card
+pub fn has_tag(tags: &[String], filter: &str) -> bool {
+    tags.iter().any(|tag| matches(tag, filter))
+}
+
+// Every tag in use together with its parents, see with_ancestors
+pub fn get_all_tags(catalogue: &Catalogue) -> Vec<String> {
+    let mut res: Vec<String> = Vec::new();
+    for item in catalogue.get_inventory() {
+        for tag in item.get_all_tags() {
+            if !res.contains(&tag) {
+                res.push(tag);
+            }
+        }
+    }
+    with_ancestors(res)
+}
+
+pub fn get_items_by_tags(
+    catalogue: &Catalogue,
+    filters: &HashSet<String>,
+    is_match_any: bool,
+) -> Vec<ListItem> {
+    catalogue
+        .get_inventory()
+        .into_iter()
+        .filter(|item| {
+            let item_tags = item.get_all_tags();
+            if is_match_any {
+                filters.iter().any(|filter| has_tag(&item_tags, filter))
+            } else {
+                filters.iter().all(|filter| has_tag(&item_tags, filter))
+            }
+        })
+        .collect()
+}
+
+// Renames the tag and everything under it in every set, merging it into `to` if that
+// tag already exists. Returns the number of sets changed
+pub fn rename_tag_in_sets(catalogue: &mut Catalogue, from: &str, to: &str) -> usize {
+    change_tags(catalogue, from, |set_tags| {
+        set_tags
+            .iter()
+            .map(|tag| rename_tag(tag, from, to))
+            .collect()
+    })
+}
+
+// Removes the tag and everything under it from every set
+pub fn delete_tag_from_sets(catalogue: &mut Catalogue, tag: &str) -> usize {
+    change_tags(catalogue, tag, |set_tags| {
+        set_tags
+            .into_iter()
+            .filter(|set_tag| !is_under(set_tag, tag))
+            .collect()
+    })
+}
+
+fn change_tags(
+    catalogue: &mut Catalogue,
+    tag: &str,
+    change: impl Fn(Vec<String>) -> Vec<String>,
+) -> usize {
+    let ids: Vec<usize> = catalogue
+        .get_inventory()
+        .iter()
+        // only the tag as spelled is renamed or deleted
+        .filter(|item| {
+            item.get_all_tags()
+                .iter()
+                .any(|item_tag| is_under(item_tag, tag))
+        })
+        .map(|item| item.get_id())
+        .collect();
+    for id in &ids {
+        let item = catalogue.get_item_by_id(*id);
+        let mut study_set = match Storage::read_set_file(item[0].get_name()) {
+            Ok(study_set) => study_set,
+            Err(_) => continue,
+        };
+        study_set.set_tags(change(study_set.get_all_tags()));
+        catalogue.update_set(*id, study_set);
+    }
+    ids.len()
+}
+
+// A colour chosen for a tag on the tags page, kept in the settings file.
+// Tags without one use the colour of the closest ancestor that has one.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct TagColor {
+    tag: String,
+    color: String,
+}
+
+impl TagColor {
+    pub fn new(tag: String, color: String) -> TagColor {
+        TagColor { tag, color }
+    }
+
+    pub fn get_tag(&self) -> String {
+        self.tag.clone()
+    }
+
+    pub fn get_color(&self) -> String {
+        self.color.clone()
+    }
+}
+
+// The name of the colour the tag is shown in, if it or an ancestor has one
+pub fn find_color_name(tag: &str, tag_colors: &[TagColor]) -> Option<String> {
+    let mut tag = tag.to_string();
+    loop {
+        if let Some(tag_color) = tag_colors.iter().find(|tag_color| tag_color.tag == tag) {
+            return Some(tag_color.get_color());
+        }
+        match tag.rfind(TAG_SEPARATOR) {
+            Some(i) => tag.truncate(i),
+            None => return None,
+        }
+    }
+}
+
+// The colour after the given one, or no colour after the last
+pub fn next_color_name(color: Option<String>) -> Option<String> {
+    match color {
+        None => Some(TAG_COLOR_NAMES[0].to_string()),
+        Some(color) => TAG_COLOR_NAMES
+            .iter()
+            .position(|name| *name == color)
+            .and_then(|i| TAG_COLOR_NAMES.get(i + 1))
+            .map(|name| name.to_string()),
+    }
+}
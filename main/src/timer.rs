@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+// Seconds allowed for each card and for the whole test, none meaning no limit
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TimeLimits {
+    per_card: Option<u64>,
+    total: Option<u64>,
+}
+
+impl TimeLimits {
+    pub fn new(per_card: Option<u64>, total: Option<u64>) -> TimeLimits {
+        TimeLimits { per_card, total }
+    }
+
+    pub fn get_per_card(&self) -> Option<u64> {
+        self.per_card
+    }
+
+    pub fn get_total(&self) -> Option<u64> {
+        self.total
+    }
+
+    pub fn is_timed(&self) -> bool {
+        self.per_card.is_some() || self.total.is_some()
+    }
+
+    pub fn get_card_time_left(&self, card_time: f64) -> Option<f64> {
+        self.per_card
+            .map(|limit| (limit as f64 - card_time).max(0.0))
+    }
+
+    pub fn get_total_time_left(&self, card_times: &[f64]) -> Option<f64> {
+        let elapsed: f64 = card_times.iter().sum();
+        self.total.map(|limit| (limit as f64 - elapsed).max(0.0))
+    }
+}
+
+// Reads "90" or "1:30" as 90 seconds, anything else (or zero) as no limit
+pub fn parse_seconds(input: &str) -> Option<u64> {
+    let input = input.trim();
+    let seconds = match input.split_once(':') {
+        Some((minutes, seconds)) => {
+            let minutes: u64 = minutes.trim().parse().ok()?;
+            let seconds: u64 = seconds.trim().parse().ok()?;
+            minutes * 60 + seconds
+        }
+        None => input.parse().ok()?,
+    };
+    if seconds == 0 {
+        None
+    } else {
+        Some(seconds)
+    }
+}
+
+// Countdowns are shown as "m:ss", rounded up so "0:00" only shows once time is up
+pub fn format_countdown(seconds: f64) -> String {
+    let seconds = seconds.ceil() as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+pub fn format_limit(limit: Option<u64>) -> String {
+    match limit {
+        Some(seconds) => format_countdown(seconds as f64),
+        None => String::from("No Limit"),
+    }
+}
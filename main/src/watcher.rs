@@ -0,0 +1,150 @@
+use druid::{ExtEventSink, Selector, Target};
+use std::collections::{BTreeMap, HashMap};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::storage::Storage;
+
+// Set files that changed on disk since the last poll, by set name
+#[derive(Clone, Debug, Default)]
+pub struct DataChanges {
+    modified: Vec<String>,
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+impl DataChanges {
+    pub fn is_empty(&self) -> bool {
+        self.modified.is_empty() && self.added.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn get_all_names(&self) -> Vec<String> {
+        let mut res = self.modified.clone();
+        res.extend(self.added.clone());
+        res.extend(self.removed.clone());
+        res
+    }
+}
+
+// Polls the data folder for set files edited outside the app
+#[derive(Clone, Debug)]
+pub struct DataWatcher {
+    known_files: HashMap<String, SystemTime>,
+}
+
+impl DataWatcher {
+    pub fn new() -> DataWatcher {
+        DataWatcher {
+            known_files: Storage::get_set_file_times(),
+        }
+    }
+
+    pub fn poll(&mut self) -> DataChanges {
+        let current_files = Storage::get_set_file_times();
+        // read after the files, so a write in between is already recorded or not yet seen
+        let changes = get_changes(
+            &self.known_files,
+            &current_files,
+            &Storage::get_own_writes(),
+        );
+        self.known_files = current_files;
+        changes
+    }
+}
+
+// Compares the set files with those seen last time. A file is left out while it is as the app
+// last wrote or removed it, however long ago that was, so a save made while polling is not
+// taken for an outside one
+fn get_changes(
+    known_files: &HashMap<String, SystemTime>,
+    current_files: &HashMap<String, SystemTime>,
+    own_writes: &BTreeMap<String, Option<SystemTime>>,
+) -> DataChanges {
+    let mut changes = DataChanges::default();
+    for (set_name, modified) in current_files {
+        if own_writes.get(set_name) == Some(&Some(*modified)) {
+            continue;
+        }
+        match known_files.get(set_name) {
+            Some(prev_modified) => {
+                if prev_modified != modified {
+                    changes.modified.push(set_name.clone());
+                }
+            }
+            None => changes.added.push(set_name.clone()),
+        }
+    }
+    for set_name in known_files.keys() {
+        if !current_files.contains_key(set_name) && own_writes.get(set_name) != Some(&None) {
+            changes.removed.push(set_name.clone());
+        }
+    }
+    changes
+}
+
+pub const DATA_CHANGED: Selector<Vec<String>> = Selector::new("quiz_late.data-changed");
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+// Polls on a background thread and notifies the app with the changed set names
+pub fn watch_data_dir(event_sink: ExtEventSink) {
+    thread::spawn(move || {
+        let mut watcher = DataWatcher::new();
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+            let changes = watcher.poll();
+            if changes.is_empty() {
+                continue;
+            }
+            let sent =
+                event_sink.submit_command(DATA_CHANGED, changes.get_all_names(), Target::Auto);
+            if sent.is_err() {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn files(times: &[(&str, u64)]) -> HashMap<String, SystemTime> {
+        times
+            .iter()
+            .map(|(set_name, secs)| (set_name.to_string(), at(*secs)))
+            .collect()
+    }
+
+    #[test]
+    fn skips_files_as_the_app_left_them() {
+        let known = files(&[("Verbs", 1), ("Kanji", 1), ("Old", 1)]);
+        let current = files(&[("Verbs", 2), ("Kanji", 3), ("New", 2)]);
+        let own_writes = BTreeMap::from([
+            (String::from("Verbs"), Some(at(2))),
+            // changed again outside after the app saved it
+            (String::from("Kanji"), Some(at(2))),
+            (String::from("Old"), None),
+        ]);
+        let changes = get_changes(&known, &current, &own_writes);
+        assert_eq!(changes.modified, vec!["Kanji"]);
+        assert_eq!(changes.added, vec!["New"]);
+        assert!(changes.removed.is_empty());
+    }
+
+    #[test]
+    fn reports_outside_changes_to_files_the_app_wrote_before() {
+        let known = files(&[("Verbs", 2), ("Kanji", 2)]);
+        let current = files(&[("Verbs", 2)]);
+        let own_writes = BTreeMap::from([
+            (String::from("Verbs"), Some(at(2))),
+            (String::from("Kanji"), Some(at(2))),
+        ]);
+        let changes = get_changes(&known, &current, &own_writes);
+        assert!(changes.modified.is_empty());
+        assert_eq!(changes.removed, vec!["Kanji"]);
+    }
+}